@@ -0,0 +1,16 @@
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    use std::fs::File;
+
+    // expects verifier.key as written by export_test_keys
+    let raw_vk = std::fs::read("verifier.key").unwrap();
+    let file_verifier = File::create("RLNVerifier.sol").unwrap();
+    rln::solidity::generate_verifier(raw_vk.as_slice(), file_verifier).unwrap();
+    let file_registry = File::create("RLNRegistry.sol").unwrap();
+    rln::solidity::generate_registry(raw_vk.as_slice(), file_registry).unwrap();
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    panic!("should not be run in wasm");
+}
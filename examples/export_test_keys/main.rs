@@ -17,6 +17,7 @@ mod test_keys {
         use rand::{SeedableRng, XorShiftRng};
         use rln::circuit::poseidon::PoseidonCircuit;
         use rln::circuit::rln::{RLNCircuit, RLNInputs};
+        use rln::parameters::{write_parameters, CircuitKind, ParametersHeader};
         use rln::poseidon::PoseidonParams;
         use sapling_crypto::bellman::groth16::generate_random_parameters;
         use std::fs::File;
@@ -27,12 +28,14 @@ mod test_keys {
         let circuit = RLNCircuit::<E> {
             inputs: RLNInputs::<E>::empty(merkle_depth),
             hasher: hasher.clone(),
+            degree: 1,
         };
         let parameters = generate_random_parameters(circuit, &mut rng).unwrap();
         let mut file_vk = File::create("verifier.key").unwrap();
         let vk = parameters.vk.clone();
         vk.write(&mut file_vk).unwrap();
         let mut file_paramaters = File::create("parameters.key").unwrap();
-        parameters.write(&mut file_paramaters).unwrap();
+        let header = ParametersHeader::new(CircuitKind::RLN, merkle_depth, 1, &poseidon_params);
+        write_parameters(&header, &parameters, &mut file_paramaters).unwrap();
     }
 }
@@ -0,0 +1,119 @@
+// phase 2 ceremony of RLN parameters
+//
+// phase2_ceremony new <merkle_depth> <powers_of_tau> <ceremony>
+// phase2_ceremony contribute <ceremony> <next_ceremony>
+// phase2_ceremony verify <merkle_depth> <powers_of_tau> <ceremony>
+// phase2_ceremony finalize <merkle_depth> <powers_of_tau> <ceremony> <parameters.key> <verifier.key>
+// phase2_ceremony insecure-powers-of-tau <merkle_depth> <powers_of_tau>
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 2 {
+        panic!("expected a command, see examples/phase2_ceremony/main.rs");
+    }
+    match args[1].as_str() {
+        "new" => ceremony::new(args[2].parse().unwrap(), &args[3], &args[4]),
+        "contribute" => ceremony::contribute(&args[2], &args[3]),
+        "verify" => ceremony::verify(args[2].parse().unwrap(), &args[3], &args[4]),
+        "finalize" => ceremony::finalize(
+            args[2].parse().unwrap(),
+            &args[3],
+            &args[4],
+            &args[5],
+            &args[6],
+        ),
+        "insecure-powers-of-tau" => {
+            ceremony::insecure_powers_of_tau(args[2].parse().unwrap(), &args[3])
+        }
+        command => panic!("unknown command {}", command),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    panic!("should not be run in wasm");
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod ceremony {
+    use rln::circuit::rln::RLNCircuit;
+    use rln::mpc::{domain_size, rln_circuit, MPCParameters, PowersOfTau};
+    use rln::parameters::{write_parameters, CircuitKind, ParametersHeader};
+    use rln::poseidon::PoseidonParams;
+    use sapling_crypto::bellman::pairing::bn256::Bn256;
+    use std::fs::File;
+    use std::io::{BufReader, BufWriter};
+
+    fn poseidon_params() -> PoseidonParams<Bn256> {
+        PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None)
+    }
+
+    fn circuit(merkle_depth: usize) -> RLNCircuit<Bn256> {
+        rln_circuit(merkle_depth, 1, poseidon_params())
+    }
+
+    fn print_hash(hash: &[u8; 64]) {
+        println!("{}", hex::encode(&hash[..]));
+    }
+
+    pub fn new(merkle_depth: usize, powers_of_tau: &str, ceremony: &str) {
+        let reader = BufReader::new(File::open(powers_of_tau).unwrap());
+        let params = MPCParameters::new(circuit(merkle_depth), reader).unwrap();
+        let writer = BufWriter::new(File::create(ceremony).unwrap());
+        params.write(writer).unwrap();
+    }
+
+    pub fn contribute(ceremony: &str, next_ceremony: &str) {
+        let reader = BufReader::new(File::open(ceremony).unwrap());
+        let mut params = MPCParameters::<Bn256>::read(reader, true).unwrap();
+        let mut rng = rand::OsRng::new().unwrap();
+        let hash = params.contribute(&mut rng).unwrap();
+        let writer = BufWriter::new(File::create(next_ceremony).unwrap());
+        params.write(writer).unwrap();
+        println!("contribution hash:");
+        print_hash(&hash);
+    }
+
+    pub fn verify(merkle_depth: usize, powers_of_tau: &str, ceremony: &str) {
+        let reader = BufReader::new(File::open(ceremony).unwrap());
+        let params = MPCParameters::<Bn256>::read(reader, true).unwrap();
+        let reader = BufReader::new(File::open(powers_of_tau).unwrap());
+        let hashes = params.verify(circuit(merkle_depth), reader).unwrap();
+        println!("verified contribution hashes:");
+        for hash in hashes.iter() {
+            print_hash(hash);
+        }
+    }
+
+    // the transcript is verified before the parameters are written
+    pub fn finalize(
+        merkle_depth: usize,
+        powers_of_tau: &str,
+        ceremony: &str,
+        parameters: &str,
+        verifier_key: &str,
+    ) {
+        let reader = BufReader::new(File::open(ceremony).unwrap());
+        let params = MPCParameters::<Bn256>::read(reader, true).unwrap();
+        let reader = BufReader::new(File::open(powers_of_tau).unwrap());
+        params.verify(circuit(merkle_depth), reader).unwrap();
+        let params = params.finalize();
+        let header = ParametersHeader::new(CircuitKind::RLN, merkle_depth, 1, &poseidon_params());
+        let writer = BufWriter::new(File::create(parameters).unwrap());
+        write_parameters(&header, &params, writer).unwrap();
+        let writer = BufWriter::new(File::create(verifier_key).unwrap());
+        params.vk.write(writer).unwrap();
+    }
+
+    // toxic waste is known to this machine, for local testing of the ceremony only
+    pub fn insecure_powers_of_tau(merkle_depth: usize, powers_of_tau: &str) {
+        let m = domain_size(circuit(merkle_depth)).unwrap();
+        let mut rng = rand::OsRng::new().unwrap();
+        let writer = BufWriter::new(File::create(powers_of_tau).unwrap());
+        PowersOfTau::<Bn256>::generate_insecure(m, &mut rng)
+            .unwrap()
+            .write(writer)
+            .unwrap();
+    }
+}
@@ -0,0 +1,171 @@
+use bellman::groth16::{verify_proof, PreparedVerifyingKey, Proof, VerifyingKey};
+use bellman::multicore::Worker;
+use bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
+use bellman::pairing::{CurveAffine, CurveProjective, Engine};
+use rand::{thread_rng, Rand, Rng};
+
+// Batch verification of groth16 proofs
+//
+// Every proof is scaled with a random r_j and all of them are checked with
+// a single multi pairing
+//
+// prod_j e(r_j * A_j, B_j) * e(-sum_j r_j * alpha, beta)
+//     * e(-sum_j r_j * IC(x_j), gamma) * e(-sum_j r_j * C_j, delta) == 1
+//
+// If a batch fails it is split in halves until the offending proofs are found.
+// Batches are distributed over cores with `multicore` feature.
+
+/// verifies each proof against its public inputs
+/// * `pvk` is the prepared `vk`, it checks single proofs of a failed batch
+/// * returns the result of each proof in order
+pub fn verify_proofs<E: Engine>(
+    vk: &VerifyingKey<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    entries: &[(Proof<E>, Vec<E::Fr>)],
+) -> Vec<bool> {
+    let mut results = vec![false; entries.len()];
+    if entries.is_empty() {
+        return results;
+    }
+    let worker = Worker::new();
+    worker.scope(entries.len(), |scope, chunk| {
+        for (entries, results) in entries.chunks(chunk).zip(results.chunks_mut(chunk)) {
+            scope.spawn(move |_| {
+                let mut rng = thread_rng();
+                verify_chunk(vk, pvk, entries, results, &mut rng);
+            });
+        }
+    });
+    results
+}
+
+fn verify_chunk<E: Engine, R: Rng>(
+    vk: &VerifyingKey<E>,
+    pvk: &PreparedVerifyingKey<E>,
+    entries: &[(Proof<E>, Vec<E::Fr>)],
+    results: &mut [bool],
+    rng: &mut R,
+) {
+    if entries.is_empty() {
+        return;
+    }
+    if entries.len() == 1 {
+        results[0] = verify_proof(pvk, &entries[0].0, &entries[0].1).unwrap_or(false);
+        return;
+    }
+    if batch_check(vk, entries, rng) {
+        for result in results.iter_mut() {
+            *result = true;
+        }
+        return;
+    }
+    // find the offending proofs
+    let mid = entries.len() / 2;
+    let (entries_0, entries_1) = entries.split_at(mid);
+    let (results_0, results_1) = results.split_at_mut(mid);
+    verify_chunk(vk, pvk, entries_0, results_0, rng);
+    verify_chunk(vk, pvk, entries_1, results_1, rng);
+}
+
+fn batch_check<E: Engine, R: Rng>(
+    vk: &VerifyingKey<E>,
+    entries: &[(Proof<E>, Vec<E::Fr>)],
+    rng: &mut R,
+) -> bool {
+    let mut r_sum = E::Fr::zero();
+    let mut acc_ic = E::G1::zero();
+    let mut acc_c = E::G1::zero();
+    let mut g1: Vec<<E::G1Affine as CurveAffine>::Prepared> = Vec::with_capacity(entries.len() + 3);
+    let mut g2: Vec<<E::G2Affine as CurveAffine>::Prepared> = Vec::with_capacity(entries.len() + 3);
+
+    for (proof, public_inputs) in entries.iter() {
+        if public_inputs.len() + 1 != vk.ic.len() {
+            return false;
+        }
+        let r = E::Fr::rand(rng);
+
+        // IC(x) = ic_0 + sum_i x_i * ic_i
+        let mut ic = vk.ic[0].into_projective();
+        for (x, b) in public_inputs.iter().zip(vk.ic.iter().skip(1)) {
+            ic.add_assign(&b.mul(x.into_repr()));
+        }
+        ic.mul_assign(r.into_repr());
+        acc_ic.add_assign(&ic);
+
+        acc_c.add_assign(&proof.c.mul(r.into_repr()));
+        r_sum.add_assign(&r);
+
+        g1.push(proof.a.mul(r.into_repr()).into_affine().prepare());
+        g2.push(proof.b.prepare());
+    }
+
+    let mut acc_alpha = vk.alpha_g1.mul(r_sum.into_repr());
+    acc_alpha.negate();
+    acc_ic.negate();
+    acc_c.negate();
+
+    g1.push(acc_alpha.into_affine().prepare());
+    g2.push(vk.beta_g2.prepare());
+    g1.push(acc_ic.into_affine().prepare());
+    g2.push(vk.gamma_g2.prepare());
+    g1.push(acc_c.into_affine().prepare());
+    g2.push(vk.delta_g2.prepare());
+
+    let pairs: Vec<(
+        &<E::G1Affine as CurveAffine>::Prepared,
+        &<E::G2Affine as CurveAffine>::Prepared,
+    )> = g1.iter().zip(g2.iter()).collect();
+
+    match E::final_exponentiation(&E::miller_loop(pairs.iter())) {
+        Some(result) => result == E::Fqk::one(),
+        None => false,
+    }
+}
+
+#[test]
+fn test_batch_verification() {
+    use crate::circuit::bench;
+    use crate::circuit::poseidon::PoseidonCircuit;
+    use crate::circuit::rln::{RLNCircuit, RLNInputs};
+    use crate::poseidon::PoseidonParams;
+    use bellman::groth16::{
+        create_random_proof, generate_random_parameters, prepare_verifying_key,
+    };
+    use bellman::pairing::bn256::{Bn256, Fr};
+
+    let merkle_depth = 3;
+    let poseidon_params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+    let rln_test = bench::RLNTest::<Bn256>::new(merkle_depth, Some(poseidon_params.clone()));
+    let mut rng = thread_rng();
+    let circuit = RLNCircuit::<Bn256> {
+        inputs: RLNInputs::<Bn256>::empty(merkle_depth),
+        hasher: PoseidonCircuit::new(poseidon_params.clone()),
+        degree: 1,
+    };
+    let params = generate_random_parameters(circuit, &mut rng).unwrap();
+    let pvk = prepare_verifying_key(&params.vk);
+
+    let inputs = rln_test.valid_inputs();
+    let mut entries = Vec::new();
+    for _ in 0..5 {
+        let circuit = RLNCircuit::<Bn256> {
+            inputs: inputs.clone(),
+            hasher: PoseidonCircuit::new(poseidon_params.clone()),
+            degree: 1,
+        };
+        let proof = create_random_proof(circuit, &params, &mut rng).unwrap();
+        entries.push((proof, inputs.public_inputs()));
+    }
+    assert!(verify_proofs(&params.vk, &pvk, &entries)
+        .into_iter()
+        .all(|r| r));
+
+    // tamper with public inputs of two proofs
+    entries[1].1[3] = Fr::rand(&mut rng);
+    entries[4].1[0] = Fr::rand(&mut rng);
+    assert_eq!(
+        verify_proofs(&params.vk, &pvk, &entries),
+        vec![true, false, true, true, false]
+    );
+    assert!(verify_proofs(&params.vk, &pvk, &[]).is_empty());
+}
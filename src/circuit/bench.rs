@@ -1,4 +1,5 @@
 use crate::circuit::rln::{RLNCircuit, RLNInputs};
+use crate::error::RLNError;
 use crate::merkle::MerkleTree;
 use crate::poseidon::{Poseidon as PoseidonHasher, PoseidonParams};
 use crate::{circuit::poseidon::PoseidonCircuit, public::RLNSignal};
@@ -15,6 +16,7 @@ use std::time::{Duration, Instant};
 use std::{error::Error, hash::Hash};
 
 use crate::public::RLN;
+use crate::sss::{evaluate, polynomial_coefficients};
 
 pub struct ProverBenchResult {
     pub prover_key_size: usize,
@@ -70,8 +72,16 @@ where
     }
 
     pub fn new(merkle_depth: usize, poseidon_params: Option<PoseidonParams<E>>) -> RLNTest<E> {
+        Self::new_with_degree(merkle_depth, 1, poseidon_params)
+    }
+
+    pub fn new_with_degree(
+        merkle_depth: usize,
+        share_degree: usize,
+        poseidon_params: Option<PoseidonParams<E>>,
+    ) -> RLNTest<E> {
         let mut rln_test = RLNTest {
-            rln: RLN::new(merkle_depth, poseidon_params),
+            rln: RLN::new_with_degree(merkle_depth, share_degree, poseidon_params).unwrap(),
             merkle_depth,
         };
         rln_test.insert_public_key();
@@ -116,17 +126,14 @@ where
         // evaluation point is the signal_hash
         let share_x = signal_hash.clone();
 
-        // calculate current line equation
-        let a_0 = secret_key.clone();
-        let a_1: E::Fr = hasher.hash(vec![a_0, epoch]);
+        // calculate current secret polynomial
+        let coeffs = polynomial_coefficients(&hasher, secret_key, epoch, self.rln.share_degree());
 
-        // evaluate line equation
-        let mut share_y = a_1.clone();
-        share_y.mul_assign(&share_x);
-        share_y.add_assign(&a_0);
+        // evaluate the polynomial
+        let share_y = evaluate::<E>(&coeffs, share_x);
 
         // calculate nullfier
-        let nullifier = hasher.hash(vec![a_1]);
+        let nullifier = hasher.hash(vec![coeffs[1]]);
 
         // compose the circuit
 
@@ -149,6 +156,7 @@ where
         let circuit = RLNCircuit::<E> {
             inputs: inputs.clone(),
             hasher: hasher.clone(),
+            degree: self.rln.share_degree(),
         };
 
         let mut cs = TestConstraintSystem::<E>::new();
@@ -225,7 +233,7 @@ where
         }
     }
 
-    pub fn export_circuit_parameters<W: Write>(&self, w: W) -> io::Result<()> {
+    pub fn export_circuit_parameters<W: Write>(&self, w: W) -> Result<(), RLNError> {
         self.rln.export_circuit_parameters(w)
     }
 }
@@ -0,0 +1,418 @@
+use crate::circuit::merkle::allocate_merkle_root;
+use crate::circuit::poseidon::PoseidonCircuit;
+use crate::circuit::range::allocate_bounded;
+use sapling_crypto::bellman::pairing::Engine;
+use sapling_crypto::bellman::{Circuit, ConstraintSystem, SynthesisError};
+use sapling_crypto::circuit::{num, Assignment};
+
+// Shielded transfer of two input notes to two output notes
+//
+// Note commitment is H(H(value, owner_pk), blinding) where owner_pk is H(owner_sk),
+// spending a note reveals the nullifier H(commitment, owner_sk).
+// Inputs with zero value are not checked against the note tree
+// so that a transaction can spend less than two notes.
+// Values are range checked to VALUE_BITS and
+// sum(input values) + value_delta == sum(output values),
+// a positive value_delta is a deposit into the pool, a negative one a withdrawal.
+
+// bit length of note values
+pub const VALUE_BITS: usize = 64;
+
+// number of spent and created notes
+pub const JOIN_SPLIT_INPUTS: usize = 2;
+pub const JOIN_SPLIT_OUTPUTS: usize = 2;
+
+#[derive(Clone)]
+pub struct InputNote<E>
+where
+    E: Engine,
+{
+    pub value: Option<E::Fr>,
+    pub owner_sk: Option<E::Fr>,
+    pub blinding: Option<E::Fr>,
+
+    // authentication path of the note commitment
+    pub auth_path: Vec<Option<(E::Fr, bool)>>,
+}
+
+#[derive(Clone)]
+pub struct OutputNote<E>
+where
+    E: Engine,
+{
+    pub value: Option<E::Fr>,
+    pub owner_pk: Option<E::Fr>,
+    pub blinding: Option<E::Fr>,
+}
+
+#[derive(Clone)]
+pub struct JoinSplitInputs<E>
+where
+    E: Engine,
+{
+    // Public inputs
+
+    // root is the current state of the note tree
+    pub root: Option<E::Fr>,
+
+    // value added to the pool, negated if withdrawn
+    pub value_delta: Option<E::Fr>,
+
+    // nullifiers of input notes
+    pub nullifiers: Vec<Option<E::Fr>>,
+
+    // commitments of output notes
+    pub commitments: Vec<Option<E::Fr>>,
+
+    // Private inputs
+    pub input_notes: Vec<InputNote<E>>,
+    pub output_notes: Vec<OutputNote<E>>,
+}
+
+impl<E> JoinSplitInputs<E>
+where
+    E: Engine,
+{
+    pub fn public_inputs(&self) -> Vec<E::Fr> {
+        let mut inputs = vec![self.root.unwrap(), self.value_delta.unwrap()];
+        inputs.extend(self.nullifiers.iter().map(|n| n.unwrap()));
+        inputs.extend(self.commitments.iter().map(|c| c.unwrap()));
+        inputs
+    }
+
+    pub fn merkle_depth(&self) -> usize {
+        self.input_notes[0].auth_path.len()
+    }
+
+    pub fn empty(merkle_depth: usize) -> JoinSplitInputs<E> {
+        JoinSplitInputs::<E> {
+            root: None,
+            value_delta: None,
+            nullifiers: vec![None; JOIN_SPLIT_INPUTS],
+            commitments: vec![None; JOIN_SPLIT_OUTPUTS],
+            input_notes: vec![
+                InputNote {
+                    value: None,
+                    owner_sk: None,
+                    blinding: None,
+                    auth_path: vec![None; merkle_depth],
+                };
+                JOIN_SPLIT_INPUTS
+            ],
+            output_notes: vec![
+                OutputNote {
+                    value: None,
+                    owner_pk: None,
+                    blinding: None,
+                };
+                JOIN_SPLIT_OUTPUTS
+            ],
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct JoinSplitCircuit<E>
+where
+    E: Engine,
+{
+    pub inputs: JoinSplitInputs<E>,
+    pub hasher: PoseidonCircuit<E>,
+}
+
+// H(H(value, owner_pk), blinding)
+fn allocate_note_commitment<E, CS>(
+    mut cs: CS,
+    hasher: &PoseidonCircuit<E>,
+    value: &num::AllocatedNum<E>,
+    owner_pk: num::AllocatedNum<E>,
+    blinding: Option<E::Fr>,
+) -> Result<num::AllocatedNum<E>, SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    let blinding = num::AllocatedNum::alloc(cs.namespace(|| "blinding"), || Ok(*blinding.get()?))?;
+    let value_owner = hasher.alloc(
+        cs.namespace(|| "value owner hash"),
+        vec![value.clone(), owner_pk],
+    )?;
+    hasher.alloc(cs.namespace(|| "commitment"), vec![value_owner, blinding])
+}
+
+impl<E> Circuit<E> for JoinSplitCircuit<E>
+where
+    E: Engine,
+{
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        assert_eq!(self.inputs.input_notes.len(), JOIN_SPLIT_INPUTS);
+        assert_eq!(self.inputs.output_notes.len(), JOIN_SPLIT_OUTPUTS);
+
+        let root = num::AllocatedNum::alloc(cs.namespace(|| "root"), || {
+            let value = self.inputs.root;
+            Ok(*value.get()?)
+        })?;
+        root.inputize(cs.namespace(|| "root is public"))?;
+
+        let value_delta = num::AllocatedNum::alloc(cs.namespace(|| "value delta"), || {
+            let value = self.inputs.value_delta;
+            Ok(*value.get()?)
+        })?;
+        value_delta.inputize(cs.namespace(|| "value delta is public"))?;
+
+        // 1. Part
+        // Spend constraints
+        // value * (root - merkle_proof(auth_path, commitment)) == 0
+        // nullifier == H(commitment, owner_sk)
+
+        let mut input_values = vec![];
+        for (i, note) in self.inputs.input_notes.iter().enumerate() {
+            let cs = &mut cs.namespace(|| format!("input {}", i));
+
+            let value = allocate_bounded(cs.namespace(|| "value"), note.value, VALUE_BITS)?;
+
+            let owner_sk = num::AllocatedNum::alloc(cs.namespace(|| "owner sk"), || {
+                let value = note.owner_sk;
+                Ok(*value.get()?)
+            })?;
+            let owner_pk = self
+                .hasher
+                .alloc(cs.namespace(|| "owner pk"), vec![owner_sk.clone()])?;
+
+            let commitment = allocate_note_commitment(
+                cs.namespace(|| "note commitment"),
+                &self.hasher,
+                &value,
+                owner_pk,
+                note.blinding,
+            )?;
+
+            let acc = allocate_merkle_root(
+                cs.namespace(|| "note membership"),
+                &self.hasher,
+                &commitment,
+                &note.auth_path,
+            )?;
+
+            cs.enforce(
+                || "enforce membership of non zero note",
+                |lc| lc + acc.get_variable() - root.get_variable(),
+                |lc| lc + value.get_variable(),
+                |lc| lc,
+            );
+
+            let nullifier_calculated = self.hasher.alloc(
+                cs.namespace(|| "calculated nullifier"),
+                vec![commitment, owner_sk],
+            )?;
+
+            let nullifier = num::AllocatedNum::alloc(cs.namespace(|| "nullifier"), || {
+                let value = self.inputs.nullifiers[i];
+                Ok(*value.get()?)
+            })?;
+            nullifier.inputize(cs.namespace(|| "nullifier is public"))?;
+
+            cs.enforce(
+                || "enforce nullifier",
+                |lc| lc + nullifier_calculated.get_variable(),
+                |lc| lc + CS::one(),
+                |lc| lc + nullifier.get_variable(),
+            );
+
+            input_values.push(value);
+        }
+
+        // 2. Part
+        // Output constraints
+        // commitment == H(H(value, owner_pk), blinding)
+
+        let mut output_values = vec![];
+        for (i, note) in self.inputs.output_notes.iter().enumerate() {
+            let cs = &mut cs.namespace(|| format!("output {}", i));
+
+            let value = allocate_bounded(cs.namespace(|| "value"), note.value, VALUE_BITS)?;
+
+            let owner_pk = num::AllocatedNum::alloc(cs.namespace(|| "owner pk"), || {
+                let value = note.owner_pk;
+                Ok(*value.get()?)
+            })?;
+
+            let commitment_calculated = allocate_note_commitment(
+                cs.namespace(|| "note commitment"),
+                &self.hasher,
+                &value,
+                owner_pk,
+                note.blinding,
+            )?;
+
+            let commitment = num::AllocatedNum::alloc(cs.namespace(|| "commitment"), || {
+                let value = self.inputs.commitments[i];
+                Ok(*value.get()?)
+            })?;
+            commitment.inputize(cs.namespace(|| "commitment is public"))?;
+
+            cs.enforce(
+                || "enforce commitment",
+                |lc| lc + commitment_calculated.get_variable(),
+                |lc| lc + CS::one(),
+                |lc| lc + commitment.get_variable(),
+            );
+
+            output_values.push(value);
+        }
+
+        // 3. Part
+        // Value conservation
+        // sum(input values) + value_delta == sum(output values)
+
+        cs.enforce(
+            || "enforce value conservation",
+            |lc| {
+                let lc = input_values
+                    .iter()
+                    .fold(lc + value_delta.get_variable(), |lc, v| {
+                        lc + v.get_variable()
+                    });
+                output_values.iter().fold(lc, |lc, v| lc - v.get_variable())
+            },
+            |lc| lc + CS::one(),
+            |lc| lc,
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{InputNote, JoinSplitCircuit, JoinSplitInputs, OutputNote};
+    use crate::circuit::poseidon::PoseidonCircuit;
+    use crate::merkle::MerkleTree;
+    use crate::poseidon::{Poseidon as PoseidonHasher, PoseidonParams};
+    use rand::{Rand, SeedableRng, XorShiftRng};
+    use sapling_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use sapling_crypto::bellman::pairing::ff::{Field, PrimeField};
+    use sapling_crypto::bellman::Circuit;
+    use sapling_crypto::circuit::test::TestConstraintSystem;
+
+    fn fr(value: &str) -> Fr {
+        Fr::from_str(value).unwrap()
+    }
+
+    fn commitment(hasher: &PoseidonHasher<Bn256>, value: Fr, owner_pk: Fr, blinding: Fr) -> Fr {
+        hasher.hash(vec![hasher.hash(vec![value, owner_pk]), blinding])
+    }
+
+    // spends a note of 70 and a zero note, creates notes of input_value + value_delta split as given
+    fn inputs(value_delta: Fr, out_0: Fr, out_1: Fr) -> JoinSplitInputs<Bn256> {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6258, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+        let hasher = PoseidonHasher::new(params);
+        let mut note_tree = MerkleTree::empty(hasher.clone(), 3);
+
+        let owner_sks = [Fr::rand(&mut rng), Fr::rand(&mut rng)];
+        let blindings = [Fr::rand(&mut rng), Fr::rand(&mut rng)];
+        let values = [fr("70"), Fr::zero()];
+        let comms: Vec<Fr> = (0..2)
+            .map(|i| {
+                let owner_pk = hasher.hash(vec![owner_sks[i]]);
+                commitment(&hasher, values[i], owner_pk, blindings[i])
+            })
+            .collect();
+
+        // zero note is not in the tree
+        note_tree.update(2, comms[0]).unwrap();
+        let auth_path: Vec<Option<(Fr, bool)>> = note_tree
+            .get_witness(2)
+            .unwrap()
+            .into_iter()
+            .map(|w| Some(w))
+            .collect();
+
+        let input_notes = (0..2)
+            .map(|i| InputNote {
+                value: Some(values[i]),
+                owner_sk: Some(owner_sks[i]),
+                blinding: Some(blindings[i]),
+                auth_path: auth_path.clone(),
+            })
+            .collect();
+        let nullifiers = (0..2)
+            .map(|i| Some(hasher.hash(vec![comms[i], owner_sks[i]])))
+            .collect();
+
+        let mut output_notes = vec![];
+        let mut commitments = vec![];
+        for value in [out_0, out_1].iter() {
+            let owner_pk = Fr::rand(&mut rng);
+            let blinding = Fr::rand(&mut rng);
+            commitments.push(Some(commitment(&hasher, *value, owner_pk, blinding)));
+            output_notes.push(OutputNote {
+                value: Some(*value),
+                owner_pk: Some(owner_pk),
+                blinding: Some(blinding),
+            });
+        }
+
+        JoinSplitInputs {
+            root: Some(note_tree.get_root()),
+            value_delta: Some(value_delta),
+            nullifiers,
+            commitments,
+            input_notes,
+            output_notes,
+        }
+    }
+
+    fn is_satisfied(inputs: JoinSplitInputs<Bn256>) -> bool {
+        let params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+        let circuit = JoinSplitCircuit {
+            inputs,
+            hasher: PoseidonCircuit::new(params),
+        };
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        circuit.synthesize(&mut cs).unwrap();
+        assert!(cs.find_unconstrained().is_empty());
+        cs.is_satisfied()
+    }
+
+    #[test]
+    fn test_join_split_circuit() {
+        let mut withdraw = fr("30");
+        withdraw.negate();
+
+        // transfer, deposit and withdrawal
+        assert!(is_satisfied(inputs(Fr::zero(), fr("60"), fr("10"))));
+        assert!(is_satisfied(inputs(fr("30"), fr("60"), fr("40"))));
+        assert!(is_satisfied(inputs(withdraw, fr("40"), Fr::zero())));
+
+        // outputs exceed inputs
+        assert!(!is_satisfied(inputs(Fr::zero(), fr("60"), fr("11"))));
+
+        // negative output value wraps around the field
+        let mut negative = fr("10");
+        negative.negate();
+        assert!(!is_satisfied(inputs(Fr::zero(), fr("80"), negative)));
+
+        // wrong nullifier
+        let mut wrong_nullifier = inputs(Fr::zero(), fr("60"), fr("10"));
+        wrong_nullifier.nullifiers[0] = Some(fr("1"));
+        assert!(!is_satisfied(wrong_nullifier));
+
+        // wrong output commitment
+        let mut wrong_commitment = inputs(Fr::zero(), fr("60"), fr("10"));
+        wrong_commitment.commitments[1] = Some(fr("1"));
+        assert!(!is_satisfied(wrong_commitment));
+
+        // note with value is not in the tree
+        let mut stale_root = inputs(Fr::zero(), fr("60"), fr("10"));
+        stale_root.root = Some(fr("1"));
+        assert!(!is_satisfied(stale_root));
+
+        // zero note is spendable without membership
+        let mut zero_note = inputs(Fr::zero(), fr("70"), Fr::zero());
+        zero_note.input_notes[1].auth_path = vec![Some((fr("5"), true)); 3];
+        assert!(is_satisfied(zero_note));
+    }
+}
@@ -0,0 +1,42 @@
+use crate::circuit::poseidon::PoseidonCircuit;
+use sapling_crypto::bellman::pairing::Engine;
+use sapling_crypto::bellman::{ConstraintSystem, SynthesisError};
+use sapling_crypto::circuit::{boolean, num, Assignment};
+
+// ascends the membership tree from a leaf with given authentication path
+// and returns the allocated root
+pub fn allocate_merkle_root<CS, E>(
+    mut cs: CS,
+    hasher: &PoseidonCircuit<E>,
+    leaf: &num::AllocatedNum<E>,
+    auth_path: &[Option<(E::Fr, bool)>],
+) -> Result<num::AllocatedNum<E>, SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    // accumulator up to the root
+
+    let mut acc = leaf.clone();
+
+    for (i, e) in auth_path.iter().enumerate() {
+        let cs = &mut cs.namespace(|| format!("auth path {}", i));
+        let position = boolean::Boolean::from(boolean::AllocatedBit::alloc(
+            cs.namespace(|| "position bit"),
+            e.map(|e| e.1),
+        )?);
+        let path_element =
+            num::AllocatedNum::alloc(cs.namespace(|| "path element"), || Ok(e.get()?.0))?;
+
+        let (xr, xl) = num::AllocatedNum::conditionally_reverse(
+            cs.namespace(|| "conditional reversal of preimage"),
+            &acc,
+            &path_element,
+            &position,
+        )?;
+
+        acc = hasher.alloc(cs.namespace(|| "hash couple"), vec![xl, xr])?;
+    }
+
+    Ok(acc)
+}
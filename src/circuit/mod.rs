@@ -1,6 +1,12 @@
+pub mod join_split;
+mod merkle;
 mod polynomial;
 pub mod poseidon;
+pub mod range;
 pub mod rln;
+pub mod rln_v2;
+pub mod semaphore;
+pub mod sparse_merkle;
 
 #[cfg(any(test, feature = "bench"))]
 pub mod bench;
@@ -44,3 +44,28 @@ where
     );
     Ok(y)
 }
+
+// horner evaluation of a polynomial of degree k
+// y = a_0 + a_1 * x + ... + a_k * x^k
+pub fn allocate_polynomial_eval<CS, E>(
+    mut cs: CS,
+    coeffs: &[num::AllocatedNum<E>],
+    x: &num::AllocatedNum<E>,
+) -> Result<num::AllocatedNum<E>, SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    assert!(coeffs.len() > 1, "polynomial degree must be at least one");
+    let k = coeffs.len() - 1;
+    let mut acc = coeffs[k].clone();
+    for i in (0..k).rev() {
+        acc = allocate_add_with_coeff(
+            cs.namespace(|| format!("horner step {}", i)),
+            &acc,
+            x,
+            &coeffs[i],
+        )?;
+    }
+    Ok(acc)
+}
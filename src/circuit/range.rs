@@ -0,0 +1,121 @@
+use sapling_crypto::bellman::pairing::ff::{Field, PrimeField};
+use sapling_crypto::bellman::pairing::Engine;
+use sapling_crypto::bellman::{ConstraintSystem, SynthesisError};
+use sapling_crypto::circuit::{boolean, num, Assignment};
+
+// Range checks by boolean decomposition
+//
+// value == sum(bit_i * 2^i) for i < n where each bit is constrained to be boolean.
+// n must be below the capacity of the scalar field,
+// otherwise the packing wraps around the modulus and is not unique.
+
+// constraints value to fit in n bits
+pub fn enforce_bit_length<E, CS>(
+    mut cs: CS,
+    value: &num::AllocatedNum<E>,
+    n: usize,
+) -> Result<(), SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    assert!(
+        n <= E::Fr::CAPACITY as usize,
+        "bit length exceeds field capacity"
+    );
+    let repr = value.get_value().map(|v| v.into_repr());
+    let mut lc = num::Num::<E>::zero();
+    let mut coeff = E::Fr::one();
+    for i in 0..n {
+        let bit = boolean::AllocatedBit::alloc(
+            cs.namespace(|| format!("bit {}", i)),
+            repr.map(|r| (r.as_ref()[i / 64] >> (i % 64)) & 1 == 1),
+        )?;
+        lc = lc.add_bool_with_coeff(CS::one(), &boolean::Boolean::from(bit), coeff);
+        coeff.double();
+    }
+    cs.enforce(
+        || "enforce packing",
+        |_| lc.lc(E::Fr::one()),
+        |lc| lc + CS::one(),
+        |lc| lc + value.get_variable(),
+    );
+    Ok(())
+}
+
+// allocates value constrained to fit in n bits
+pub fn allocate_bounded<E, CS>(
+    mut cs: CS,
+    value: Option<E::Fr>,
+    n: usize,
+) -> Result<num::AllocatedNum<E>, SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    let value = num::AllocatedNum::alloc(cs.namespace(|| "value"), || Ok(*value.get()?))?;
+    enforce_bit_length(cs.namespace(|| "range"), &value, n)?;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{allocate_bounded, enforce_bit_length};
+    use sapling_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use sapling_crypto::bellman::pairing::ff::{Field, PrimeField};
+    use sapling_crypto::bellman::ConstraintSystem;
+    use sapling_crypto::circuit::num;
+    use sapling_crypto::circuit::test::TestConstraintSystem;
+
+    fn is_in_range(value: Fr, n: usize) -> bool {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        allocate_bounded(cs.namespace(|| "bounded"), Some(value), n).unwrap();
+        assert!(cs.find_unconstrained().is_empty());
+        cs.is_satisfied()
+    }
+
+    fn pow2(n: usize) -> Fr {
+        let mut value = Fr::one();
+        for _ in 0..n {
+            value.double();
+        }
+        value
+    }
+
+    #[test]
+    fn test_range_check() {
+        let mut max_u64 = pow2(64);
+        max_u64.sub_assign(&Fr::one());
+
+        assert!(is_in_range(Fr::zero(), 16));
+        assert!(is_in_range(Fr::from_str("65535").unwrap(), 16));
+        assert!(!is_in_range(Fr::from_str("65536").unwrap(), 16));
+        assert!(is_in_range(max_u64, 64));
+        assert!(!is_in_range(pow2(64), 64));
+        assert!(is_in_range(Fr::zero(), 0));
+        assert!(!is_in_range(Fr::one(), 0));
+
+        // negative values wrap around the modulus
+        let mut minus_one = Fr::one();
+        minus_one.negate();
+        assert!(!is_in_range(minus_one, 64));
+        assert!(!is_in_range(minus_one, Fr::CAPACITY as usize));
+
+        // bits of a wrong decomposition do not pack into the value
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let value =
+            num::AllocatedNum::alloc(cs.namespace(|| "value"), || Ok(Fr::from_str("5").unwrap()))
+                .unwrap();
+        enforce_bit_length(cs.namespace(|| "range"), &value, 3).unwrap();
+        assert!(cs.is_satisfied());
+        cs.set("range/bit 1/boolean", Fr::one());
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_range_check_over_capacity() {
+        is_in_range(Fr::zero(), Fr::NUM_BITS as usize);
+    }
+}
@@ -1,4 +1,5 @@
-use crate::circuit::polynomial::allocate_add_with_coeff;
+use crate::circuit::merkle::allocate_merkle_root;
+use crate::circuit::polynomial::allocate_polynomial_eval;
 use crate::circuit::poseidon::PoseidonCircuit;
 use crate::poseidon::{Poseidon as PoseidonHasher, PoseidonParams};
 use sapling_crypto::bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
@@ -232,6 +233,9 @@ where
 {
     pub inputs: RLNInputs<E>,
     pub hasher: PoseidonCircuit<E>,
+    // degree of the secret polynomial,
+    // a member can signal `degree` times per epoch
+    pub degree: usize,
 }
 
 impl<E> Circuit<E> for RLNCircuit<E>
@@ -239,6 +243,10 @@ where
     E: Engine,
 {
     fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        if self.degree == 0 {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
         // 1. Part
         // Membership constraints
         // root == merkle_proof(auth_path, preimage_of_leaf)
@@ -260,33 +268,14 @@ where
             .hasher
             .alloc(cs.namespace(|| "identity"), vec![preimage.clone()])?;
 
-        // accumulator up to the root
-
-        let mut acc = identity.clone();
-
         // ascend the tree
 
-        let auth_path_witness = self.inputs.auth_path.clone();
-        for (i, e) in auth_path_witness.into_iter().enumerate() {
-            let cs = &mut cs.namespace(|| format!("auth path {}", i));
-            let position = boolean::Boolean::from(boolean::AllocatedBit::alloc(
-                cs.namespace(|| "position bit"),
-                e.map(|e| e.1),
-            )?);
-            let path_element =
-                num::AllocatedNum::alloc(cs.namespace(|| "path element"), || Ok(e.get()?.0))?;
-
-            let (xr, xl) = num::AllocatedNum::conditionally_reverse(
-                cs.namespace(|| "conditional reversal of preimage"),
-                &acc,
-                &path_element,
-                &position,
-            )?;
-
-            acc = self
-                .hasher
-                .alloc(cs.namespace(|| "hash couple"), vec![xl, xr])?;
-        }
+        let acc = allocate_merkle_root(
+            cs.namespace(|| "membership"),
+            &self.hasher,
+            &identity,
+            &self.inputs.auth_path,
+        )?;
 
         // see if it is a member
 
@@ -298,9 +287,9 @@ where
         );
 
         // 2. Part
-        // Line Equation Constaints
-        // a_1 = hash(a_0, epoch)
-        // share_y == a_0 + a_1 * share_x
+        // Polynomial Constaints
+        // a_1 = hash(a_0, epoch), a_i = hash(a_{i-1}, epoch)
+        // share_y == a_0 + a_1 * share_x + ... + a_k * share_x^k
 
         let epoch = num::AllocatedNum::alloc(cs.namespace(|| "epoch"), || {
             let value = self.inputs.epoch.clone();
@@ -310,11 +299,17 @@ where
 
         let a_0 = preimage.clone();
 
-        // a_1 == h(a_0, epoch)
+        // a_i == h(a_{i-1}, epoch)
 
-        let a_1 = self
-            .hasher
-            .alloc(cs.namespace(|| "a_1"), vec![a_0.clone(), epoch])?;
+        let mut coeffs = vec![a_0.clone()];
+        for i in 0..self.degree {
+            let a_i = self.hasher.alloc(
+                cs.namespace(|| format!("a_{}", i + 1)),
+                vec![coeffs[i].clone(), epoch.clone()],
+            )?;
+            coeffs.push(a_i);
+        }
+        let a_1 = coeffs[1].clone();
 
         let share_x = num::AllocatedNum::alloc(cs.namespace(|| "share x"), || {
             let value = self.inputs.share_x.clone();
@@ -322,9 +317,9 @@ where
         })?;
         share_x.inputize(cs.namespace(|| "share x is public"))?;
 
-        // constaint the evaluation the line equation
+        // constaint the evaluation the polynomial
 
-        let eval = allocate_add_with_coeff(cs.namespace(|| "eval"), &a_1, &share_x, &a_0)?;
+        let eval = allocate_polynomial_eval(cs.namespace(|| "eval"), &coeffs, &share_x)?;
 
         let share_y = num::AllocatedNum::alloc(cs.namespace(|| "share y"), || {
             let value = self.inputs.share_y.clone();
@@ -332,7 +327,7 @@ where
         })?;
         share_y.inputize(cs.namespace(|| "share y is public"))?;
 
-        // see if share satisfies the line equation
+        // see if share satisfies the polynomial
 
         cs.enforce(
             || "enforce lookup",
@@ -440,6 +435,25 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_rln_higher_degree() {
+        use sapling_crypto::bellman::pairing::bn256::Bn256;
+        let poseidon_parameters = PoseidonParams::new(8, 55, 3, None, None, None);
+        for share_degree in 1..4 {
+            let rln_test = bench::RLNTest::<Bn256>::new_with_degree(
+                3,
+                share_degree,
+                Some(poseidon_parameters.clone()),
+            );
+            let num_constraints = rln_test.synthesize();
+            rln_test.run_prover_bench();
+            println!(
+                "share degree: {}, number of constatins:\t{}",
+                share_degree, num_constraints
+            );
+        }
+    }
+
     #[test]
     fn test_input_serialization() {
         use sapling_crypto::bellman::pairing::bn256::{Bn256, Fr};
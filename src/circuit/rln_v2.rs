@@ -0,0 +1,326 @@
+use crate::circuit::merkle::allocate_merkle_root;
+use crate::circuit::polynomial::allocate_add_with_coeff;
+use crate::circuit::poseidon::PoseidonCircuit;
+use crate::circuit::range::enforce_bit_length;
+use crate::poseidon::{Poseidon as PoseidonHasher, PoseidonParams};
+use sapling_crypto::bellman::pairing::ff::Field;
+use sapling_crypto::bellman::pairing::Engine;
+use sapling_crypto::bellman::{Circuit, ConstraintSystem, SynthesisError};
+use sapling_crypto::circuit::{num, Assignment};
+
+// Rate Limit Nullifier with a per epoch message limit
+//
+// Membership leaf is the rate commitment
+// H(H(id_key), user_message_limit)
+// and the prover picks a private message_id < user_message_limit
+// which is mixed into the line equation of the epoch.
+// A member can send user_message_limit signals in an epoch,
+// reusing a message_id reveals the secret.
+
+// bit length of message limit and message id
+pub const MESSAGE_LIMIT_BITS: usize = 16;
+
+#[derive(Clone)]
+pub struct RLNv2Inputs<E>
+where
+    E: Engine,
+{
+    // Public inputs
+
+    // share, (x, y),
+    // where x should be hash of the signal
+    // and y is the evaluation
+    pub share_x: Option<E::Fr>,
+    pub share_y: Option<E::Fr>,
+
+    // epoch is the external nullifier
+    pub epoch: Option<E::Fr>,
+
+    // nullifier
+    pub nullifier: Option<E::Fr>,
+
+    // root is the current state of membership set
+    pub root: Option<E::Fr>,
+
+    // Private inputs
+
+    // H(id_key) together with user_message_limit
+    // must be a preimage of a leaf in membership tree.
+    pub id_key: Option<E::Fr>,
+
+    // number of messages the member is allowed to send per epoch
+    pub user_message_limit: Option<E::Fr>,
+
+    // index of the message in the epoch,
+    // must be less than user_message_limit
+    pub message_id: Option<E::Fr>,
+
+    // authentication path of the member
+    pub auth_path: Vec<Option<(E::Fr, bool)>>,
+}
+
+impl<E> RLNv2Inputs<E>
+where
+    E: Engine,
+{
+    pub fn public_inputs(&self) -> Vec<E::Fr> {
+        vec![
+            self.root.unwrap(),
+            self.epoch.unwrap(),
+            self.share_x.unwrap(),
+            self.share_y.unwrap(),
+            self.nullifier.unwrap(),
+        ]
+    }
+
+    pub fn merkle_depth(&self) -> usize {
+        self.auth_path.len()
+    }
+
+    pub fn empty(merkle_depth: usize) -> RLNv2Inputs<E> {
+        RLNv2Inputs::<E> {
+            share_x: None,
+            share_y: None,
+            epoch: None,
+            nullifier: None,
+            root: None,
+            id_key: None,
+            user_message_limit: None,
+            message_id: None,
+            auth_path: vec![None; merkle_depth],
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RLNv2Circuit<E>
+where
+    E: Engine,
+{
+    pub inputs: RLNv2Inputs<E>,
+    pub hasher: PoseidonCircuit<E>,
+}
+
+impl<E> Circuit<E> for RLNv2Circuit<E>
+where
+    E: Engine,
+{
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        // 1. Part
+        // Membership constraints
+        // root == merkle_proof(auth_path, H(H(id_key), user_message_limit))
+
+        let root = num::AllocatedNum::alloc(cs.namespace(|| "root"), || {
+            let value = self.inputs.root.clone();
+            Ok(*value.get()?)
+        })?;
+        root.inputize(cs.namespace(|| "root is public"))?;
+
+        let preimage = num::AllocatedNum::alloc(cs.namespace(|| "preimage"), || {
+            let value = self.inputs.id_key;
+            Ok(*value.get()?)
+        })?;
+
+        let identity = self
+            .hasher
+            .alloc(cs.namespace(|| "identity"), vec![preimage.clone()])?;
+
+        let user_message_limit =
+            num::AllocatedNum::alloc(cs.namespace(|| "user message limit"), || {
+                let value = self.inputs.user_message_limit;
+                Ok(*value.get()?)
+            })?;
+
+        // rate commitment is a leaf of membership tree
+
+        let rate_commitment = self.hasher.alloc(
+            cs.namespace(|| "rate commitment"),
+            vec![identity, user_message_limit.clone()],
+        )?;
+
+        let acc = allocate_merkle_root(
+            cs.namespace(|| "membership"),
+            &self.hasher,
+            &rate_commitment,
+            &self.inputs.auth_path,
+        )?;
+
+        cs.enforce(
+            || "enforce membership",
+            |lc| lc + acc.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + root.get_variable(),
+        );
+
+        // 2. Part
+        // Message limit constraints
+        // message_id < user_message_limit < 2^MESSAGE_LIMIT_BITS
+
+        let message_id = num::AllocatedNum::alloc(cs.namespace(|| "message id"), || {
+            let value = self.inputs.message_id;
+            Ok(*value.get()?)
+        })?;
+
+        // slack == user_message_limit - message_id - 1
+        let slack = num::AllocatedNum::alloc(cs.namespace(|| "slack"), || {
+            let mut value = *user_message_limit.get_value().get()?;
+            value.sub_assign(message_id.get_value().get()?);
+            value.sub_assign(&E::Fr::one());
+            Ok(value)
+        })?;
+        cs.enforce(
+            || "enforce slack",
+            |lc| lc + user_message_limit.get_variable() - message_id.get_variable() - CS::one(),
+            |lc| lc + CS::one(),
+            |lc| lc + slack.get_variable(),
+        );
+
+        enforce_bit_length(
+            cs.namespace(|| "user message limit range"),
+            &user_message_limit,
+            MESSAGE_LIMIT_BITS,
+        )?;
+        enforce_bit_length(
+            cs.namespace(|| "message id range"),
+            &message_id,
+            MESSAGE_LIMIT_BITS,
+        )?;
+        enforce_bit_length(cs.namespace(|| "slack range"), &slack, MESSAGE_LIMIT_BITS)?;
+
+        // 3. Part
+        // Line Equation Constaints
+        // a_1 = hash(hash(a_0, epoch), message_id)
+        // share_y == a_0 + a_1 * share_x
+
+        let epoch = num::AllocatedNum::alloc(cs.namespace(|| "epoch"), || {
+            let value = self.inputs.epoch.clone();
+            Ok(*value.get()?)
+        })?;
+        epoch.inputize(cs.namespace(|| "epoch is public"))?;
+
+        let a_0 = preimage.clone();
+
+        let epoch_secret = self
+            .hasher
+            .alloc(cs.namespace(|| "epoch secret"), vec![a_0.clone(), epoch])?;
+        let a_1 = self
+            .hasher
+            .alloc(cs.namespace(|| "a_1"), vec![epoch_secret, message_id])?;
+
+        let share_x = num::AllocatedNum::alloc(cs.namespace(|| "share x"), || {
+            let value = self.inputs.share_x.clone();
+            Ok(*value.get()?)
+        })?;
+        share_x.inputize(cs.namespace(|| "share x is public"))?;
+
+        let eval = allocate_add_with_coeff(cs.namespace(|| "eval"), &a_1, &share_x, &a_0)?;
+
+        let share_y = num::AllocatedNum::alloc(cs.namespace(|| "share y"), || {
+            let value = self.inputs.share_y.clone();
+            Ok(*value.get()?)
+        })?;
+        share_y.inputize(cs.namespace(|| "share y is public"))?;
+
+        cs.enforce(
+            || "enforce lookup",
+            |lc| lc + share_y.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + eval.get_variable(),
+        );
+
+        // 4. Part
+        // Nullifier constraints
+        // nullifier == hash(a_1)
+
+        let nullifier_calculated = self
+            .hasher
+            .alloc(cs.namespace(|| "calculated nullifier"), vec![a_1.clone()])?;
+
+        let nullifier = num::AllocatedNum::alloc(cs.namespace(|| "nullifier"), || {
+            let value = self.inputs.nullifier.clone();
+            Ok(*value.get()?)
+        })?;
+        nullifier.inputize(cs.namespace(|| "nullifier is public"))?;
+
+        cs.enforce(
+            || "enforce nullifier",
+            |lc| lc + nullifier_calculated.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + nullifier.get_variable(),
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{RLNv2Circuit, RLNv2Inputs};
+    use crate::circuit::poseidon::PoseidonCircuit;
+    use crate::merkle::MerkleTree;
+    use crate::poseidon::{Poseidon as PoseidonHasher, PoseidonParams};
+    use rand::{Rand, SeedableRng, XorShiftRng};
+    use sapling_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use sapling_crypto::bellman::pairing::ff::{Field, PrimeField};
+    use sapling_crypto::bellman::Circuit;
+    use sapling_crypto::circuit::test::TestConstraintSystem;
+
+    fn inputs(user_message_limit: u64, message_id: u64) -> RLNv2Inputs<Bn256> {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6258, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+        let hasher = PoseidonHasher::new(params);
+        let mut membership_tree = MerkleTree::empty(hasher.clone(), 3);
+
+        let id_key = Fr::rand(&mut rng);
+        let user_message_limit = Fr::from_str(&user_message_limit.to_string()).unwrap();
+        let message_id = Fr::from_str(&message_id.to_string()).unwrap();
+        let id_comm = hasher.hash(vec![id_key]);
+        let rate_comm = hasher.hash(vec![id_comm, user_message_limit]);
+        let id_index = 5;
+        membership_tree.update(id_index, rate_comm).unwrap();
+        let auth_path = membership_tree.get_witness(id_index).unwrap();
+
+        let epoch = Fr::rand(&mut rng);
+        let share_x = Fr::rand(&mut rng);
+        let a_0 = id_key;
+        let a_1 = hasher.hash(vec![hasher.hash(vec![a_0, epoch]), message_id]);
+        let mut share_y = a_1;
+        share_y.mul_assign(&share_x);
+        share_y.add_assign(&a_0);
+        let nullifier = hasher.hash(vec![a_1]);
+
+        RLNv2Inputs {
+            share_x: Some(share_x),
+            share_y: Some(share_y),
+            epoch: Some(epoch),
+            nullifier: Some(nullifier),
+            root: Some(membership_tree.get_root()),
+            id_key: Some(id_key),
+            user_message_limit: Some(user_message_limit),
+            message_id: Some(message_id),
+            auth_path: auth_path.into_iter().map(|w| Some(w)).collect(),
+        }
+    }
+
+    fn is_satisfied(inputs: RLNv2Inputs<Bn256>) -> bool {
+        let params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+        let circuit = RLNv2Circuit {
+            inputs,
+            hasher: PoseidonCircuit::new(params),
+        };
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        circuit.synthesize(&mut cs).unwrap();
+        assert!(cs.find_unconstrained().is_empty());
+        cs.is_satisfied()
+    }
+
+    #[test]
+    fn test_rln_v2_message_limit() {
+        assert!(is_satisfied(inputs(3, 0)));
+        assert!(is_satisfied(inputs(3, 2)));
+        assert!(!is_satisfied(inputs(3, 3)));
+        assert!(!is_satisfied(inputs(3, 1 << 20)));
+        assert!(!is_satisfied(inputs(0, 0)));
+    }
+}
@@ -0,0 +1,284 @@
+use crate::circuit::merkle::allocate_merkle_root;
+use crate::circuit::poseidon::PoseidonCircuit;
+use crate::hash_to_field::hash_to_field;
+use crate::poseidon::Poseidon as PoseidonHasher;
+use sapling_crypto::bellman::pairing::Engine;
+use sapling_crypto::bellman::{Circuit, ConstraintSystem, SynthesisError};
+use sapling_crypto::circuit::{num, Assignment};
+
+// Semaphore style membership with a topic nullifier
+//
+// Membership leaf is the id commitment H(id_key) as in RLN,
+// the nullifier H(H(id_key, external_nullifier), domain) is unique per member and topic
+// and the signal hash is bound to the proof.
+//
+// H(id_key, external_nullifier) is the first coefficient of the RLN polynomial of an epoch
+// with the same value as the topic, so it is hashed once more with a fixed domain.
+// The domain is above any RLNv2 message id, so the nullifier is never an RLN coefficient
+// and does not reveal one.
+
+/// domain of the semaphore nullifier
+pub fn nullifier_domain<E: Engine>() -> E::Fr {
+    hash_to_field::<E>(b"semaphore_nullifier")
+}
+
+/// nullifier of the member for the topic
+pub fn semaphore_nullifier<E: Engine>(
+    hasher: &PoseidonHasher<E>,
+    id_key: E::Fr,
+    external_nullifier: E::Fr,
+) -> E::Fr {
+    let topic_key = hasher.hash(vec![id_key, external_nullifier]);
+    hasher.hash(vec![topic_key, nullifier_domain::<E>()])
+}
+
+#[derive(Clone)]
+pub struct SemaphoreInputs<E>
+where
+    E: Engine,
+{
+    // Public inputs
+
+    // root is the current state of membership set
+    pub root: Option<E::Fr>,
+
+    // topic of the signal
+    pub external_nullifier: Option<E::Fr>,
+
+    // hash of the signal
+    pub signal_hash: Option<E::Fr>,
+
+    // nullifier of the member for the topic
+    pub nullifier: Option<E::Fr>,
+
+    // Private inputs
+
+    // H(id_key) must be a preimage of a leaf in membership tree.
+    pub id_key: Option<E::Fr>,
+
+    // authentication path of the member
+    pub auth_path: Vec<Option<(E::Fr, bool)>>,
+}
+
+impl<E> SemaphoreInputs<E>
+where
+    E: Engine,
+{
+    pub fn public_inputs(&self) -> Vec<E::Fr> {
+        vec![
+            self.root.unwrap(),
+            self.external_nullifier.unwrap(),
+            self.signal_hash.unwrap(),
+            self.nullifier.unwrap(),
+        ]
+    }
+
+    pub fn merkle_depth(&self) -> usize {
+        self.auth_path.len()
+    }
+
+    pub fn empty(merkle_depth: usize) -> SemaphoreInputs<E> {
+        SemaphoreInputs::<E> {
+            root: None,
+            external_nullifier: None,
+            signal_hash: None,
+            nullifier: None,
+            id_key: None,
+            auth_path: vec![None; merkle_depth],
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SemaphoreCircuit<E>
+where
+    E: Engine,
+{
+    pub inputs: SemaphoreInputs<E>,
+    pub hasher: PoseidonCircuit<E>,
+}
+
+impl<E> Circuit<E> for SemaphoreCircuit<E>
+where
+    E: Engine,
+{
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        // 1. Part
+        // Membership constraints
+        // root == merkle_proof(auth_path, H(id_key))
+
+        let root = num::AllocatedNum::alloc(cs.namespace(|| "root"), || {
+            let value = self.inputs.root;
+            Ok(*value.get()?)
+        })?;
+        root.inputize(cs.namespace(|| "root is public"))?;
+
+        let preimage = num::AllocatedNum::alloc(cs.namespace(|| "preimage"), || {
+            let value = self.inputs.id_key;
+            Ok(*value.get()?)
+        })?;
+
+        let identity = self
+            .hasher
+            .alloc(cs.namespace(|| "identity"), vec![preimage.clone()])?;
+
+        let acc = allocate_merkle_root(
+            cs.namespace(|| "membership"),
+            &self.hasher,
+            &identity,
+            &self.inputs.auth_path,
+        )?;
+
+        cs.enforce(
+            || "enforce membership",
+            |lc| lc + acc.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + root.get_variable(),
+        );
+
+        // 2. Part
+        // Nullifier constraints
+        // nullifier == H(H(id_key, external_nullifier), domain)
+
+        let external_nullifier =
+            num::AllocatedNum::alloc(cs.namespace(|| "external nullifier"), || {
+                let value = self.inputs.external_nullifier;
+                Ok(*value.get()?)
+            })?;
+        external_nullifier.inputize(cs.namespace(|| "external nullifier is public"))?;
+
+        let topic_key = self.hasher.alloc(
+            cs.namespace(|| "topic key"),
+            vec![preimage, external_nullifier],
+        )?;
+
+        let domain = num::AllocatedNum::alloc(cs.namespace(|| "nullifier domain"), || {
+            Ok(nullifier_domain::<E>())
+        })?;
+        cs.enforce(
+            || "enforce nullifier domain",
+            |lc| lc + domain.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + (nullifier_domain::<E>(), CS::one()),
+        );
+
+        let nullifier_calculated = self.hasher.alloc(
+            cs.namespace(|| "calculated nullifier"),
+            vec![topic_key, domain],
+        )?;
+
+        let nullifier = num::AllocatedNum::alloc(cs.namespace(|| "nullifier"), || {
+            let value = self.inputs.nullifier;
+            Ok(*value.get()?)
+        })?;
+        nullifier.inputize(cs.namespace(|| "nullifier is public"))?;
+
+        cs.enforce(
+            || "enforce nullifier",
+            |lc| lc + nullifier_calculated.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + nullifier.get_variable(),
+        );
+
+        // 3. Part
+        // Signal binding
+        // signal hash takes part in a constraint so that the proof is bound to it
+
+        let signal_hash = num::AllocatedNum::alloc(cs.namespace(|| "signal hash"), || {
+            let value = self.inputs.signal_hash;
+            Ok(*value.get()?)
+        })?;
+        signal_hash.inputize(cs.namespace(|| "signal hash is public"))?;
+
+        signal_hash.square(cs.namespace(|| "signal hash square"))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{nullifier_domain, semaphore_nullifier, SemaphoreCircuit, SemaphoreInputs};
+    use crate::circuit::poseidon::PoseidonCircuit;
+    use crate::circuit::rln_v2::MESSAGE_LIMIT_BITS;
+    use crate::merkle::MerkleTree;
+    use crate::poseidon::{Poseidon as PoseidonHasher, PoseidonParams};
+    use rand::{Rand, SeedableRng, XorShiftRng};
+    use sapling_crypto::bellman::pairing::bn256::{Bn256, Fr, FrRepr};
+    use sapling_crypto::bellman::pairing::ff::PrimeField;
+    use sapling_crypto::bellman::Circuit;
+    use sapling_crypto::circuit::test::TestConstraintSystem;
+
+    fn inputs() -> SemaphoreInputs<Bn256> {
+        let mut rng = XorShiftRng::from_seed([0x3dbe6258, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+        let hasher = PoseidonHasher::new(params);
+        let mut membership_tree = MerkleTree::empty(hasher.clone(), 3);
+
+        let id_key = Fr::rand(&mut rng);
+        let id_comm = hasher.hash(vec![id_key]);
+        let id_index = 6;
+        membership_tree.update(id_index, id_comm).unwrap();
+        let auth_path = membership_tree.get_witness(id_index).unwrap();
+
+        let external_nullifier = Fr::rand(&mut rng);
+        let nullifier = semaphore_nullifier(&hasher, id_key, external_nullifier);
+
+        SemaphoreInputs {
+            root: Some(membership_tree.get_root()),
+            external_nullifier: Some(external_nullifier),
+            signal_hash: Some(Fr::rand(&mut rng)),
+            nullifier: Some(nullifier),
+            id_key: Some(id_key),
+            auth_path: auth_path.into_iter().map(|w| Some(w)).collect(),
+        }
+    }
+
+    fn is_satisfied(inputs: SemaphoreInputs<Bn256>) -> bool {
+        let params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+        let circuit = SemaphoreCircuit {
+            inputs,
+            hasher: PoseidonCircuit::new(params),
+        };
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        circuit.synthesize(&mut cs).unwrap();
+        assert!(cs.find_unconstrained().is_empty());
+        cs.is_satisfied()
+    }
+
+    #[test]
+    fn test_semaphore_circuit() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        assert!(is_satisfied(inputs()));
+
+        // nullifier of another topic
+        let mut wrong_nullifier = inputs();
+        wrong_nullifier.external_nullifier = Some(Fr::rand(&mut rng));
+        assert!(!is_satisfied(wrong_nullifier));
+
+        // key of a non member
+        let mut wrong_key = inputs();
+        wrong_key.id_key = Some(Fr::rand(&mut rng));
+        assert!(!is_satisfied(wrong_key));
+
+        // stale root
+        let mut wrong_root = inputs();
+        wrong_root.root = Some(Fr::rand(&mut rng));
+        assert!(!is_satisfied(wrong_root));
+
+        // nullifier without the domain is the first coefficient of an RLN polynomial
+        let params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+        let hasher = PoseidonHasher::new(params);
+        let mut undomained = inputs();
+        undomained.nullifier = Some(hasher.hash(vec![
+            undomained.id_key.unwrap(),
+            undomained.external_nullifier.unwrap(),
+        ]));
+        assert!(!is_satisfied(undomained));
+
+        // domain is never an RLNv2 message id
+        let max_message_id = FrRepr::from(1u64 << MESSAGE_LIMIT_BITS);
+        assert!(nullifier_domain::<Bn256>().into_repr() > max_message_id);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::circuit::poseidon::PoseidonCircuit;
+use sapling_crypto::bellman::pairing::ff::Field;
+use sapling_crypto::bellman::pairing::Engine;
+use sapling_crypto::bellman::{ConstraintSystem, SynthesisError};
+use sapling_crypto::circuit::{num, Assignment};
+
+// Sparse merkle tree gadgets, see `merkle::SparseMerkleTree`
+//
+// The slot of the key is given by its bits, one bit per sibling,
+// siblings are ordered from the leaf up to the root.
+
+// ascends the sparse tree from the slot of the key and returns the allocated root
+pub fn allocate_sparse_merkle_root<CS, E>(
+    mut cs: CS,
+    hasher: &PoseidonCircuit<E>,
+    key: &num::AllocatedNum<E>,
+    leaf: &num::AllocatedNum<E>,
+    siblings: &[Option<E::Fr>],
+) -> Result<num::AllocatedNum<E>, SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    let key_bits = key.into_bits_le_strict(cs.namespace(|| "key bits"))?;
+    assert_eq!(siblings.len(), key_bits.len());
+
+    let mut acc = leaf.clone();
+
+    for (i, sibling) in siblings.iter().enumerate() {
+        let cs = &mut cs.namespace(|| format!("sibling {}", i));
+        let sibling = num::AllocatedNum::alloc(cs.namespace(|| "sibling"), || Ok(*sibling.get()?))?;
+
+        // current node is the right child if the key bit is set
+        let (xl, xr) = num::AllocatedNum::conditionally_reverse(
+            cs.namespace(|| "conditional reversal of preimage"),
+            &acc,
+            &sibling,
+            &key_bits[i],
+        )?;
+
+        acc = hasher.alloc(cs.namespace(|| "hash couple"), vec![xl, xr])?;
+    }
+
+    Ok(acc)
+}
+
+// constraints the key to map to the value in the tree of given root
+pub fn enforce_inclusion<CS, E>(
+    mut cs: CS,
+    hasher: &PoseidonCircuit<E>,
+    root: &num::AllocatedNum<E>,
+    key: &num::AllocatedNum<E>,
+    value: &num::AllocatedNum<E>,
+    siblings: &[Option<E::Fr>],
+) -> Result<(), SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    let leaf = hasher.alloc(cs.namespace(|| "leaf"), vec![key.clone(), value.clone()])?;
+    let acc = allocate_sparse_merkle_root(cs.namespace(|| "path"), hasher, key, &leaf, siblings)?;
+    cs.enforce(
+        || "enforce inclusion",
+        |lc| lc + acc.get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc + root.get_variable(),
+    );
+    Ok(())
+}
+
+// constraints the slot of the key to be empty in the tree of given root
+pub fn enforce_non_membership<CS, E>(
+    mut cs: CS,
+    hasher: &PoseidonCircuit<E>,
+    root: &num::AllocatedNum<E>,
+    key: &num::AllocatedNum<E>,
+    siblings: &[Option<E::Fr>],
+) -> Result<(), SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    let leaf = num::AllocatedNum::alloc(cs.namespace(|| "empty leaf"), || Ok(E::Fr::zero()))?;
+    cs.enforce(
+        || "enforce empty leaf",
+        |lc| lc + leaf.get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc,
+    );
+    let acc = allocate_sparse_merkle_root(cs.namespace(|| "path"), hasher, key, &leaf, siblings)?;
+    cs.enforce(
+        || "enforce non membership",
+        |lc| lc + acc.get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc + root.get_variable(),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{enforce_inclusion, enforce_non_membership};
+    use crate::circuit::poseidon::PoseidonCircuit;
+    use crate::merkle::SparseMerkleTree;
+    use crate::poseidon::{Poseidon as PoseidonHasher, PoseidonParams};
+    use sapling_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use sapling_crypto::bellman::pairing::ff::PrimeField;
+    use sapling_crypto::bellman::ConstraintSystem;
+    use sapling_crypto::circuit::num;
+    use sapling_crypto::circuit::test::TestConstraintSystem;
+
+    fn tree() -> SparseMerkleTree<Bn256> {
+        let params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+        let mut tree = SparseMerkleTree::empty(PoseidonHasher::new(params));
+        let one = Fr::from_str("1").unwrap();
+        tree.insert(Fr::from_str("300").unwrap(), one).unwrap();
+        tree.insert(Fr::from_str("7").unwrap(), one).unwrap();
+        tree
+    }
+
+    fn alloc(
+        cs: &mut TestConstraintSystem<Bn256>,
+        name: &str,
+        value: Fr,
+    ) -> num::AllocatedNum<Bn256> {
+        num::AllocatedNum::alloc(cs.namespace(|| name), || Ok(value)).unwrap()
+    }
+
+    fn is_absent(tree: &SparseMerkleTree<Bn256>, key: Fr) -> bool {
+        let params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+        let hasher = PoseidonCircuit::new(params);
+        let siblings: Vec<Option<Fr>> = tree.prove(&key).siblings.into_iter().map(Some).collect();
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let root = alloc(&mut cs, "root", tree.get_root());
+        let key = alloc(&mut cs, "key", key);
+        enforce_non_membership(cs.namespace(|| "absent"), &hasher, &root, &key, &siblings).unwrap();
+        cs.is_satisfied()
+    }
+
+    fn is_included(tree: &SparseMerkleTree<Bn256>, key: Fr, value: Fr) -> bool {
+        let params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+        let hasher = PoseidonCircuit::new(params);
+        let siblings: Vec<Option<Fr>> = tree.prove(&key).siblings.into_iter().map(Some).collect();
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+        let root = alloc(&mut cs, "root", tree.get_root());
+        let key = alloc(&mut cs, "key", key);
+        let value = alloc(&mut cs, "value", value);
+        enforce_inclusion(
+            cs.namespace(|| "included"),
+            &hasher,
+            &root,
+            &key,
+            &value,
+            &siblings,
+        )
+        .unwrap();
+        cs.is_satisfied()
+    }
+
+    #[test]
+    fn test_sparse_merkle_gadgets() {
+        let tree = tree();
+        let one = Fr::from_str("1").unwrap();
+
+        assert!(is_absent(&tree, Fr::from_str("301").unwrap()));
+        assert!(!is_absent(&tree, Fr::from_str("300").unwrap()));
+        // shares the lowest 8 bits of a present key
+        assert!(is_absent(&tree, Fr::from_str("44").unwrap()));
+
+        assert!(is_included(&tree, Fr::from_str("7").unwrap(), one));
+        assert!(!is_included(
+            &tree,
+            Fr::from_str("7").unwrap(),
+            Fr::from_str("2").unwrap()
+        ));
+        assert!(!is_included(&tree, Fr::from_str("44").unwrap(), one));
+    }
+}
@@ -0,0 +1,123 @@
+use bellman::SynthesisError;
+use std::{error, fmt, io};
+
+pub type Result<T> = std::result::Result<T, RLNError>;
+
+#[derive(Debug)]
+pub enum RLNError {
+    // bytes are not a canonical encoding of a scalar field element
+    InvalidFieldElement,
+    // leaf index is out of the membership tree
+    IndexOutOfRange(usize),
+    // signal does not hash to share_x of the proof
+    SignalHashMismatch,
+    // circuit is not satisfied or can not be synthesized
+    Synthesis(SynthesisError),
+    // public inputs do not match the verifying key
+    VerifyingKeyMismatch,
+    // reading or writing serialized data failed
+    Serialization(io::Error),
+    // share degree is zero
+    InvalidShareDegree,
+    // shares can not be combined into a secret
+    InvalidShares(&'static str),
+    // identity is not in the membership tree
+    MemberNotFound,
+    // user message limit is zero or does not fit in message limit bits
+    InvalidMessageLimit,
+    // message id is not less than user message limit
+    MessageIdOutOfRange,
+    // id key is not the preimage of the leaf at given index
+    IdKeyMismatch,
+    // leading byte of a tagged proof is not a known format
+    InvalidProofFormat(u8),
+    // parameters file is malformed or its digest does not match
+    InvalidParameters(&'static str),
+    // parameters were generated for another circuit shape
+    ParametersMismatch(&'static str),
+    // contribution of a setup ceremony does not verify
+    InvalidContribution(&'static str),
+    // transaction can not be built or spends a note twice
+    InvalidTransaction(&'static str),
+    // membership event does not continue the synced tree
+    InvalidEvent(&'static str),
+}
+
+impl RLNError {
+    /// stable numeric code of the error for foreign callers, zero is reserved for no error
+    pub fn code(&self) -> u32 {
+        match self {
+            RLNError::InvalidFieldElement => 1,
+            RLNError::IndexOutOfRange(_) => 2,
+            RLNError::SignalHashMismatch => 3,
+            RLNError::Synthesis(_) => 4,
+            RLNError::VerifyingKeyMismatch => 5,
+            RLNError::Serialization(_) => 6,
+            RLNError::InvalidShareDegree => 7,
+            RLNError::InvalidShares(_) => 8,
+            RLNError::MemberNotFound => 9,
+            RLNError::InvalidMessageLimit => 10,
+            RLNError::MessageIdOutOfRange => 11,
+            RLNError::IdKeyMismatch => 12,
+            RLNError::InvalidProofFormat(_) => 13,
+            RLNError::InvalidParameters(_) => 14,
+            RLNError::ParametersMismatch(_) => 15,
+            RLNError::InvalidContribution(_) => 16,
+            RLNError::InvalidTransaction(_) => 17,
+            RLNError::InvalidEvent(_) => 19,
+        }
+    }
+}
+
+impl fmt::Display for RLNError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RLNError::InvalidFieldElement => write!(f, "invalid field element"),
+            RLNError::IndexOutOfRange(index) => write!(f, "index {} is out of range", index),
+            RLNError::SignalHashMismatch => write!(f, "signal hash mismatch"),
+            RLNError::Synthesis(e) => write!(f, "synthesis error: {}", e),
+            RLNError::VerifyingKeyMismatch => write!(f, "verifying key mismatch"),
+            RLNError::Serialization(e) => write!(f, "serialization error: {}", e),
+            RLNError::InvalidShareDegree => write!(f, "share degree must be at least one"),
+            RLNError::InvalidShares(reason) => write!(f, "invalid shares: {}", reason),
+            RLNError::MemberNotFound => write!(f, "identity is not a member"),
+            RLNError::InvalidMessageLimit => write!(f, "user message limit out of range"),
+            RLNError::MessageIdOutOfRange => write!(f, "message id exceeds user message limit"),
+            RLNError::IdKeyMismatch => write!(f, "id key does not match the member at index"),
+            RLNError::InvalidProofFormat(tag) => write!(f, "unknown proof format {}", tag),
+            RLNError::InvalidParameters(reason) => write!(f, "invalid parameters: {}", reason),
+            RLNError::ParametersMismatch(field) => {
+                write!(f, "parameters were generated for another {}", field)
+            }
+            RLNError::InvalidContribution(reason) => write!(f, "invalid contribution: {}", reason),
+            RLNError::InvalidTransaction(reason) => write!(f, "invalid transaction: {}", reason),
+            RLNError::InvalidEvent(reason) => write!(f, "invalid membership event: {}", reason),
+        }
+    }
+}
+
+impl error::Error for RLNError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            RLNError::Synthesis(e) => Some(e),
+            RLNError::Serialization(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for RLNError {
+    fn from(e: io::Error) -> RLNError {
+        RLNError::Serialization(e)
+    }
+}
+
+impl From<SynthesisError> for RLNError {
+    fn from(e: SynthesisError) -> RLNError {
+        match e {
+            SynthesisError::MalformedVerifyingKey => RLNError::VerifyingKeyMismatch,
+            SynthesisError::IoError(e) => RLNError::Serialization(e),
+            e => RLNError::Synthesis(e),
+        }
+    }
+}
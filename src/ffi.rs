@@ -1,5 +1,10 @@
-use crate::{circuit::rln, public::RLN};
+use crate::{
+    circuit::rln,
+    error::RLNError,
+    public::{ParametersCheck, ProofFormat, RLNVerifier, Semaphore, RLN},
+};
 use bellman::pairing::bn256::Bn256;
+use std::cell::RefCell;
 use std::slice;
 
 /// Buffer struct is taken from
@@ -12,8 +17,15 @@ pub struct Buffer {
     pub len: usize,
 }
 
+// an empty slice is passed as a null buffer of zero length
 impl From<&[u8]> for Buffer {
     fn from(src: &[u8]) -> Self {
+        if src.is_empty() {
+            return Self {
+                ptr: std::ptr::null(),
+                len: 0,
+            };
+        }
         Self {
             ptr: &src[0] as *const u8,
             len: src.len(),
@@ -23,6 +35,9 @@ impl From<&[u8]> for Buffer {
 
 impl<'a> From<&Buffer> for &'a [u8] {
     fn from(src: &Buffer) -> &'a [u8] {
+        if src.ptr.is_null() || src.len == 0 {
+            return &[];
+        }
         unsafe { slice::from_raw_parts(src.ptr, src.len) }
     }
 }
@@ -40,6 +55,38 @@ impl Auth {
     }
 }
 
+thread_local! {
+    static LAST_ERROR: RefCell<Option<RLNError>> = RefCell::new(None);
+}
+
+// keeps the error of a failed call on this thread, returns false as the failed call
+fn set_last_error(e: RLNError) -> bool {
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(e));
+    false
+}
+
+/// returns the code of the last error on this thread, 0 if no call has failed
+#[no_mangle]
+pub extern "C" fn last_error_code() -> u32 {
+    LAST_ERROR.with(|last_error| match &*last_error.borrow() {
+        Some(e) => e.code(),
+        None => 0,
+    })
+}
+
+/// writes the message of the last error on this thread as utf8 bytes
+#[no_mangle]
+pub extern "C" fn last_error_message(output_buffer: *mut Buffer) -> bool {
+    let message = LAST_ERROR.with(|last_error| last_error.borrow().as_ref().map(|e| e.to_string()));
+    let output_data = match message {
+        Some(message) => message.into_bytes(),
+        None => return false,
+    };
+    unsafe { *output_buffer = Buffer::from(&output_data[..]) };
+    std::mem::forget(output_data);
+    true
+}
+
 #[no_mangle]
 pub extern "C" fn new_circuit_from_params(
     merkle_depth: usize,
@@ -49,19 +96,68 @@ pub extern "C" fn new_circuit_from_params(
     let buffer = <&[u8]>::from(unsafe { &*parameters_buffer });
     let rln = match RLN::<Bn256>::new_with_raw_params(merkle_depth, buffer, None) {
         Ok(rln) => rln,
-        Err(_) => return false,
+        Err(e) => return set_last_error(e),
+    };
+    unsafe { *ctx = Box::into_raw(Box::new(rln)) };
+    true
+}
+
+/// creates an instance from parameters matching the pinned digest, points are not checked
+/// * `share_degree` is the number of signals per epoch the parameters were generated for
+/// * `digest_buffer` is the 32 bytes digest from `circuit_parameters_digest`
+#[no_mangle]
+pub extern "C" fn new_circuit_from_pinned_params(
+    merkle_depth: usize,
+    share_degree: usize,
+    parameters_buffer: *const Buffer,
+    digest_buffer: *const Buffer,
+    ctx: *mut *mut RLN<Bn256>,
+) -> bool {
+    let buffer = <&[u8]>::from(unsafe { &*parameters_buffer });
+    let digest_data = <&[u8]>::from(unsafe { &*digest_buffer });
+    if digest_data.len() != 32 {
+        return set_last_error(RLNError::InvalidParameters(
+            "pinned digest must be 32 bytes",
+        ));
+    }
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(digest_data);
+    let rln = match RLN::<Bn256>::new_with_checked_params(
+        merkle_depth,
+        share_degree,
+        buffer,
+        None,
+        ParametersCheck::PinnedDigest(digest),
+    ) {
+        Ok(rln) => rln,
+        Err(e) => return set_last_error(e),
     };
     unsafe { *ctx = Box::into_raw(Box::new(rln)) };
     true
 }
 
+#[no_mangle]
+pub extern "C" fn circuit_parameters_digest(
+    ctx: *const RLN<Bn256>,
+    output_buffer: *mut Buffer,
+) -> bool {
+    let rln = unsafe { &*ctx };
+    let output_data = match rln.circuit_parameters_digest() {
+        Ok(digest) => digest.to_vec(),
+        Err(e) => return set_last_error(e),
+    };
+    unsafe { *output_buffer = Buffer::from(&output_data[..]) };
+    std::mem::forget(output_data);
+    true
+}
+
 #[no_mangle]
 pub extern "C" fn get_root(ctx: *const RLN<Bn256>, output_buffer: *mut Buffer) -> bool {
     let rln = unsafe { &*ctx };
     let mut output_data: Vec<u8> = Vec::new();
     match rln.get_root(&mut output_data) {
-        Ok(_) => true,
-        Err(_) => false,
+        Ok(_) => (),
+        Err(e) => return set_last_error(e),
     };
     unsafe { *output_buffer = Buffer::from(&output_data[..]) };
     std::mem::forget(output_data);
@@ -74,7 +170,54 @@ pub extern "C" fn update_next_member(ctx: *mut RLN<Bn256>, input_buffer: *const
     let input_data = <&[u8]>::from(unsafe { &*input_buffer });
     match rln.update_next_member(input_data) {
         Ok(_) => true,
-        Err(_) => false,
+        Err(e) => set_last_error(e),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn append_members(ctx: *mut RLN<Bn256>, input_buffer: *const Buffer) -> bool {
+    let rln = unsafe { &mut *ctx };
+    let input_data = <&[u8]>::from(unsafe { &*input_buffer });
+    match rln.append_members(input_data) {
+        Ok(_) => true,
+        Err(e) => set_last_error(e),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn set_members(
+    ctx: *mut RLN<Bn256>,
+    start_index: usize,
+    input_buffer: *const Buffer,
+) -> bool {
+    let rln = unsafe { &mut *ctx };
+    let input_data = <&[u8]>::from(unsafe { &*input_buffer });
+    match rln.set_members(start_index, input_data) {
+        Ok(_) => true,
+        Err(e) => set_last_error(e),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn export_tree(ctx: *const RLN<Bn256>, output_buffer: *mut Buffer) -> bool {
+    let rln = unsafe { &*ctx };
+    let mut output_data: Vec<u8> = Vec::new();
+    match rln.export_tree(&mut output_data) {
+        Ok(_) => (),
+        Err(e) => return set_last_error(e),
+    };
+    unsafe { *output_buffer = Buffer::from(&output_data[..]) };
+    std::mem::forget(output_data);
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn import_tree(ctx: *mut RLN<Bn256>, input_buffer: *const Buffer) -> bool {
+    let rln = unsafe { &mut *ctx };
+    let input_data = <&[u8]>::from(unsafe { &*input_buffer });
+    match rln.import_tree(input_data) {
+        Ok(_) => true,
+        Err(e) => set_last_error(e),
     }
 }
 
@@ -83,10 +226,32 @@ pub extern "C" fn delete_member(ctx: *mut RLN<Bn256>, index: usize) -> bool {
     let rln = unsafe { &mut *ctx };
     match rln.delete_member(index) {
         Ok(_) => true,
-        Err(_) => false,
+        Err(e) => set_last_error(e),
     }
 }
 
+#[no_mangle]
+pub extern "C" fn find_member_index(
+    ctx: *const RLN<Bn256>,
+    input_buffer: *const Buffer,
+    index_ptr: *mut usize,
+) -> bool {
+    let rln = unsafe { &*ctx };
+    let input_data = <&[u8]>::from(unsafe { &*input_buffer });
+    match rln.find_member_index(input_data) {
+        Ok(index) => unsafe { *index_ptr = index },
+        Err(e) => return set_last_error(e),
+    };
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn set_root_window(ctx: *mut RLN<Bn256>, size: usize) -> bool {
+    let rln = unsafe { &mut *ctx };
+    rln.set_root_window(size);
+    true
+}
+
 #[no_mangle]
 pub extern "C" fn generate_proof(
     ctx: *const RLN<Bn256>,
@@ -99,7 +264,32 @@ pub extern "C" fn generate_proof(
 
     match rln.generate_proof(input_data, &mut output_data) {
         Ok(proof_data) => proof_data,
-        Err(_) => return false,
+        Err(e) => return set_last_error(e),
+    };
+    unsafe { *output_buffer = Buffer::from(&output_data[..]) };
+    std::mem::forget(output_data);
+    true
+}
+
+/// * `format` is 0 for uncompressed and 1 for compressed proof
+#[no_mangle]
+pub extern "C" fn generate_tagged_proof(
+    ctx: *const RLN<Bn256>,
+    input_buffer: *const Buffer,
+    format: u8,
+    output_buffer: *mut Buffer,
+) -> bool {
+    let rln = unsafe { &*ctx };
+    let input_data = <&[u8]>::from(unsafe { &*input_buffer });
+    let format = match ProofFormat::from_tag(format) {
+        Ok(format) => format,
+        Err(e) => return set_last_error(e),
+    };
+    let mut output_data: Vec<u8> = Vec::new();
+
+    match rln.generate_tagged_proof(input_data, format, &mut output_data) {
+        Ok(proof_data) => proof_data,
+        Err(e) => return set_last_error(e),
     };
     unsafe { *output_buffer = Buffer::from(&output_data[..]) };
     std::mem::forget(output_data);
@@ -116,7 +306,7 @@ pub extern "C" fn verify(
     let proof_data = <&[u8]>::from(unsafe { &*proof_buffer });
     if match rln.verify(proof_data) {
         Ok(verified) => verified,
-        Err(_) => return false,
+        Err(e) => return set_last_error(e),
     } {
         unsafe { *result_ptr = 0 };
     } else {
@@ -125,6 +315,123 @@ pub extern "C" fn verify(
     true
 }
 
+/// * expect `proof_buffer` as the output of `generate_tagged_proof` followed by the signal
+/// * `result_ptr` is 0 if verified and 1 otherwise
+#[no_mangle]
+pub extern "C" fn verify_tagged(
+    ctx: *const RLN<Bn256>,
+    proof_buffer: *const Buffer,
+    result_ptr: *mut u32,
+) -> bool {
+    let rln = unsafe { &*ctx };
+    let proof_data = <&[u8]>::from(unsafe { &*proof_buffer });
+    if match rln.verify_tagged(proof_data) {
+        Ok(verified) => verified,
+        Err(e) => return set_last_error(e),
+    } {
+        unsafe { *result_ptr = 0 };
+    } else {
+        unsafe { *result_ptr = 1 };
+    };
+    true
+}
+
+/// * `output_buffer` has a byte per proof, 0 if verified and 1 otherwise
+#[no_mangle]
+pub extern "C" fn verify_batch(
+    ctx: *const RLN<Bn256>,
+    proofs_buffer: *const Buffer,
+    output_buffer: *mut Buffer,
+) -> bool {
+    let rln = unsafe { &*ctx };
+    let proofs_data = <&[u8]>::from(unsafe { &*proofs_buffer });
+    let results = match rln.verify_batch(proofs_data) {
+        Ok(results) => results,
+        Err(e) => return set_last_error(e),
+    };
+    let output_data: Vec<u8> = results
+        .into_iter()
+        .map(|verified| if verified { 0 } else { 1 })
+        .collect();
+    unsafe { *output_buffer = Buffer::from(&output_data[..]) };
+    std::mem::forget(output_data);
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn new_verifier_from_key(
+    verifier_key_buffer: *const Buffer,
+    ctx: *mut *mut RLNVerifier<Bn256>,
+) -> bool {
+    let buffer = <&[u8]>::from(unsafe { &*verifier_key_buffer });
+    let verifier = match RLNVerifier::<Bn256>::new_with_raw_verifier_key(buffer) {
+        Ok(verifier) => verifier,
+        Err(e) => return set_last_error(e),
+    };
+    unsafe { *ctx = Box::into_raw(Box::new(verifier)) };
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn verifier_verify(
+    ctx: *const RLNVerifier<Bn256>,
+    proof_buffer: *const Buffer,
+    result_ptr: *mut u32,
+) -> bool {
+    let verifier = unsafe { &*ctx };
+    let proof_data = <&[u8]>::from(unsafe { &*proof_buffer });
+    if match verifier.verify(proof_data) {
+        Ok(verified) => verified,
+        Err(e) => return set_last_error(e),
+    } {
+        unsafe { *result_ptr = 0 };
+    } else {
+        unsafe { *result_ptr = 1 };
+    };
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn verifier_verify_tagged(
+    ctx: *const RLNVerifier<Bn256>,
+    proof_buffer: *const Buffer,
+    result_ptr: *mut u32,
+) -> bool {
+    let verifier = unsafe { &*ctx };
+    let proof_data = <&[u8]>::from(unsafe { &*proof_buffer });
+    if match verifier.verify_tagged(proof_data) {
+        Ok(verified) => verified,
+        Err(e) => return set_last_error(e),
+    } {
+        unsafe { *result_ptr = 0 };
+    } else {
+        unsafe { *result_ptr = 1 };
+    };
+    true
+}
+
+/// * `output_buffer` has a byte per proof, 0 if verified and 1 otherwise
+#[no_mangle]
+pub extern "C" fn verifier_verify_batch(
+    ctx: *const RLNVerifier<Bn256>,
+    proofs_buffer: *const Buffer,
+    output_buffer: *mut Buffer,
+) -> bool {
+    let verifier = unsafe { &*ctx };
+    let proofs_data = <&[u8]>::from(unsafe { &*proofs_buffer });
+    let results = match verifier.verify_batch(proofs_data) {
+        Ok(results) => results,
+        Err(e) => return set_last_error(e),
+    };
+    let output_data: Vec<u8> = results
+        .into_iter()
+        .map(|verified| if verified { 0 } else { 1 })
+        .collect();
+    unsafe { *output_buffer = Buffer::from(&output_data[..]) };
+    std::mem::forget(output_data);
+    true
+}
+
 #[no_mangle]
 pub extern "C" fn signal_to_field(
     ctx: *const RLN<Bn256>,
@@ -137,7 +444,7 @@ pub extern "C" fn signal_to_field(
     let mut output_data: Vec<u8> = Vec::new();
     match rln.signal_to_field(input_data, &mut output_data) {
         Ok(output_data) => output_data,
-        Err(_) => return false,
+        Err(e) => return set_last_error(e),
     };
     unsafe { *output_buffer = Buffer::from(&output_data[..]) };
     std::mem::forget(output_data);
@@ -150,7 +457,129 @@ pub extern "C" fn key_gen(ctx: *const RLN<Bn256>, input_buffer: *mut Buffer) ->
     let mut output_data: Vec<u8> = Vec::new();
     match rln.key_gen(&mut output_data) {
         Ok(_) => (),
-        Err(_) => return false,
+        Err(e) => return set_last_error(e),
+    }
+    unsafe { *input_buffer = Buffer::from(&output_data[..]) };
+    std::mem::forget(output_data);
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn new_semaphore_from_params(
+    merkle_depth: usize,
+    parameters_buffer: *const Buffer,
+    ctx: *mut *mut Semaphore<Bn256>,
+) -> bool {
+    let buffer = <&[u8]>::from(unsafe { &*parameters_buffer });
+    let semaphore = match Semaphore::<Bn256>::new_with_raw_params(merkle_depth, buffer, None) {
+        Ok(semaphore) => semaphore,
+        Err(e) => return set_last_error(e),
+    };
+    unsafe { *ctx = Box::into_raw(Box::new(semaphore)) };
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn semaphore_get_root(
+    ctx: *const Semaphore<Bn256>,
+    output_buffer: *mut Buffer,
+) -> bool {
+    let semaphore = unsafe { &*ctx };
+    let mut output_data: Vec<u8> = Vec::new();
+    match semaphore.get_root(&mut output_data) {
+        Ok(_) => (),
+        Err(e) => return set_last_error(e),
+    };
+    unsafe { *output_buffer = Buffer::from(&output_data[..]) };
+    std::mem::forget(output_data);
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn semaphore_update_next_member(
+    ctx: *mut Semaphore<Bn256>,
+    input_buffer: *const Buffer,
+) -> bool {
+    let semaphore = unsafe { &mut *ctx };
+    let input_data = <&[u8]>::from(unsafe { &*input_buffer });
+    match semaphore.update_next_member(input_data) {
+        Ok(_) => true,
+        Err(e) => set_last_error(e),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn semaphore_delete_member(ctx: *mut Semaphore<Bn256>, index: usize) -> bool {
+    let semaphore = unsafe { &mut *ctx };
+    match semaphore.delete_member(index) {
+        Ok(_) => true,
+        Err(e) => set_last_error(e),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn semaphore_find_member_index(
+    ctx: *const Semaphore<Bn256>,
+    input_buffer: *const Buffer,
+    index_ptr: *mut usize,
+) -> bool {
+    let semaphore = unsafe { &*ctx };
+    let input_data = <&[u8]>::from(unsafe { &*input_buffer });
+    match semaphore.find_member_index(input_data) {
+        Ok(index) => unsafe { *index_ptr = index },
+        Err(e) => return set_last_error(e),
+    };
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn semaphore_generate_proof(
+    ctx: *const Semaphore<Bn256>,
+    input_buffer: *const Buffer,
+    output_buffer: *mut Buffer,
+) -> bool {
+    let semaphore = unsafe { &*ctx };
+    let input_data = <&[u8]>::from(unsafe { &*input_buffer });
+    let mut output_data: Vec<u8> = Vec::new();
+
+    match semaphore.generate_proof(input_data, &mut output_data) {
+        Ok(proof_data) => proof_data,
+        Err(e) => return set_last_error(e),
+    };
+    unsafe { *output_buffer = Buffer::from(&output_data[..]) };
+    std::mem::forget(output_data);
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn semaphore_verify(
+    ctx: *const Semaphore<Bn256>,
+    proof_buffer: *const Buffer,
+    result_ptr: *mut u32,
+) -> bool {
+    let semaphore = unsafe { &*ctx };
+    let proof_data = <&[u8]>::from(unsafe { &*proof_buffer });
+    if match semaphore.verify(proof_data) {
+        Ok(verified) => verified,
+        Err(e) => return set_last_error(e),
+    } {
+        unsafe { *result_ptr = 0 };
+    } else {
+        unsafe { *result_ptr = 1 };
+    };
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn semaphore_key_gen(
+    ctx: *const Semaphore<Bn256>,
+    input_buffer: *mut Buffer,
+) -> bool {
+    let semaphore = unsafe { &*ctx };
+    let mut output_data: Vec<u8> = Vec::new();
+    match semaphore.key_gen(&mut output_data) {
+        Ok(_) => (),
+        Err(e) => return set_last_error(e),
     }
     unsafe { *input_buffer = Buffer::from(&output_data[..]) };
     std::mem::forget(output_data);
@@ -277,6 +706,12 @@ mod tests {
             // generate proof
             let mut proof_buffer = MaybeUninit::<Buffer>::uninit();
             let success = generate_proof(rln_pointer, input_buffer, proof_buffer.as_mut_ptr());
+            if fail {
+                // id key is not the preimage of the leaf at given index
+                assert!(!success, "proof generation with wrong index should fail");
+                assert_eq!(last_error_code(), RLNError::IdKeyMismatch.code());
+                return;
+            }
             assert!(success, "proof generation call failed");
             let proof_buffer = unsafe { proof_buffer.assume_init() };
 
@@ -294,13 +729,18 @@ mod tests {
             let result_ptr = &mut result as *mut u32;
             let success = verify(rln_pointer, input_buffer, result_ptr);
             assert!(success, "verification call failed");
-            if fail {
-                assert_eq!(1, result);
-            } else {
-                assert_eq!(0, result);
-            }
+            assert_eq!(0, result);
         };
 
+        // lookup index of the member
+        let mut input_data: Vec<u8> = Vec::new();
+        public_key.into_repr().write_le(&mut input_data).unwrap();
+        let input_buffer = &Buffer::from(input_data.as_ref());
+        let mut member_index = 0usize;
+        let success = find_member_index(rln_pointer, input_buffer, &mut member_index as *mut usize);
+        assert!(success, "member lookup call failed");
+        assert_eq!(index, member_index);
+
         gen_proof_and_verify(rln_pointer, false);
         gen_proof_and_verify(rln_pointer, true);
 
@@ -312,6 +752,190 @@ mod tests {
         gen_proof_and_verify(rln_pointer, true);
     }
 
+    #[test]
+    fn test_verifier_ffi() {
+        let rln_test = rln_test();
+        let mut verifier_key: Vec<u8> = Vec::new();
+        rln_test.rln.export_verifier_key(&mut verifier_key).unwrap();
+        let verifier_key_buffer = &Buffer::from(verifier_key.as_ref());
+        let mut verifier_pointer = MaybeUninit::<*mut RLNVerifier<Bn256>>::uninit();
+        let success = new_verifier_from_key(verifier_key_buffer, verifier_pointer.as_mut_ptr());
+        assert!(success, "cannot init verifier instance");
+        let verifier_pointer = unsafe { &*verifier_pointer.assume_init() };
+
+        // the test member is inserted at index 0 with secret 1001
+        let epoch = Fr::from_str("7").unwrap();
+        let signal = b"rln signal test xyz abc";
+        let mut input_data: Vec<u8> = Vec::new();
+        Fr::from_str("1001")
+            .unwrap()
+            .into_repr()
+            .write_le(&mut input_data)
+            .unwrap();
+        input_data.write_u64::<LittleEndian>(0).unwrap();
+        epoch.into_repr().write_le(&mut input_data).unwrap();
+        input_data
+            .write_u64::<LittleEndian>(signal.len() as u64)
+            .unwrap();
+        input_data.write(&signal[..]).unwrap();
+        let mut proof_data: Vec<u8> = Vec::new();
+        rln_test
+            .rln
+            .generate_proof(input_data.as_slice(), &mut proof_data)
+            .unwrap();
+        proof_data
+            .write_u64::<LittleEndian>(signal.len() as u64)
+            .unwrap();
+        proof_data.write(&signal[..]).unwrap();
+
+        let proof_buffer = &Buffer::from(proof_data.as_ref());
+        let mut result = 1u32;
+        let success = verifier_verify(verifier_pointer, proof_buffer, &mut result as *mut u32);
+        assert!(success, "verification call failed");
+        assert_eq!(0, result);
+
+        // batch of the valid proof and a proof with tampered share y
+        let mut tampered = proof_data.clone();
+        tampered[256 + 32 * 3] ^= 1;
+        let mut batch_data: Vec<u8> = Vec::new();
+        batch_data.write_u64::<LittleEndian>(2).unwrap();
+        batch_data.write(&proof_data[..]).unwrap();
+        batch_data.write(&tampered[..]).unwrap();
+        let batch_buffer = &Buffer::from(batch_data.as_ref());
+        let mut result_buffer = MaybeUninit::<Buffer>::uninit();
+        let success =
+            verifier_verify_batch(verifier_pointer, batch_buffer, result_buffer.as_mut_ptr());
+        assert!(success, "batch verification call failed");
+        let result_buffer = unsafe { result_buffer.assume_init() };
+        assert_eq!(<&[u8]>::from(&result_buffer), &[0u8, 1u8][..]);
+
+        // count beyond the input fails to read instead of allocating for it
+        let mut batch_data: Vec<u8> = Vec::new();
+        batch_data.write_u64::<LittleEndian>(u64::MAX).unwrap();
+        batch_data.write(&proof_data[..]).unwrap();
+        let batch_buffer = &Buffer::from(batch_data.as_ref());
+        let mut result_buffer = MaybeUninit::<Buffer>::uninit();
+        let success =
+            verifier_verify_batch(verifier_pointer, batch_buffer, result_buffer.as_mut_ptr());
+        assert!(!success, "batch with a count beyond the input should fail");
+    }
+
+    #[test]
+    fn test_empty_batch_ffi() {
+        let rln_test = rln_test();
+        let mut circuit_parameters: Vec<u8> = Vec::new();
+        rln_test
+            .export_circuit_parameters(&mut circuit_parameters)
+            .unwrap();
+        let rln_pointer = unsafe { &*rln_pointer(circuit_parameters).assume_init() };
+        let mut verifier_key: Vec<u8> = Vec::new();
+        rln_test.rln.export_verifier_key(&mut verifier_key).unwrap();
+        let mut verifier_pointer = MaybeUninit::<*mut RLNVerifier<Bn256>>::uninit();
+        let success = new_verifier_from_key(
+            &Buffer::from(verifier_key.as_ref()),
+            verifier_pointer.as_mut_ptr(),
+        );
+        assert!(success, "cannot init verifier instance");
+        let verifier_pointer = unsafe { &*verifier_pointer.assume_init() };
+
+        let mut batch_data: Vec<u8> = Vec::new();
+        batch_data.write_u64::<LittleEndian>(0).unwrap();
+        let batch_buffer = &Buffer::from(batch_data.as_ref());
+
+        let mut result_buffer = MaybeUninit::<Buffer>::uninit();
+        let success = verify_batch(rln_pointer, batch_buffer, result_buffer.as_mut_ptr());
+        assert!(success, "batch verification call failed");
+        let result_buffer = unsafe { result_buffer.assume_init() };
+        assert_eq!(result_buffer.len, 0);
+        assert!(result_buffer.ptr.is_null());
+        assert!(<&[u8]>::from(&result_buffer).is_empty());
+
+        let mut result_buffer = MaybeUninit::<Buffer>::uninit();
+        let success =
+            verifier_verify_batch(verifier_pointer, batch_buffer, result_buffer.as_mut_ptr());
+        assert!(success, "batch verification call failed");
+        let result_buffer = unsafe { result_buffer.assume_init() };
+        assert_eq!(result_buffer.len, 0);
+        assert!(<&[u8]>::from(&result_buffer).is_empty());
+    }
+
+    #[test]
+    fn test_tagged_proof_ffi() {
+        let rln_test = rln_test();
+        let mut circuit_parameters: Vec<u8> = Vec::new();
+        rln_test
+            .export_circuit_parameters(&mut circuit_parameters)
+            .unwrap();
+        let rln_pointer = unsafe { &mut *rln_pointer(circuit_parameters).assume_init() };
+
+        // insert the test member at index 0 with secret 1001
+        let id_key = Fr::from_str("1001").unwrap();
+        let mut member_data: Vec<u8> = Vec::new();
+        rln_test
+            .hasher()
+            .hash(vec![id_key])
+            .into_repr()
+            .write_le(&mut member_data)
+            .unwrap();
+        let success = update_next_member(rln_pointer, &Buffer::from(member_data.as_ref()));
+        assert!(success, "update with new pubkey call failed");
+
+        let signal = b"rln signal test xyz abc";
+        let mut input_data: Vec<u8> = Vec::new();
+        id_key.into_repr().write_le(&mut input_data).unwrap();
+        input_data.write_u64::<LittleEndian>(0).unwrap();
+        Fr::from_str("7")
+            .unwrap()
+            .into_repr()
+            .write_le(&mut input_data)
+            .unwrap();
+        input_data
+            .write_u64::<LittleEndian>(signal.len() as u64)
+            .unwrap();
+        input_data.write(&signal[..]).unwrap();
+        let input_buffer = &Buffer::from(input_data.as_ref());
+
+        for (tag, proof_len) in [(0u8, 256usize), (1u8, 128usize)].iter() {
+            let mut proof_buffer = MaybeUninit::<Buffer>::uninit();
+            let success =
+                generate_tagged_proof(rln_pointer, input_buffer, *tag, proof_buffer.as_mut_ptr());
+            assert!(success, "tagged proof generation call failed");
+            let proof_buffer = unsafe { proof_buffer.assume_init() };
+            let mut proof_data = <&[u8]>::from(&proof_buffer).to_vec();
+            assert_eq!(proof_data[0], *tag);
+            assert_eq!(proof_data.len(), 1 + proof_len + 32 * 5);
+            proof_data
+                .write_u64::<LittleEndian>(signal.len() as u64)
+                .unwrap();
+            proof_data.write(&signal[..]).unwrap();
+
+            let mut result = 1u32;
+            let success = verify_tagged(
+                rln_pointer,
+                &Buffer::from(proof_data.as_ref()),
+                &mut result as *mut u32,
+            );
+            assert!(success, "tagged verification call failed");
+            assert_eq!(0, result);
+
+            // tampered share y
+            proof_data[1 + proof_len + 32 * 3] ^= 1;
+            let success = verify_tagged(
+                rln_pointer,
+                &Buffer::from(proof_data.as_ref()),
+                &mut result as *mut u32,
+            );
+            assert!(success, "tagged verification call failed");
+            assert_eq!(1, result);
+        }
+
+        let mut proof_buffer = MaybeUninit::<Buffer>::uninit();
+        let success =
+            generate_tagged_proof(rln_pointer, input_buffer, 2, proof_buffer.as_mut_ptr());
+        assert!(!success, "unknown proof format should fail");
+        assert_eq!(last_error_code(), RLNError::InvalidProofFormat(2).code());
+    }
+
     #[test]
     fn test_signal_to_field_ffi() {
         let rln_test = rln_test();
@@ -377,6 +1001,268 @@ mod tests {
         assert_eq!(public, expected_public);
     }
 
+    #[test]
+    fn test_last_error_ffi() {
+        let rln_test = rln_test();
+        let mut circuit_parameters: Vec<u8> = Vec::new();
+        rln_test
+            .export_circuit_parameters(&mut circuit_parameters)
+            .unwrap();
+        let rln_pointer = rln_pointer(circuit_parameters);
+        let rln_pointer = unsafe { &mut *rln_pointer.assume_init() };
+
+        let set_size = 1 << merkle_depth();
+        let success = delete_member(rln_pointer, set_size);
+        assert!(!success, "deleting out of range member should fail");
+        assert_eq!(
+            last_error_code(),
+            RLNError::IndexOutOfRange(set_size).code()
+        );
+
+        let mut message_buffer = MaybeUninit::<Buffer>::uninit();
+        let success = last_error_message(message_buffer.as_mut_ptr());
+        assert!(success, "no error message");
+        let message_buffer = unsafe { message_buffer.assume_init() };
+        let message = <&[u8]>::from(&message_buffer);
+        assert_eq!(
+            message,
+            RLNError::IndexOutOfRange(set_size).to_string().as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_append_members_ffi() {
+        let rln_test = rln_test();
+        let mut circuit_parameters: Vec<u8> = Vec::new();
+        rln_test
+            .export_circuit_parameters(&mut circuit_parameters)
+            .unwrap();
+        let sequential = unsafe { &mut *rln_pointer(circuit_parameters.clone()).assume_init() };
+        let batch = unsafe { &mut *rln_pointer(circuit_parameters).assume_init() };
+
+        let mut rng = XorShiftRng::from_seed([0x3dbe6258, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let mut members: Vec<u8> = Vec::new();
+        members.write_u64::<LittleEndian>(5).unwrap();
+        for _ in 0..5 {
+            let mut member: Vec<u8> = Vec::new();
+            Fr::rand(&mut rng)
+                .into_repr()
+                .write_le(&mut member)
+                .unwrap();
+            let success = update_next_member(sequential, &Buffer::from(member.as_ref()));
+            assert!(success, "update with new pubkey failed");
+            members.extend_from_slice(&member);
+        }
+        let success = append_members(batch, &Buffer::from(members.as_ref()));
+        assert!(success, "append members failed");
+
+        let root = |rln_pointer: *const RLN<Bn256>| {
+            let mut root_buffer = MaybeUninit::<Buffer>::uninit();
+            let success = get_root(rln_pointer, root_buffer.as_mut_ptr());
+            assert!(success, "get root call failed");
+            let root_buffer = unsafe { root_buffer.assume_init() };
+            <&[u8]>::from(&root_buffer).to_vec()
+        };
+        assert_eq!(root(sequential), root(batch));
+
+        // rewriting the members leaves the root unchanged
+        let success = set_members(batch, 0, &Buffer::from(members.as_ref()));
+        assert!(success, "set members failed");
+        assert_eq!(root(sequential), root(batch));
+
+        let success = set_members(batch, 6, &Buffer::from(members.as_ref()));
+        assert!(!success, "members after a gap should fail");
+    }
+
+    #[test]
+    fn test_tree_snapshot_ffi() {
+        let rln_test = rln_test();
+        let mut circuit_parameters: Vec<u8> = Vec::new();
+        rln_test
+            .export_circuit_parameters(&mut circuit_parameters)
+            .unwrap();
+        let source = unsafe { &mut *rln_pointer(circuit_parameters.clone()).assume_init() };
+        let target = unsafe { &mut *rln_pointer(circuit_parameters).assume_init() };
+
+        let mut rng = XorShiftRng::from_seed([0x3dbe6258, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        for _ in 0..3 {
+            let mut member: Vec<u8> = Vec::new();
+            Fr::rand(&mut rng)
+                .into_repr()
+                .write_le(&mut member)
+                .unwrap();
+            let success = update_next_member(source, &Buffer::from(member.as_ref()));
+            assert!(success, "update with new pubkey failed");
+        }
+        let success = delete_member(source, 1);
+        assert!(success, "delete member failed");
+
+        let mut snapshot_buffer = MaybeUninit::<Buffer>::uninit();
+        let success = export_tree(source, snapshot_buffer.as_mut_ptr());
+        assert!(success, "export tree failed");
+        let snapshot_buffer = unsafe { snapshot_buffer.assume_init() };
+        let success = import_tree(target, &snapshot_buffer);
+        assert!(success, "import tree failed");
+
+        let root = |rln_pointer: *const RLN<Bn256>| {
+            let mut root_buffer = MaybeUninit::<Buffer>::uninit();
+            let success = get_root(rln_pointer, root_buffer.as_mut_ptr());
+            assert!(success, "get root call failed");
+            let root_buffer = unsafe { root_buffer.assume_init() };
+            <&[u8]>::from(&root_buffer).to_vec()
+        };
+        assert_eq!(root(source), root(target));
+
+        let snapshot = <&[u8]>::from(&snapshot_buffer);
+        let truncated = &snapshot[..snapshot.len() - 1];
+        let success = import_tree(target, &Buffer::from(truncated));
+        assert!(!success, "truncated snapshot should fail");
+        assert_eq!(root(source), root(target));
+    }
+
+    #[test]
+    fn test_pinned_params_ffi() {
+        let rln_test = rln_test();
+        let mut circuit_parameters: Vec<u8> = Vec::new();
+        rln_test
+            .export_circuit_parameters(&mut circuit_parameters)
+            .unwrap();
+        let rln_pointer = rln_pointer(circuit_parameters.clone());
+        let rln_pointer = unsafe { &*rln_pointer.assume_init() };
+
+        let mut digest_buffer = MaybeUninit::<Buffer>::uninit();
+        let success = circuit_parameters_digest(rln_pointer, digest_buffer.as_mut_ptr());
+        assert!(success, "digest call failed");
+        let digest_buffer = unsafe { digest_buffer.assume_init() };
+
+        let circuit_parameters_buffer = &Buffer::from(circuit_parameters.as_ref());
+        let mut pinned_pointer = MaybeUninit::<*mut RLN<Bn256>>::uninit();
+        let success = new_circuit_from_pinned_params(
+            merkle_depth(),
+            1,
+            circuit_parameters_buffer,
+            &digest_buffer,
+            pinned_pointer.as_mut_ptr(),
+        );
+        assert!(success, "creating from pinned parameters failed");
+
+        let success = new_circuit_from_pinned_params(
+            merkle_depth(),
+            2,
+            circuit_parameters_buffer,
+            &digest_buffer,
+            pinned_pointer.as_mut_ptr(),
+        );
+        assert!(!success, "wrong share degree should fail");
+        assert_eq!(last_error_code(), RLNError::ParametersMismatch("").code());
+
+        // parameters of share degree 2
+        let rln = public::RLN::<Bn256>::new_with_degree(merkle_depth(), 2, None).unwrap();
+        let mut degree_parameters: Vec<u8> = Vec::new();
+        rln.export_circuit_parameters(&mut degree_parameters)
+            .unwrap();
+        let digest = rln.circuit_parameters_digest().unwrap();
+        let success = new_circuit_from_pinned_params(
+            merkle_depth(),
+            2,
+            &Buffer::from(degree_parameters.as_ref()),
+            &Buffer::from(&digest[..]),
+            pinned_pointer.as_mut_ptr(),
+        );
+        assert!(
+            success,
+            "creating from pinned parameters of share degree 2 failed"
+        );
+
+        let wrong_digest = [0u8; 32];
+        let success = new_circuit_from_pinned_params(
+            merkle_depth(),
+            1,
+            circuit_parameters_buffer,
+            &Buffer::from(&wrong_digest[..]),
+            pinned_pointer.as_mut_ptr(),
+        );
+        assert!(!success, "wrong digest should fail");
+        assert_eq!(last_error_code(), RLNError::InvalidParameters("").code());
+    }
+
+    #[test]
+    fn test_semaphore_ffi() {
+        let merkle_depth = merkle_depth();
+        let semaphore = public::Semaphore::<Bn256>::new(merkle_depth, None).unwrap();
+        let mut circuit_parameters: Vec<u8> = Vec::new();
+        semaphore
+            .export_circuit_parameters(&mut circuit_parameters)
+            .unwrap();
+        let circuit_parameters_buffer = &Buffer::from(circuit_parameters.as_ref());
+        let mut semaphore_pointer = MaybeUninit::<*mut Semaphore<Bn256>>::uninit();
+        let success = new_semaphore_from_params(
+            merkle_depth,
+            circuit_parameters_buffer,
+            semaphore_pointer.as_mut_ptr(),
+        );
+        assert!(success, "cannot init semaphore instance");
+        let semaphore_pointer = unsafe { &mut *semaphore_pointer.assume_init() };
+
+        // generate new key pair
+        let mut keypair_buffer = MaybeUninit::<Buffer>::uninit();
+        let success = semaphore_key_gen(semaphore_pointer, keypair_buffer.as_mut_ptr());
+        assert!(success, "key generation call failed");
+        let keypair_buffer = unsafe { keypair_buffer.assume_init() };
+        let keypair_data = <&[u8]>::from(&keypair_buffer);
+        let id_key = &keypair_data[..32];
+        let id_commitment = &keypair_data[32..];
+
+        let success = semaphore_update_next_member(semaphore_pointer, &Buffer::from(id_commitment));
+        assert!(success, "update call failed");
+        let mut index: usize = usize::MAX;
+        let success = semaphore_find_member_index(
+            semaphore_pointer,
+            &Buffer::from(id_commitment),
+            &mut index,
+        );
+        assert!(success, "find member call failed");
+        assert_eq!(index, 0);
+
+        // [ id_key<32> | id_index<8> | external_nullifier<32> | signal_len<8> | signal<var> ]
+        let signal = b"semaphore ffi signal";
+        let mut inputs_data: Vec<u8> = Vec::new();
+        inputs_data.write_all(id_key).unwrap();
+        inputs_data.write_u64::<LittleEndian>(index as u64).unwrap();
+        Fr::from_str("42")
+            .unwrap()
+            .into_repr()
+            .write_le(&mut inputs_data)
+            .unwrap();
+        inputs_data
+            .write_u64::<LittleEndian>(signal.len() as u64)
+            .unwrap();
+        inputs_data.write_all(&signal[..]).unwrap();
+
+        let mut proof_buffer = MaybeUninit::<Buffer>::uninit();
+        let success = semaphore_generate_proof(
+            semaphore_pointer,
+            &Buffer::from(inputs_data.as_ref()),
+            proof_buffer.as_mut_ptr(),
+        );
+        assert!(success, "proof generation failed");
+        let proof_buffer = unsafe { proof_buffer.assume_init() };
+        let mut proof_data = <&[u8]>::from(&proof_buffer).to_vec();
+        proof_data
+            .write_u64::<LittleEndian>(signal.len() as u64)
+            .unwrap();
+        proof_data.write_all(&signal[..]).unwrap();
+
+        let mut result = 0u32;
+        let success = semaphore_verify(
+            semaphore_pointer,
+            &Buffer::from(proof_data.as_ref()),
+            &mut result,
+        );
+        assert!(success, "verification call failed");
+        assert_eq!(0, result);
+    }
+
     #[test]
     #[ignore]
     fn test_parameters_from_file() {
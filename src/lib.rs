@@ -1,11 +1,22 @@
 #![allow(dead_code)]
 #![allow(unused_imports)]
 
+pub mod batch;
 pub mod circuit;
+pub mod error;
 mod hash_to_field;
 pub mod merkle;
+pub mod mpc;
+pub mod nullifier_log;
+pub mod parameters;
 pub mod poseidon;
 pub mod public;
+pub mod snapshot;
+pub mod snarkjs;
+pub mod solidity;
+pub mod sss;
+pub mod storage;
+pub mod sync;
 mod utils;
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -1,20 +1,42 @@
+use crate::error::{RLNError, Result};
+use crate::nullifier_log::fr_key;
 use crate::poseidon::{Poseidon as Hasher, PoseidonParams};
+use crate::storage::{MemoryStorage, MerkleStorage};
+use bellman::multicore::Worker;
 use sapling_crypto::bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
 use sapling_crypto::bellman::pairing::Engine;
 use std::io::{self, Error, ErrorKind};
-use std::{collections::HashMap, hash::Hash};
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    hash::Hash,
+};
 
-enum SyncMode {
-    Bootstarp,
-    Maintain,
+// number of most recent roots a proof may be built against
+pub const DEFAULT_ROOT_HISTORY_SIZE: usize = 5;
+
+// roots of empty subtrees indexed by depth from the root,
+// the last one is the empty leaf
+fn zero_hashes<E: Engine>(hasher: &Hasher<E>, depth: usize) -> Vec<E::Fr> {
+    let mut zero: Vec<E::Fr> = Vec::with_capacity(depth + 1);
+    zero.push(E::Fr::from_str("0").unwrap());
+    for i in 0..depth {
+        zero.push(hasher.hash([zero[i]; 2].to_vec()));
+    }
+    zero.reverse();
+    zero
 }
 
-pub struct IncrementalMerkleTree<E>
+pub struct IncrementalMerkleTree<E, S = MemoryStorage<E>>
 where
     E: Engine,
+    S: MerkleStorage<E>,
 {
     pub current_index: usize,
-    merkle_tree: MerkleTree<E>,
+    pub(crate) merkle_tree: MerkleTree<E, S>,
+    root_history: VecDeque<E::Fr>,
+    root_history_size: usize,
+    // nonzero leaf -> indexes of the inserted leaves equal to it
+    leaf_indexes: HashMap<Vec<u64>, BTreeSet<usize>>,
 }
 
 impl<E> IncrementalMerkleTree<E>
@@ -22,43 +44,180 @@ where
     E: Engine,
 {
     pub fn empty(hasher: Hasher<E>, depth: usize) -> Self {
-        let mut zero: Vec<E::Fr> = Vec::with_capacity(depth + 1);
-        zero.push(E::Fr::from_str("0").unwrap());
-        for i in 0..depth {
-            zero.push(hasher.hash([zero[i]; 2].to_vec()));
-        }
-        zero.reverse();
-        let merkle_tree = MerkleTree {
-            hasher: hasher,
-            zero: zero.clone(),
-            depth: depth,
-            nodes: HashMap::new(),
-        };
-        let current_index: usize = 0;
-        IncrementalMerkleTree {
+        Self::with_storage(hasher, depth, MemoryStorage::new())
+    }
+
+    // writes the nodes of the tree to the empty storage and opens the tree on it
+    pub(crate) fn into_storage<S: MerkleStorage<E>>(
+        self,
+        mut storage: S,
+    ) -> Result<IncrementalMerkleTree<E, S>> {
+        self.merkle_tree.storage.copy_into(&mut storage)?;
+        let mut tree = IncrementalMerkleTree::with_storage(
+            self.merkle_tree.hasher,
+            self.merkle_tree.depth,
+            storage,
+        );
+        tree.set_root_history_size(self.root_history_size);
+        Ok(tree)
+    }
+}
+
+impl<E, S> IncrementalMerkleTree<E, S>
+where
+    E: Engine,
+    S: MerkleStorage<E>,
+{
+    /// opens the tree at the last commit of the storage
+    pub fn with_storage(hasher: Hasher<E>, depth: usize, storage: S) -> Self {
+        let merkle_tree = MerkleTree::with_storage(hasher, depth, storage);
+        let current_index = merkle_tree.storage.next_index();
+        let mut root_history = VecDeque::with_capacity(DEFAULT_ROOT_HISTORY_SIZE);
+        root_history.push_back(merkle_tree.get_root());
+        let mut tree = IncrementalMerkleTree {
             current_index,
             merkle_tree,
+            root_history,
+            root_history_size: DEFAULT_ROOT_HISTORY_SIZE,
+            leaf_indexes: HashMap::new(),
+        };
+        for index in 0..current_index {
+            let leaf = tree.merkle_tree.get_leaf(index);
+            tree.index_leaf(index, leaf);
         }
+        tree
     }
 
-    pub fn update_next(&mut self, leaf: E::Fr) -> io::Result<()> {
-        self.merkle_tree.update(self.current_index, leaf)?;
+    pub fn update_next(&mut self, leaf: E::Fr) -> Result<()> {
+        self.merkle_tree
+            .write_leaf(self.current_index, leaf, self.current_index + 1)?;
+        self.index_leaf(self.current_index, leaf);
         self.current_index += 1;
+        self.push_root();
         Ok(())
     }
 
-    pub fn delete(&mut self, index: usize) -> io::Result<()> {
+    /// appends the leaves after the last inserted leaf, the root is recomputed once
+    pub fn append_many(&mut self, leaves: &[E::Fr]) -> Result<()> {
+        let next_index = self.current_index + leaves.len();
+        self.merkle_tree
+            .write_leaves(self.current_index, leaves, next_index)?;
+        for (i, leaf) in leaves.iter().enumerate() {
+            self.index_leaf(self.current_index + i, *leaf);
+        }
+        self.current_index = next_index;
+        self.push_root();
+        Ok(())
+    }
+
+    // fills the empty tree with the leaves at ascending indexes below `next_index`,
+    // the other leaves are zero
+    pub(crate) fn import_leaves(
+        &mut self,
+        leaves: &[(usize, E::Fr)],
+        next_index: usize,
+    ) -> Result<()> {
+        self.merkle_tree.write_sparse_leaves(leaves, next_index)?;
+        self.leaf_indexes.clear();
+        for (index, leaf) in leaves.iter() {
+            self.index_leaf(*index, *leaf);
+        }
+        self.current_index = next_index;
+        self.reset_root_history();
+        Ok(())
+    }
+
+    /// sets the leaves from `start_index` on, the batch may run past the last inserted leaf
+    /// but may not leave a gap after it
+    pub fn update_batch(&mut self, start_index: usize, leaves: &[E::Fr]) -> Result<()> {
+        if start_index > self.current_index {
+            return Err(RLNError::IndexOutOfRange(start_index));
+        }
+        let next_index = std::cmp::max(self.current_index, start_index + leaves.len());
+        let replaced: Vec<E::Fr> = (start_index..self.current_index)
+            .take(leaves.len())
+            .map(|i| self.merkle_tree.get_leaf(i))
+            .collect();
+        self.merkle_tree
+            .write_leaves(start_index, leaves, next_index)?;
+        for (i, leaf) in replaced.into_iter().enumerate() {
+            self.unindex_leaf(start_index + i, leaf);
+        }
+        for (i, leaf) in leaves.iter().enumerate() {
+            self.index_leaf(start_index + i, *leaf);
+        }
+        self.current_index = next_index;
+        self.push_root();
+        Ok(())
+    }
+
+    pub fn delete(&mut self, index: usize) -> Result<()> {
         let zero = E::Fr::from_str("0").unwrap();
-        self.merkle_tree.update(index, zero)?;
+        let deleted = self.merkle_tree.get_leaf(index);
+        self.merkle_tree
+            .write_leaf(index, zero, self.current_index)?;
+        self.unindex_leaf(index, deleted);
+        self.push_root();
         Ok(())
     }
 
-    pub fn get_witness(&self, index: usize) -> io::Result<Vec<(E::Fr, bool)>> {
+    fn index_leaf(&mut self, index: usize, leaf: E::Fr) {
+        if !leaf.is_zero() {
+            self.leaf_indexes
+                .entry(fr_key::<E>(&leaf))
+                .or_insert_with(BTreeSet::new)
+                .insert(index);
+        }
+    }
+
+    fn unindex_leaf(&mut self, index: usize, leaf: E::Fr) {
+        let key = fr_key::<E>(&leaf);
+        if let Some(indexes) = self.leaf_indexes.get_mut(&key) {
+            indexes.remove(&index);
+            if indexes.is_empty() {
+                self.leaf_indexes.remove(&key);
+            }
+        }
+    }
+
+    fn push_root(&mut self) {
+        self.root_history.push_back(self.merkle_tree.get_root());
+        while self.root_history.len() > self.root_history_size {
+            self.root_history.pop_front();
+        }
+    }
+
+    // forgets the roots before the current one
+    pub(crate) fn reset_root_history(&mut self) {
+        self.root_history.clear();
+        self.root_history.push_back(self.merkle_tree.get_root());
+    }
+
+    /// sets the number of most recent roots kept, at least the current root is kept
+    pub fn set_root_history_size(&mut self, size: usize) {
+        self.root_history_size = size.max(1);
+        while self.root_history.len() > self.root_history_size {
+            self.root_history.pop_front();
+        }
+    }
+
+    pub fn root_history_size(&self) -> usize {
+        self.root_history_size
+    }
+
+    /// returns recent roots from oldest to the current one
+    pub fn recent_roots(&self) -> Vec<E::Fr> {
+        self.root_history.iter().cloned().collect()
+    }
+
+    /// checks if root is one of the recent roots
+    pub fn is_recent_root(&self, root: &E::Fr) -> bool {
+        self.root_history.iter().any(|r| r == root)
+    }
+
+    pub fn get_witness(&self, index: usize) -> Result<Vec<(E::Fr, bool)>> {
         if index >= self.current_index {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "index exceeds incremental index",
-            ));
+            return Err(RLNError::IndexOutOfRange(index));
         }
         self.merkle_tree.get_witness(index)
     }
@@ -67,16 +226,17 @@ where
         self.merkle_tree.hasher.hash(inputs)
     }
 
-    pub fn check_inclusion(
-        &self,
-        witness: Vec<(E::Fr, bool)>,
-        leaf_index: usize,
-    ) -> io::Result<bool> {
+    pub fn hasher(&self) -> &Hasher<E> {
+        &self.merkle_tree.hasher
+    }
+
+    pub fn depth(&self) -> usize {
+        self.merkle_tree.depth
+    }
+
+    pub fn check_inclusion(&self, witness: Vec<(E::Fr, bool)>, leaf_index: usize) -> Result<bool> {
         if leaf_index >= self.current_index {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "index exceeds incremental index",
-            ));
+            return Err(RLNError::IndexOutOfRange(leaf_index));
         }
         self.merkle_tree.check_inclusion(witness, leaf_index)
     }
@@ -84,16 +244,31 @@ where
     pub fn get_root(&self) -> E::Fr {
         return self.merkle_tree.get_root();
     }
+
+    pub fn get_leaf(&self, index: usize) -> Result<E::Fr> {
+        if index >= self.current_index {
+            return Err(RLNError::IndexOutOfRange(index));
+        }
+        Ok(self.merkle_tree.get_leaf(index))
+    }
+
+    /// returns the index of the first inserted leaf equal to `leaf`
+    pub fn leaf_index(&self, leaf: E::Fr) -> Option<usize> {
+        self.leaf_indexes
+            .get(&fr_key::<E>(&leaf))
+            .and_then(|indexes| indexes.iter().next().cloned())
+    }
 }
 
-pub struct MerkleTree<E>
+pub struct MerkleTree<E, S = MemoryStorage<E>>
 where
     E: Engine,
+    S: MerkleStorage<E>,
 {
     pub hasher: Hasher<E>,
     pub depth: usize,
     zero: Vec<E::Fr>,
-    nodes: HashMap<(usize, usize), E::Fr>,
+    storage: S,
 }
 
 impl<E> MerkleTree<E>
@@ -101,17 +276,22 @@ where
     E: Engine,
 {
     pub fn empty(hasher: Hasher<E>, depth: usize) -> Self {
-        let mut zero: Vec<E::Fr> = Vec::with_capacity(depth + 1);
-        zero.push(E::Fr::from_str("0").unwrap());
-        for i in 0..depth {
-            zero.push(hasher.hash([zero[i]; 2].to_vec()));
-        }
-        zero.reverse();
+        Self::with_storage(hasher, depth, MemoryStorage::new())
+    }
+}
+
+impl<E, S> MerkleTree<E, S>
+where
+    E: Engine,
+    S: MerkleStorage<E>,
+{
+    pub fn with_storage(hasher: Hasher<E>, depth: usize, storage: S) -> Self {
+        let zero = zero_hashes(&hasher, depth);
         MerkleTree {
             hasher: hasher,
-            zero: zero.clone(),
+            zero: zero,
             depth: depth,
-            nodes: HashMap::new(),
+            storage,
         }
     }
 
@@ -119,24 +299,97 @@ where
         1 << self.depth
     }
 
-    pub fn update(&mut self, index: usize, leaf: E::Fr) -> io::Result<()> {
+    pub fn update(&mut self, index: usize, leaf: E::Fr) -> Result<()> {
+        let next_index = self.storage.next_index();
+        self.write_leaf(index, leaf, next_index)
+    }
+
+    // updates the leaf and commits the path with the next leaf index of the owner
+    fn write_leaf(&mut self, index: usize, leaf: E::Fr, next_index: usize) -> Result<()> {
         if index >= self.set_size() {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "index exceeds set size",
-            ));
+            return Err(RLNError::IndexOutOfRange(index));
         }
-        self.nodes.insert((self.depth, index), leaf);
+        self.storage.put(self.depth, index, leaf);
         self.recalculate_from(index);
-        Ok(())
+        self.storage.commit(next_index)
+    }
+
+    /// sets consecutive leaves from `start_index` on
+    pub fn update_batch(&mut self, start_index: usize, leaves: &[E::Fr]) -> Result<()> {
+        let next_index = self.storage.next_index();
+        self.write_leaves(start_index, leaves, next_index)
+    }
+
+    // updates consecutive leaves and recomputes every affected node once, level by level,
+    // then commits with the next leaf index of the owner
+    fn write_leaves(&mut self, start: usize, leaves: &[E::Fr], next_index: usize) -> Result<()> {
+        if leaves.is_empty() {
+            return self.storage.commit(next_index);
+        }
+        let end = start + leaves.len() - 1;
+        if end >= self.set_size() {
+            return Err(RLNError::IndexOutOfRange(end));
+        }
+        for (i, leaf) in leaves.iter().enumerate() {
+            self.storage.put(self.depth, start + i, *leaf);
+        }
+
+        let worker = Worker::new();
+        let (mut from, mut to) = (start, end);
+        for depth in (1..=self.depth).rev() {
+            from >>= 1;
+            to >>= 1;
+            let children: Vec<[E::Fr; 2]> = (from..=to)
+                .map(|i| [self.get_node(depth, 2 * i), self.get_node(depth, 2 * i + 1)])
+                .collect();
+            let mut parents = vec![E::Fr::zero(); children.len()];
+            let hasher = &self.hasher;
+            worker.scope(children.len(), |scope, chunk| {
+                for (children, parents) in children.chunks(chunk).zip(parents.chunks_mut(chunk)) {
+                    scope.spawn(move |_| {
+                        for (pair, parent) in children.iter().zip(parents.iter_mut()) {
+                            *parent = hasher.hash(pair.to_vec());
+                        }
+                    });
+                }
+            });
+            for (i, parent) in parents.into_iter().enumerate() {
+                self.storage.put(depth - 1, from + i, parent);
+            }
+        }
+        self.storage.commit(next_index)
+    }
+
+    // updates the leaves at ascending indexes and recomputes every affected node once,
+    // level by level, then commits with the next leaf index of the owner
+    fn write_sparse_leaves(&mut self, leaves: &[(usize, E::Fr)], next_index: usize) -> Result<()> {
+        if let Some((last, _)) = leaves.last() {
+            if *last >= self.set_size() {
+                return Err(RLNError::IndexOutOfRange(*last));
+            }
+        }
+        let mut indexes: Vec<usize> = Vec::with_capacity(leaves.len());
+        for (index, leaf) in leaves.iter() {
+            self.storage.put(self.depth, *index, *leaf);
+            indexes.push(*index);
+        }
+        for depth in (1..=self.depth).rev() {
+            indexes = indexes.into_iter().map(|i| i >> 1).collect();
+            indexes.dedup();
+            for i in indexes.iter() {
+                let parent = self.hasher.hash(vec![
+                    self.get_node(depth, 2 * i),
+                    self.get_node(depth, 2 * i + 1),
+                ]);
+                self.storage.put(depth - 1, *i, parent);
+            }
+        }
+        self.storage.commit(next_index)
     }
 
-    pub fn check_inclusion(&self, witness: Vec<(E::Fr, bool)>, index: usize) -> io::Result<bool> {
+    pub fn check_inclusion(&self, witness: Vec<(E::Fr, bool)>, index: usize) -> Result<bool> {
         if index >= self.set_size() {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "index exceeds set size",
-            ));
+            return Err(RLNError::IndexOutOfRange(index));
         }
         let mut acc = self.get_node(self.depth, index);
 
@@ -154,12 +407,9 @@ where
         return self.get_node(0, 0);
     }
 
-    pub fn get_witness(&self, index: usize) -> io::Result<Vec<(E::Fr, bool)>> {
+    pub fn get_witness(&self, index: usize) -> Result<Vec<(E::Fr, bool)>> {
         if index >= self.set_size() {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "index exceeds set size",
-            ));
+            return Err(RLNError::IndexOutOfRange(index));
         }
         let mut witness = Vec::<(E::Fr, bool)>::with_capacity(self.depth);
         let mut i = index;
@@ -178,11 +428,9 @@ where
     }
 
     fn get_node(&self, depth: usize, index: usize) -> E::Fr {
-        let node = *self
-            .nodes
-            .get(&(depth, index))
-            .unwrap_or_else(|| &self.zero[depth]);
-        node
+        self.storage
+            .get(depth, index)
+            .unwrap_or_else(|| self.zero[depth])
     }
 
     fn get_leaf(&self, index: usize) -> E::Fr {
@@ -202,7 +450,7 @@ where
             let h = self.hash_couple(depth, i);
             i >>= 1;
             depth -= 1;
-            self.nodes.insert((depth, i), h);
+            self.storage.put(depth, i, h);
             if depth == 0 {
                 break;
             }
@@ -212,6 +460,301 @@ where
     }
 }
 
+// Incremental Merkle tree keeping only its frontier
+//
+// The frontier holds, for every level, the last left node waiting for its right sibling,
+// so appending a leaf recomputes its path to the root with O(depth) storage.
+// Marked leaves keep their authentication path, which is updated as leaves arrive.
+// Leaves can only be marked while they are appended.
+//
+// The tree is append-only: written leaves cannot be updated, so it cannot follow
+// membership deletions of `RLN` and must not back a group whose members can be removed.
+
+pub struct FrontierMerkleTree<E>
+where
+    E: Engine,
+{
+    pub hasher: Hasher<E>,
+    pub depth: usize,
+    pub next_index: usize,
+    zero: Vec<E::Fr>,
+    // left nodes indexed by level from the leaves
+    frontier: Vec<E::Fr>,
+    root: E::Fr,
+    // siblings of marked leaves from the leaf up to the root
+    marked: HashMap<usize, Vec<E::Fr>>,
+}
+
+impl<E> FrontierMerkleTree<E>
+where
+    E: Engine,
+{
+    pub fn empty(hasher: Hasher<E>, depth: usize) -> Self {
+        let zero = zero_hashes(&hasher, depth);
+        FrontierMerkleTree {
+            hasher,
+            depth,
+            next_index: 0,
+            frontier: vec![zero[depth]; depth],
+            root: zero[0],
+            zero,
+            marked: HashMap::new(),
+        }
+    }
+
+    pub fn set_size(&self) -> usize {
+        1 << self.depth
+    }
+
+    /// appends the leaf and returns its index
+    pub fn append(&mut self, leaf: E::Fr) -> Result<usize> {
+        self.push(leaf, false)
+    }
+
+    /// appends the leaf and tracks its witness, returns its index
+    pub fn append_marked(&mut self, leaf: E::Fr) -> Result<usize> {
+        self.push(leaf, true)
+    }
+
+    /// stops tracking the witness of the leaf
+    pub fn unmark(&mut self, index: usize) -> Result<()> {
+        self.marked
+            .remove(&index)
+            .map(|_| ())
+            .ok_or(RLNError::MemberNotFound)
+    }
+
+    /// indexes of marked leaves in ascending order
+    pub fn marked_leaves(&self) -> Vec<usize> {
+        let mut indexes: Vec<usize> = self.marked.keys().cloned().collect();
+        indexes.sort();
+        indexes
+    }
+
+    pub fn get_root(&self) -> E::Fr {
+        self.root
+    }
+
+    /// authentication path of a marked leaf in the format of `MerkleTree::get_witness`
+    pub fn get_witness(&self, index: usize) -> Result<Vec<(E::Fr, bool)>> {
+        let siblings = self.marked.get(&index).ok_or(RLNError::MemberNotFound)?;
+        Ok(siblings
+            .iter()
+            .enumerate()
+            .map(|(level, sibling)| (*sibling, (index >> level) & 1 == 0))
+            .collect())
+    }
+
+    fn push(&mut self, leaf: E::Fr, mark: bool) -> Result<usize> {
+        let index = self.next_index;
+        if index >= self.set_size() {
+            return Err(RLNError::IndexOutOfRange(index));
+        }
+        let mut siblings: Vec<E::Fr> = Vec::with_capacity(self.depth);
+        let mut node = leaf;
+        for level in 0..self.depth {
+            let i = index >> level;
+            // marked leaves whose sibling at this level is the updated node
+            for (m, path) in self.marked.iter_mut() {
+                if (m >> level) ^ 1 == i {
+                    path[level] = node;
+                }
+            }
+            let parent = if i & 1 == 0 {
+                let right = self.zero[self.depth - level];
+                self.frontier[level] = node;
+                siblings.push(right);
+                [node, right]
+            } else {
+                let left = self.frontier[level];
+                siblings.push(left);
+                [left, node]
+            };
+            node = self.hasher.hash(parent.to_vec());
+        }
+        if mark {
+            self.marked.insert(index, siblings);
+        }
+        self.root = node;
+        self.next_index += 1;
+        Ok(index)
+    }
+}
+
+// Sparse Merkle tree keyed by field element
+//
+// The slot of a key is given by its bits, the leaf of an entry is H(key, value)
+// and empty slots hold the zero leaf. The tree is as deep as the bit length of the field
+// so that every key has a slot of its own and an absent key always has an empty slot.
+
+pub struct SparseMerkleTree<E>
+where
+    E: Engine,
+{
+    pub hasher: Hasher<E>,
+    pub depth: usize,
+    zero: Vec<E::Fr>,
+    // (depth, path bits from the node to the root) -> node
+    nodes: HashMap<(usize, Vec<bool>), E::Fr>,
+    // slot -> (key, value)
+    entries: HashMap<Vec<bool>, (E::Fr, E::Fr)>,
+}
+
+// siblings of a slot from the leaf up to the root,
+// proves inclusion of an entry or that the slot of a key is empty
+
+#[derive(Clone)]
+pub struct SparseMerkleProof<E>
+where
+    E: Engine,
+{
+    pub siblings: Vec<E::Fr>,
+}
+
+// depth of sparse merkle trees, the bit length of the field
+pub fn sparse_depth<E: Engine>() -> usize {
+    E::Fr::NUM_BITS as usize
+}
+
+// bits of the slot of the key, leaf level first
+pub fn sparse_path<E: Engine>(key: &E::Fr) -> Vec<bool> {
+    let repr = key.into_repr();
+    (0..sparse_depth::<E>())
+        .map(|i| (repr.as_ref()[i / 64] >> (i % 64)) & 1 == 1)
+        .collect()
+}
+
+impl<E> SparseMerkleTree<E>
+where
+    E: Engine,
+{
+    pub fn empty(hasher: Hasher<E>) -> Self {
+        let depth = sparse_depth::<E>();
+        let zero = zero_hashes(&hasher, depth);
+        SparseMerkleTree {
+            hasher,
+            depth,
+            zero,
+            nodes: HashMap::new(),
+            entries: HashMap::new(),
+        }
+    }
+
+    /// inserts or updates the value of a key
+    pub fn insert(&mut self, key: E::Fr, value: E::Fr) -> Result<()> {
+        let path = sparse_path::<E>(&key);
+        let leaf = self.hasher.hash(vec![key, value]);
+        self.entries.insert(path.clone(), (key, value));
+        self.update_slot(&path, leaf);
+        Ok(())
+    }
+
+    pub fn delete(&mut self, key: E::Fr) -> Result<()> {
+        if self.get(&key).is_none() {
+            return Err(RLNError::MemberNotFound);
+        }
+        let path = sparse_path::<E>(&key);
+        self.entries.remove(&path);
+        let zero = self.zero[self.depth];
+        self.update_slot(&path, zero);
+        Ok(())
+    }
+
+    /// returns the value of the key if present
+    pub fn get(&self, key: &E::Fr) -> Option<E::Fr> {
+        let path = sparse_path::<E>(key);
+        match self.entries.get(&path) {
+            Some((other, value)) if other == key => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn get_root(&self) -> E::Fr {
+        self.get_node(0, &[])
+    }
+
+    /// returns siblings of the slot of the key
+    /// * the proof is an inclusion proof if the key is present and a non membership proof otherwise
+    pub fn prove(&self, key: &E::Fr) -> SparseMerkleProof<E> {
+        let path = sparse_path::<E>(key);
+        let siblings = (0..self.depth)
+            .map(|i| self.get_node(self.depth - i, &sibling_path(&path[i..])))
+            .collect();
+        SparseMerkleProof { siblings }
+    }
+
+    fn get_node(&self, depth: usize, path: &[bool]) -> E::Fr {
+        *self
+            .nodes
+            .get(&(depth, path.to_vec()))
+            .unwrap_or_else(|| &self.zero[depth])
+    }
+
+    fn update_slot(&mut self, path: &[bool], leaf: E::Fr) {
+        let mut acc = leaf;
+        self.nodes.insert((self.depth, path.to_vec()), acc);
+        for i in 0..self.depth {
+            let depth = self.depth - i;
+            let sibling = self.get_node(depth, &sibling_path(&path[i..]));
+            acc = if path[i] {
+                self.hasher.hash(vec![sibling, acc])
+            } else {
+                self.hasher.hash(vec![acc, sibling])
+            };
+            self.nodes.insert((depth - 1, path[i + 1..].to_vec()), acc);
+        }
+    }
+}
+
+fn sibling_path(path: &[bool]) -> Vec<bool> {
+    let mut sibling = path.to_vec();
+    sibling[0] = !sibling[0];
+    sibling
+}
+
+impl<E> SparseMerkleProof<E>
+where
+    E: Engine,
+{
+    /// root of the tree with given leaf at the slot of the key
+    /// * none if the proof does not have a sibling per level of the tree
+    pub fn compute_root(&self, hasher: &Hasher<E>, key: &E::Fr, leaf: E::Fr) -> Option<E::Fr> {
+        if self.siblings.len() != sparse_depth::<E>() {
+            return None;
+        }
+        let path = sparse_path::<E>(key);
+        let root =
+            self.siblings
+                .iter()
+                .zip(path.into_iter())
+                .fold(leaf, |acc, (sibling, right)| {
+                    if right {
+                        hasher.hash(vec![*sibling, acc])
+                    } else {
+                        hasher.hash(vec![acc, *sibling])
+                    }
+                });
+        Some(root)
+    }
+
+    /// checks that the key maps to the value in the tree of given root
+    pub fn verify_inclusion(
+        &self,
+        hasher: &Hasher<E>,
+        root: &E::Fr,
+        key: &E::Fr,
+        value: &E::Fr,
+    ) -> bool {
+        let leaf = hasher.hash(vec![*key, *value]);
+        self.compute_root(hasher, key, leaf) == Some(*root)
+    }
+
+    /// checks that the slot of the key is empty in the tree of given root
+    pub fn verify_non_membership(&self, hasher: &Hasher<E>, root: &E::Fr, key: &E::Fr) -> bool {
+        self.compute_root(hasher, key, E::Fr::zero()) == Some(*root)
+    }
+}
+
 #[test]
 fn test_merkle_set() {
     let data: Vec<Fr> = (0..8)
@@ -227,3 +770,188 @@ fn test_merkle_set() {
     let witness = set.get_witness(leaf_index).unwrap();
     assert!(set.check_inclusion(witness, leaf_index).unwrap());
 }
+
+#[test]
+fn test_root_history() {
+    use sapling_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    let params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+    let hasher = Hasher::new(params);
+    let mut tree = IncrementalMerkleTree::empty(hasher, 3);
+    tree.set_root_history_size(2);
+
+    let empty_root = tree.get_root();
+    assert!(tree.is_recent_root(&empty_root));
+
+    tree.update_next(Fr::from_str("1").unwrap()).unwrap();
+    let root_1 = tree.get_root();
+    assert!(tree.is_recent_root(&empty_root));
+
+    tree.delete(0).unwrap();
+    assert!(!tree.is_recent_root(&empty_root));
+    assert!(tree.is_recent_root(&root_1));
+    assert_eq!(tree.recent_roots(), vec![root_1, tree.get_root()]);
+}
+
+#[test]
+fn test_batch_update() {
+    use sapling_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    let params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+    let hasher = Hasher::new(params);
+    let leaves: Vec<Fr> = (1..8)
+        .map(|s| Fr::from_str(&format!("{}", s)).unwrap())
+        .collect();
+
+    let mut sequential = IncrementalMerkleTree::empty(hasher.clone(), 3);
+    let mut batch = IncrementalMerkleTree::empty(hasher.clone(), 3);
+    for leaf in leaves[..5].iter() {
+        sequential.update_next(*leaf).unwrap();
+    }
+    batch.update_next(leaves[0]).unwrap();
+    batch.append_many(&leaves[1..5]).unwrap();
+    assert_eq!(batch.get_root(), sequential.get_root());
+    assert_eq!(batch.current_index, 5);
+
+    // unaligned range running past the last inserted leaf
+    for (i, leaf) in leaves[3..7].iter().enumerate() {
+        sequential.merkle_tree.update(3 + i, *leaf).unwrap();
+    }
+    sequential.current_index = 7;
+    batch.update_batch(3, &leaves[3..7]).unwrap();
+    assert_eq!(batch.get_root(), sequential.get_root());
+    assert_eq!(batch.current_index, 7);
+    assert_eq!(batch.get_leaf(6).unwrap(), leaves[6]);
+
+    assert!(batch.append_many(&leaves[..2]).is_err());
+    assert!(batch.update_batch(8, &leaves[..1]).is_err());
+    assert_eq!(batch.get_root(), sequential.get_root());
+}
+
+#[test]
+fn test_leaf_index() {
+    use sapling_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    let params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+    let hasher = Hasher::new(params);
+    let leaves: Vec<Fr> = (1..5)
+        .map(|s| Fr::from_str(&format!("{}", s)).unwrap())
+        .collect();
+
+    let mut tree = IncrementalMerkleTree::empty(hasher.clone(), 3);
+    tree.update_next(leaves[0]).unwrap();
+    tree.append_many(&[leaves[1], leaves[0]]).unwrap();
+    assert_eq!(tree.leaf_index(leaves[0]), Some(0));
+    assert_eq!(tree.leaf_index(leaves[1]), Some(1));
+    assert_eq!(tree.leaf_index(leaves[2]), None);
+    assert_eq!(tree.leaf_index(Fr::zero()), None);
+
+    // the duplicate is found once the first leaf is deleted
+    tree.delete(0).unwrap();
+    assert_eq!(tree.leaf_index(leaves[0]), Some(2));
+
+    tree.update_batch(1, &[leaves[2], leaves[3]]).unwrap();
+    assert_eq!(tree.leaf_index(leaves[0]), None);
+    assert_eq!(tree.leaf_index(leaves[1]), None);
+    assert_eq!(tree.leaf_index(leaves[3]), Some(2));
+
+    let mut imported = IncrementalMerkleTree::empty(hasher, 3);
+    imported
+        .import_leaves(&[(1, leaves[2]), (2, leaves[3])], 3)
+        .unwrap();
+    assert_eq!(imported.leaf_index(leaves[2]), Some(1));
+    assert_eq!(imported.leaf_index(leaves[3]), Some(2));
+
+    // rebuilt when the tree is opened on its storage
+    let reopened = imported.into_storage(MemoryStorage::new()).unwrap();
+    assert_eq!(reopened.leaf_index(leaves[3]), Some(2));
+    assert_eq!(reopened.leaf_index(leaves[0]), None);
+}
+
+#[test]
+fn test_frontier_merkle_tree() {
+    use sapling_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    let params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+    let hasher = Hasher::new(params);
+    let mut tree = MerkleTree::empty(hasher.clone(), 3);
+    let mut frontier = FrontierMerkleTree::empty(hasher.clone(), 3);
+    assert_eq!(frontier.get_root(), tree.get_root());
+
+    for i in 0..8 {
+        let leaf = Fr::from_str(&format!("{}", i + 1)).unwrap();
+        tree.update(i, leaf).unwrap();
+        let index = if i == 2 || i == 5 {
+            frontier.append_marked(leaf).unwrap()
+        } else {
+            frontier.append(leaf).unwrap()
+        };
+        assert_eq!(index, i);
+        assert_eq!(frontier.get_root(), tree.get_root());
+
+        for m in frontier.marked_leaves() {
+            let witness = frontier.get_witness(m).unwrap();
+            assert_eq!(witness, tree.get_witness(m).unwrap());
+            assert!(tree.check_inclusion(witness, m).unwrap());
+        }
+    }
+    assert_eq!(frontier.marked_leaves(), vec![2, 5]);
+
+    assert!(frontier.append(Fr::from_str("9").unwrap()).is_err());
+    assert!(frontier.get_witness(3).is_err());
+    frontier.unmark(2).unwrap();
+    assert!(frontier.get_witness(2).is_err());
+    assert!(frontier.unmark(2).is_err());
+}
+
+#[test]
+fn test_sparse_merkle_tree() {
+    use sapling_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    let params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+    let hasher = Hasher::new(params);
+    let mut tree = SparseMerkleTree::empty(hasher.clone());
+    let depth = sparse_depth::<Bn256>();
+
+    // empty sparse tree shares zero hashes with the dense tree
+    assert_eq!(tree.get_root(), zero_hashes(&hasher, depth)[0]);
+
+    let key = Fr::from_str("300").unwrap();
+    let value = Fr::from_str("1").unwrap();
+    let absent = Fr::from_str("301").unwrap();
+    tree.insert(key, value).unwrap();
+    tree.insert(Fr::from_str("7").unwrap(), value).unwrap();
+    let root = tree.get_root();
+
+    let proof = tree.prove(&key);
+    assert!(proof.verify_inclusion(&hasher, &root, &key, &value));
+    assert!(!proof.verify_inclusion(&hasher, &root, &key, &Fr::from_str("2").unwrap()));
+    assert!(!proof.verify_non_membership(&hasher, &root, &key));
+
+    // proofs must have a sibling per bit of the key
+    let mut short = proof.clone();
+    let top = short.siblings.pop().unwrap();
+    assert!(short.compute_root(&hasher, &key, Fr::zero()).is_none());
+    let mut long = proof.clone();
+    long.siblings.push(top);
+    assert!(!long.verify_inclusion(&hasher, &root, &key, &value));
+
+    let proof = tree.prove(&absent);
+    assert!(proof.verify_non_membership(&hasher, &root, &absent));
+
+    // 300 and 44 share their lowest 8 bits but not their slot
+    let other = Fr::from_str("44").unwrap();
+    assert!(tree
+        .prove(&other)
+        .verify_non_membership(&hasher, &root, &other));
+    tree.insert(other, value).unwrap();
+    let root = tree.get_root();
+    assert!(tree
+        .prove(&key)
+        .verify_inclusion(&hasher, &root, &key, &value));
+    assert!(tree
+        .prove(&other)
+        .verify_inclusion(&hasher, &root, &other, &value));
+
+    tree.delete(key).unwrap();
+    assert!(tree.get(&key).is_none());
+    assert_eq!(tree.get(&other), Some(value));
+    let proof = tree.prove(&key);
+    assert!(proof.verify_non_membership(&hasher, &tree.get_root(), &key));
+    assert!(tree.delete(key).is_err());
+}
@@ -0,0 +1,29 @@
+// Multi party trusted setup of circuit parameters
+//
+// A ceremony starts from the powers of tau of the circuit domain size, takes contributions
+// in turn and is verified against the circuit and the same powers of tau before it is
+// finalized to parameters. Every step runs offline on files.
+
+mod phase2;
+mod powers_of_tau;
+
+pub use phase2::{domain_size, MPCParameters, PublicKey};
+pub use powers_of_tau::PowersOfTau;
+
+use crate::circuit::poseidon::PoseidonCircuit;
+use crate::circuit::rln::{RLNCircuit, RLNInputs};
+use crate::poseidon::PoseidonParams;
+use bellman::pairing::Engine;
+
+/// circuit of RLN parameters without witness values for the ceremony
+pub fn rln_circuit<E: Engine>(
+    merkle_depth: usize,
+    share_degree: usize,
+    poseidon_params: PoseidonParams<E>,
+) -> RLNCircuit<E> {
+    RLNCircuit {
+        inputs: RLNInputs::empty(merkle_depth),
+        hasher: PoseidonCircuit::new(poseidon_params),
+        degree: share_degree,
+    }
+}
@@ -0,0 +1,654 @@
+use crate::error::{RLNError, Result};
+use crate::mpc::powers_of_tau::{read_point, PowersOfTau};
+use bellman::groth16::{Parameters, VerifyingKey};
+use bellman::multicore::Worker;
+use bellman::pairing::ff::{Field, PrimeField};
+use bellman::pairing::{CurveAffine, CurveProjective, EncodedPoint, Engine};
+use bellman::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+use blake2::{Blake2b, Digest};
+use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
+use rand::{thread_rng, ChaChaRng, Rand, Rng, SeedableRng};
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+// Phase 2 of the groth16 setup
+//
+// Parameters are evaluated from the powers of tau with delta = 1. Each contribution
+// multiplies delta by a secret d and divides the h and l queries by d, and publishes
+// [ delta_after | s | s * d | r * d ] where r is hashed to G2 from the transcript and s.
+// Parameters are secure if at least one contributor discarded its d.
+
+#[derive(Clone)]
+pub struct PublicKey<E>
+where
+    E: Engine,
+{
+    pub delta_after: E::G1Affine,
+    pub s: E::G1Affine,
+    pub s_delta: E::G1Affine,
+    pub r_delta: E::G2Affine,
+    pub transcript: [u8; 64],
+}
+
+impl<E> PublicKey<E>
+where
+    E: Engine,
+{
+    /// * expect `reader` serialized as [ delta_after<64> | s<64> | s_delta<64> | r_delta<128> | transcript<64> ]
+    pub fn read<R: Read>(mut reader: R) -> Result<PublicKey<E>> {
+        let delta_after: E::G1Affine = read_point(&mut reader)?;
+        let s: E::G1Affine = read_point(&mut reader)?;
+        let s_delta: E::G1Affine = read_point(&mut reader)?;
+        let r_delta: E::G2Affine = read_point(&mut reader)?;
+        if delta_after.is_zero() || s.is_zero() || s_delta.is_zero() || r_delta.is_zero() {
+            return Err(RLNError::InvalidContribution("point at infinity"));
+        }
+        let mut transcript = [0u8; 64];
+        reader.read_exact(&mut transcript)?;
+        Ok(PublicKey {
+            delta_after,
+            s,
+            s_delta,
+            r_delta,
+            transcript,
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(self.delta_after.into_uncompressed().as_ref())?;
+        writer.write_all(self.s.into_uncompressed().as_ref())?;
+        writer.write_all(self.s_delta.into_uncompressed().as_ref())?;
+        writer.write_all(self.r_delta.into_uncompressed().as_ref())?;
+        writer.write_all(&self.transcript)?;
+        Ok(())
+    }
+
+    /// hash of the contribution to be published by the contributor
+    pub fn hash(&self) -> Result<[u8; 64]> {
+        let mut sink = HashWriter::new();
+        self.write(&mut sink)?;
+        Ok(sink.into_hash())
+    }
+}
+
+#[derive(Clone)]
+pub struct MPCParameters<E>
+where
+    E: Engine,
+{
+    params: Parameters<E>,
+    cs_hash: [u8; 64],
+    contributions: Vec<PublicKey<E>>,
+}
+
+impl<E> MPCParameters<E>
+where
+    E: Engine,
+{
+    /// evaluates the initial parameters of the circuit from powers of tau
+    /// * expect `powers_of_tau` of the domain size of the circuit, see `domain_size`
+    pub fn new<C: Circuit<E>, R: Read>(circuit: C, powers_of_tau: R) -> Result<MPCParameters<E>> {
+        let assembly = KeypairAssembly::synthesize(circuit)?;
+        let m = assembly.domain_size()?;
+        let powers_of_tau = PowersOfTau::<E>::read(powers_of_tau, m)?;
+
+        let worker = Worker::new();
+        let inputs = eval(&powers_of_tau, &assembly.inputs, &worker);
+        let aux = eval(&powers_of_tau, &assembly.aux, &worker);
+
+        let vk = VerifyingKey {
+            alpha_g1: powers_of_tau.alpha_g1,
+            beta_g1: powers_of_tau.beta_g1,
+            beta_g2: powers_of_tau.beta_g2,
+            gamma_g2: E::G2Affine::one(),
+            delta_g1: E::G1Affine::one(),
+            delta_g2: E::G2Affine::one(),
+            ic: inputs.iter().map(|q| q.ext).collect(),
+        };
+        // points at infinity are filtered away from A and B queries as in the bellman generator
+        let queries = || inputs.iter().chain(aux.iter());
+        let params = Parameters {
+            vk,
+            h: Arc::new(powers_of_tau.h),
+            l: Arc::new(aux.iter().map(|q| q.ext).collect()),
+            a: Arc::new(queries().map(|q| q.a_g1).filter(|p| !p.is_zero()).collect()),
+            b_g1: Arc::new(queries().map(|q| q.b_g1).filter(|p| !p.is_zero()).collect()),
+            b_g2: Arc::new(queries().map(|q| q.b_g2).filter(|p| !p.is_zero()).collect()),
+        };
+
+        let mut sink = HashWriter::new();
+        params.write(&mut sink)?;
+        Ok(MPCParameters {
+            params,
+            cs_hash: sink.into_hash(),
+            contributions: vec![],
+        })
+    }
+
+    /// * expect `reader` serialized as
+    /// [ parameters<var> | cs_hash<64> | contributions_len<8> | contribution<384> * contributions_len ]
+    /// * `checked` enables point checks of the parameters
+    pub fn read<R: Read>(mut reader: R, checked: bool) -> Result<MPCParameters<E>> {
+        let params = Parameters::<E>::read(&mut reader, checked)?;
+        let mut cs_hash = [0u8; 64];
+        reader.read_exact(&mut cs_hash)?;
+        // contributions_len is untrusted, contributions are pushed as they are read
+        let contributions_len = reader.read_u64::<LittleEndian>()?;
+        let mut contributions = Vec::new();
+        for _ in 0..contributions_len {
+            contributions.push(PublicKey::read(&mut reader)?);
+        }
+        Ok(MPCParameters {
+            params,
+            cs_hash,
+            contributions,
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        self.params.write(&mut writer)?;
+        writer.write_all(&self.cs_hash)?;
+        writer.write_u64::<LittleEndian>(self.contributions.len() as u64)?;
+        for pubkey in self.contributions.iter() {
+            pubkey.write(&mut writer)?;
+        }
+        Ok(())
+    }
+
+    /// contributes the secret from `rng` and discards it
+    /// * returns the hash of the contribution
+    pub fn contribute<R: Rng>(&mut self, rng: &mut R) -> Result<[u8; 64]> {
+        let delta = loop {
+            let delta = E::Fr::rand(rng);
+            if !delta.is_zero() {
+                break delta;
+            }
+        };
+        let delta_inv = delta.inverse().unwrap();
+
+        let s = E::G1::rand(rng).into_affine();
+        let s_delta = s.mul(delta.into_repr()).into_affine();
+        let transcript = self.transcript()?;
+        let r = hash_to_g2::<E>(&transcript, &s, &s_delta);
+        let r_delta = r.mul(delta.into_repr()).into_affine();
+
+        let worker = Worker::new();
+        self.params.h = Arc::new(scale(&self.params.h, delta_inv, &worker));
+        self.params.l = Arc::new(scale(&self.params.l, delta_inv, &worker));
+        self.params.vk.delta_g1 = self.params.vk.delta_g1.mul(delta.into_repr()).into_affine();
+        self.params.vk.delta_g2 = self.params.vk.delta_g2.mul(delta.into_repr()).into_affine();
+
+        let pubkey = PublicKey {
+            delta_after: self.params.vk.delta_g1,
+            s,
+            s_delta,
+            r_delta,
+            transcript,
+        };
+        let hash = pubkey.hash()?;
+        self.contributions.push(pubkey);
+        Ok(hash)
+    }
+
+    /// verifies every contribution against the initial parameters of the circuit
+    /// * returns the hashes of the contributions in order
+    pub fn verify<C: Circuit<E>, R: Read>(
+        &self,
+        circuit: C,
+        powers_of_tau: R,
+    ) -> Result<Vec<[u8; 64]>> {
+        let initial = MPCParameters::<E>::new(circuit, powers_of_tau)?;
+        if initial.cs_hash != self.cs_hash {
+            return Err(RLNError::InvalidContribution("circuit does not match"));
+        }
+        let (before, after) = (&initial.params, &self.params);
+        if before.vk.alpha_g1 != after.vk.alpha_g1
+            || before.vk.beta_g1 != after.vk.beta_g1
+            || before.vk.beta_g2 != after.vk.beta_g2
+            || before.vk.gamma_g2 != after.vk.gamma_g2
+            || before.vk.ic != after.vk.ic
+            || before.a != after.a
+            || before.b_g1 != after.b_g1
+            || before.b_g2 != after.b_g2
+            || before.h.len() != after.h.len()
+            || before.l.len() != after.l.len()
+        {
+            return Err(RLNError::InvalidContribution(
+                "parameters other than delta were changed",
+            ));
+        }
+
+        let mut sink = HashWriter::new();
+        sink.write_all(&self.cs_hash)?;
+        let mut current_delta = E::G1Affine::one();
+        let mut hashes = Vec::with_capacity(self.contributions.len());
+        for pubkey in self.contributions.iter() {
+            if pubkey.transcript != sink.clone().into_hash() {
+                return Err(RLNError::InvalidContribution("transcript mismatch"));
+            }
+            let r = hash_to_g2::<E>(&pubkey.transcript, &pubkey.s, &pubkey.s_delta);
+            // contributor knows delta
+            if !same_ratio((pubkey.s, pubkey.s_delta), (r, pubkey.r_delta)) {
+                return Err(RLNError::InvalidContribution("invalid proof of knowledge"));
+            }
+            // delta is updated by the same delta
+            if !same_ratio((current_delta, pubkey.delta_after), (r, pubkey.r_delta)) {
+                return Err(RLNError::InvalidContribution(
+                    "delta does not follow the previous contribution",
+                ));
+            }
+            current_delta = pubkey.delta_after;
+            pubkey.write(&mut sink)?;
+            hashes.push(pubkey.hash()?);
+        }
+
+        if current_delta != after.vk.delta_g1 {
+            return Err(RLNError::InvalidContribution(
+                "delta does not match the last contribution",
+            ));
+        }
+        if !same_ratio(
+            (E::G1Affine::one(), after.vk.delta_g1),
+            (E::G2Affine::one(), after.vk.delta_g2),
+        ) {
+            return Err(RLNError::InvalidContribution("delta in G1 and G2 differ"));
+        }
+        // h and l are divided by delta
+        if !same_ratio(
+            merge_pairs(&before.h, &after.h),
+            (after.vk.delta_g2, E::G2Affine::one()),
+        ) {
+            return Err(RLNError::InvalidContribution(
+                "h query is not scaled by delta",
+            ));
+        }
+        if !same_ratio(
+            merge_pairs(&before.l, &after.l),
+            (after.vk.delta_g2, E::G2Affine::one()),
+        ) {
+            return Err(RLNError::InvalidContribution(
+                "l query is not scaled by delta",
+            ));
+        }
+        Ok(hashes)
+    }
+
+    pub fn contributions(&self) -> &[PublicKey<E>] {
+        &self.contributions
+    }
+
+    /// parameters of the ceremony for proving and verifying
+    pub fn finalize(self) -> Parameters<E> {
+        self.params
+    }
+
+    fn transcript(&self) -> Result<[u8; 64]> {
+        let mut sink = HashWriter::new();
+        sink.write_all(&self.cs_hash)?;
+        for pubkey in self.contributions.iter() {
+            pubkey.write(&mut sink)?;
+        }
+        Ok(sink.into_hash())
+    }
+}
+
+/// size of the evaluation domain of the circuit, the powers of tau must be of this size
+pub fn domain_size<E: Engine, C: Circuit<E>>(circuit: C) -> Result<usize> {
+    KeypairAssembly::synthesize(circuit)?.domain_size()
+}
+
+// lagrange basis terms of a variable in A, B and C polynomials
+
+#[derive(Clone)]
+struct LagrangeTerms<E>
+where
+    E: Engine,
+{
+    at: Vec<(E::Fr, usize)>,
+    bt: Vec<(E::Fr, usize)>,
+    ct: Vec<(E::Fr, usize)>,
+}
+
+impl<E> LagrangeTerms<E>
+where
+    E: Engine,
+{
+    fn empty() -> LagrangeTerms<E> {
+        LagrangeTerms {
+            at: vec![],
+            bt: vec![],
+            ct: vec![],
+        }
+    }
+}
+
+// records the constraints of a circuit without witness values
+struct KeypairAssembly<E>
+where
+    E: Engine,
+{
+    num_constraints: usize,
+    inputs: Vec<LagrangeTerms<E>>,
+    aux: Vec<LagrangeTerms<E>>,
+}
+
+impl<E> KeypairAssembly<E>
+where
+    E: Engine,
+{
+    // synthesizes the circuit as the bellman prover does,
+    // with the one input first and input constraints last
+    fn synthesize<C: Circuit<E>>(circuit: C) -> Result<KeypairAssembly<E>> {
+        let mut assembly = KeypairAssembly {
+            num_constraints: 0,
+            inputs: vec![],
+            aux: vec![],
+        };
+        assembly.alloc_input(|| "", || Ok(E::Fr::one()))?;
+        circuit.synthesize(&mut assembly)?;
+        for i in 0..assembly.inputs.len() {
+            assembly.enforce(
+                || "",
+                |lc| lc + Variable::new_unchecked(Index::Input(i)),
+                |lc| lc,
+                |lc| lc,
+            );
+        }
+        Ok(assembly)
+    }
+
+    fn domain_size(&self) -> Result<usize> {
+        let mut m = 1;
+        let mut exp = 0;
+        while m < self.num_constraints {
+            m *= 2;
+            exp += 1;
+            if exp > E::Fr::S {
+                return Err(SynthesisError::PolynomialDegreeTooLarge.into());
+            }
+        }
+        Ok(m)
+    }
+
+    fn terms(&mut self, variable: Variable) -> &mut LagrangeTerms<E> {
+        match variable.get_unchecked() {
+            Index::Input(i) => &mut self.inputs[i],
+            Index::Aux(i) => &mut self.aux[i],
+        }
+    }
+}
+
+impl<E> ConstraintSystem<E> for KeypairAssembly<E>
+where
+    E: Engine,
+{
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, _: F) -> std::result::Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> std::result::Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let index = self.aux.len();
+        self.aux.push(LagrangeTerms::empty());
+        Ok(Variable::new_unchecked(Index::Aux(index)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, _: F) -> std::result::Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> std::result::Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let index = self.inputs.len();
+        self.inputs.push(LagrangeTerms::empty());
+        Ok(Variable::new_unchecked(Index::Input(index)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        let constraint = self.num_constraints;
+        for &(variable, coeff) in a(LinearCombination::zero()).as_ref() {
+            self.terms(variable).at.push((coeff, constraint));
+        }
+        for &(variable, coeff) in b(LinearCombination::zero()).as_ref() {
+            self.terms(variable).bt.push((coeff, constraint));
+        }
+        for &(variable, coeff) in c(LinearCombination::zero()).as_ref() {
+            self.terms(variable).ct.push((coeff, constraint));
+        }
+        self.num_constraints += 1;
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self) {}
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+// queries of a variable, ext is beta * A + alpha * B + C
+
+#[derive(Clone)]
+struct VariableQuery<E>
+where
+    E: Engine,
+{
+    a_g1: E::G1Affine,
+    b_g1: E::G1Affine,
+    b_g2: E::G2Affine,
+    ext: E::G1Affine,
+}
+
+fn eval<E: Engine>(
+    powers_of_tau: &PowersOfTau<E>,
+    variables: &[LagrangeTerms<E>],
+    worker: &Worker,
+) -> Vec<VariableQuery<E>> {
+    let mut queries = vec![
+        VariableQuery {
+            a_g1: E::G1Affine::zero(),
+            b_g1: E::G1Affine::zero(),
+            b_g2: E::G2Affine::zero(),
+            ext: E::G1Affine::zero(),
+        };
+        variables.len()
+    ];
+    worker.scope(variables.len(), |scope, chunk| {
+        for (variables, queries) in variables.chunks(chunk).zip(queries.chunks_mut(chunk)) {
+            scope.spawn(move |_| {
+                for (terms, query) in variables.iter().zip(queries.iter_mut()) {
+                    let mut ext = lincomb(&powers_of_tau.beta_coeffs_g1, &terms.at);
+                    ext.add_assign(&lincomb(&powers_of_tau.alpha_coeffs_g1, &terms.bt));
+                    ext.add_assign(&lincomb(&powers_of_tau.coeffs_g1, &terms.ct));
+                    *query = VariableQuery {
+                        a_g1: lincomb(&powers_of_tau.coeffs_g1, &terms.at).into_affine(),
+                        b_g1: lincomb(&powers_of_tau.coeffs_g1, &terms.bt).into_affine(),
+                        b_g2: lincomb(&powers_of_tau.coeffs_g2, &terms.bt).into_affine(),
+                        ext: ext.into_affine(),
+                    };
+                }
+            });
+        }
+    });
+    queries
+}
+
+fn lincomb<G: CurveAffine>(bases: &[G], terms: &[(G::Scalar, usize)]) -> G::Projective {
+    let mut acc = G::Projective::zero();
+    for &(coeff, lagrange) in terms.iter() {
+        acc.add_assign(&bases[lagrange].mul(coeff.into_repr()));
+    }
+    acc
+}
+
+fn scale<G: CurveAffine>(points: &[G], by: G::Scalar, worker: &Worker) -> Vec<G> {
+    let mut scaled = points.to_vec();
+    worker.scope(scaled.len(), |scope, chunk| {
+        for scaled in scaled.chunks_mut(chunk) {
+            scope.spawn(move |_| {
+                for point in scaled.iter_mut() {
+                    *point = point.mul(by.into_repr()).into_affine();
+                }
+            });
+        }
+    });
+    scaled
+}
+
+// random linear combinations of both vectors with the same coefficients
+fn merge_pairs<G: CurveAffine>(v1: &[G], v2: &[G]) -> (G, G) {
+    let mut rng = thread_rng();
+    let mut s = G::Projective::zero();
+    let mut sx = G::Projective::zero();
+    for (p1, p2) in v1.iter().zip(v2.iter()) {
+        let rho = G::Scalar::rand(&mut rng);
+        s.add_assign(&p1.mul(rho.into_repr()));
+        sx.add_assign(&p2.mul(rho.into_repr()));
+    }
+    (s.into_affine(), sx.into_affine())
+}
+
+// e(g1.0, g2.1) == e(g1.1, g2.0)
+fn same_ratio<G: CurveAffine>(g1: (G, G), g2: (G::Pair, G::Pair)) -> bool {
+    g1.0.pairing_with(&g2.1) == g1.1.pairing_with(&g2.0)
+}
+
+fn hash_to_g2<E: Engine>(
+    transcript: &[u8; 64],
+    s: &E::G1Affine,
+    s_delta: &E::G1Affine,
+) -> E::G2Affine {
+    let mut hasher = Blake2b::new();
+    hasher.input(&transcript[..]);
+    hasher.input(s.into_uncompressed().as_ref());
+    hasher.input(s_delta.into_uncompressed().as_ref());
+    let digest = hasher.result();
+    let seed: Vec<u32> = digest[..32].chunks(4).map(BigEndian::read_u32).collect();
+    let mut rng = ChaChaRng::from_seed(&seed[..]);
+    E::G2::rand(&mut rng).into_affine()
+}
+
+#[derive(Clone)]
+struct HashWriter {
+    hasher: Blake2b,
+}
+
+impl HashWriter {
+    fn new() -> HashWriter {
+        HashWriter {
+            hasher: Blake2b::new(),
+        }
+    }
+
+    fn into_hash(self) -> [u8; 64] {
+        let mut hash = [0u8; 64];
+        hash.copy_from_slice(&self.hasher.result()[..]);
+        hash
+    }
+}
+
+impl Write for HashWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.hasher.input(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_phase2_ceremony() {
+    use crate::mpc::rln_circuit;
+    use crate::parameters::{write_parameters, CircuitKind, ParametersHeader};
+    use crate::poseidon::PoseidonParams;
+    use crate::public::{Identity, RLNWitnessInput, RLN};
+    use bellman::pairing::bn256::{Bn256, Fr, G1Affine};
+    use bellman::pairing::ff::PrimeFieldRepr;
+
+    let merkle_depth = 3usize;
+    let poseidon_params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+    let circuit = || rln_circuit(merkle_depth, 1, poseidon_params.clone());
+
+    let mut rng = thread_rng();
+    let m = domain_size(circuit()).unwrap();
+    let mut raw_powers_of_tau: Vec<u8> = Vec::new();
+    PowersOfTau::<Bn256>::generate_insecure(m, &mut rng)
+        .unwrap()
+        .write(&mut raw_powers_of_tau)
+        .unwrap();
+
+    let mut ceremony = MPCParameters::new(circuit(), raw_powers_of_tau.as_slice()).unwrap();
+    let mut hashes = vec![];
+    for _ in 0..2 {
+        // every contributor reads the previous state
+        let mut raw_ceremony: Vec<u8> = Vec::new();
+        ceremony.write(&mut raw_ceremony).unwrap();
+        ceremony = MPCParameters::read(raw_ceremony.as_slice(), true).unwrap();
+        hashes.push(ceremony.contribute(&mut rng).unwrap());
+    }
+    let verified = ceremony
+        .verify(circuit(), raw_powers_of_tau.as_slice())
+        .unwrap();
+    assert_eq!(verified.len(), 2);
+    assert!(verified
+        .iter()
+        .zip(hashes.iter())
+        .all(|(a, b)| a[..] == b[..]));
+
+    // ceremony over other powers of tau is rejected
+    let mut other_powers_of_tau: Vec<u8> = Vec::new();
+    PowersOfTau::<Bn256>::generate_insecure(m, &mut rng)
+        .unwrap()
+        .write(&mut other_powers_of_tau)
+        .unwrap();
+    assert!(ceremony
+        .verify(circuit(), other_powers_of_tau.as_slice())
+        .is_err());
+
+    // tampered delta is rejected
+    let mut tampered = ceremony.clone();
+    tampered.params.vk.delta_g1 = G1Affine::one();
+    assert!(tampered
+        .verify(circuit(), raw_powers_of_tau.as_slice())
+        .is_err());
+
+    // final parameters prove and verify
+    let header = ParametersHeader::new(CircuitKind::RLN, merkle_depth, 1, &poseidon_params);
+    let mut raw_parameters: Vec<u8> = Vec::new();
+    write_parameters(&header, &ceremony.finalize(), &mut raw_parameters).unwrap();
+    let mut rln =
+        RLN::<Bn256>::new_with_raw_params(merkle_depth, raw_parameters.as_slice(), None).unwrap();
+    let identity = Identity::random(&rln.hasher(), &mut rng);
+    let mut commitment: Vec<u8> = Vec::new();
+    identity
+        .id_commitment
+        .into_repr()
+        .write_le(&mut commitment)
+        .unwrap();
+    rln.update_next_member(commitment.as_slice()).unwrap();
+    let witness = RLNWitnessInput::<Bn256> {
+        id_key: identity.id_key,
+        id_index: 0,
+        epoch: Fr::from_str("1").unwrap(),
+        signal: b"phase2 signal".to_vec(),
+    };
+    let rln_proof = rln.prove(&witness).unwrap();
+    assert!(rln.verify_rln_proof(&rln_proof, &witness.signal).unwrap());
+}
@@ -0,0 +1,156 @@
+use crate::error::Result;
+use bellman::domain::{EvaluationDomain, Scalar};
+use bellman::multicore::Worker;
+use bellman::pairing::ff::{Field, PrimeField};
+use bellman::pairing::{CurveAffine, CurveProjective, EncodedPoint, Engine};
+use rand::{Rand, Rng};
+use std::io::{self, Read, Write};
+
+// Output of the powers of tau ceremony in lagrange basis of a radix-2 domain of size m
+//
+// [ alpha_g1 | beta_g1 | beta_g2 | coeffs_g1 * m | coeffs_g2 * m | alpha_coeffs_g1 * m | beta_coeffs_g1 * m | h * (m - 1) ]
+//
+// Points are uncompressed. coeffs are L_i(tau), alpha and beta coeffs are scaled by alpha and beta,
+// h is tau^i * t(tau) where t is the vanishing polynomial of the domain.
+// This is the layout of `phase1radix2m{exp}` files prepared from the powers of tau transcript.
+
+#[derive(Clone)]
+pub struct PowersOfTau<E>
+where
+    E: Engine,
+{
+    pub alpha_g1: E::G1Affine,
+    pub beta_g1: E::G1Affine,
+    pub beta_g2: E::G2Affine,
+    pub coeffs_g1: Vec<E::G1Affine>,
+    pub coeffs_g2: Vec<E::G2Affine>,
+    pub alpha_coeffs_g1: Vec<E::G1Affine>,
+    pub beta_coeffs_g1: Vec<E::G1Affine>,
+    pub h: Vec<E::G1Affine>,
+}
+
+impl<E> PowersOfTau<E>
+where
+    E: Engine,
+{
+    /// generates powers of tau of a domain of size `m` from local randomness
+    /// * tau, alpha and beta are known to the caller, keys of such a setup must not be used in production
+    pub fn generate_insecure<R: Rng>(m: usize, rng: &mut R) -> Result<PowersOfTau<E>> {
+        let tau = E::Fr::rand(rng);
+        let alpha = E::Fr::rand(rng);
+        let beta = E::Fr::rand(rng);
+        let g1 = E::G1Affine::one();
+        let g2 = E::G2Affine::one();
+
+        let mut powers = Vec::with_capacity(m);
+        let mut power = E::Fr::one();
+        for _ in 0..m {
+            powers.push(Scalar::<E>(power));
+            power.mul_assign(&tau);
+        }
+        let mut domain = EvaluationDomain::from_coeffs(powers)?;
+
+        // t(tau) * tau^i
+        let z = domain.z(&tau);
+        let h = domain.as_ref()[..m - 1]
+            .iter()
+            .map(|p| {
+                let mut e = p.0;
+                e.mul_assign(&z);
+                g1.mul(e.into_repr()).into_affine()
+            })
+            .collect();
+
+        // L_i(tau)
+        let worker = Worker::new();
+        domain.ifft(&worker);
+        let lagrange: Vec<E::Fr> = domain.into_coeffs().into_iter().map(|s| s.0).collect();
+        let scaled = |by: &E::Fr| -> Vec<E::G1Affine> {
+            lagrange
+                .iter()
+                .map(|l| {
+                    let mut e = *l;
+                    e.mul_assign(by);
+                    g1.mul(e.into_repr()).into_affine()
+                })
+                .collect()
+        };
+
+        Ok(PowersOfTau {
+            alpha_g1: g1.mul(alpha.into_repr()).into_affine(),
+            beta_g1: g1.mul(beta.into_repr()).into_affine(),
+            beta_g2: g2.mul(beta.into_repr()).into_affine(),
+            coeffs_g1: scaled(&E::Fr::one()),
+            coeffs_g2: lagrange
+                .iter()
+                .map(|l| g2.mul(l.into_repr()).into_affine())
+                .collect(),
+            alpha_coeffs_g1: scaled(&alpha),
+            beta_coeffs_g1: scaled(&beta),
+            h,
+        })
+    }
+
+    /// reads powers of tau of a domain of size `m`, points are checked to be on the curve
+    pub fn read<R: Read>(mut reader: R, m: usize) -> Result<PowersOfTau<E>> {
+        let alpha_g1 = read_point(&mut reader)?;
+        let beta_g1 = read_point(&mut reader)?;
+        let beta_g2 = read_point(&mut reader)?;
+        let coeffs_g1 = read_points(&mut reader, m)?;
+        let coeffs_g2 = read_points(&mut reader, m)?;
+        let alpha_coeffs_g1 = read_points(&mut reader, m)?;
+        let beta_coeffs_g1 = read_points(&mut reader, m)?;
+        let h = read_points(&mut reader, m - 1)?;
+        Ok(PowersOfTau {
+            alpha_g1,
+            beta_g1,
+            beta_g2,
+            coeffs_g1,
+            coeffs_g2,
+            alpha_coeffs_g1,
+            beta_coeffs_g1,
+            h,
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(self.alpha_g1.into_uncompressed().as_ref())?;
+        writer.write_all(self.beta_g1.into_uncompressed().as_ref())?;
+        writer.write_all(self.beta_g2.into_uncompressed().as_ref())?;
+        write_points(&self.coeffs_g1, &mut writer)?;
+        write_points(&self.coeffs_g2, &mut writer)?;
+        write_points(&self.alpha_coeffs_g1, &mut writer)?;
+        write_points(&self.beta_coeffs_g1, &mut writer)?;
+        write_points(&self.h, &mut writer)?;
+        Ok(())
+    }
+
+    /// size of the domain
+    pub fn size(&self) -> usize {
+        self.coeffs_g1.len()
+    }
+}
+
+pub(crate) fn read_point<G: CurveAffine, R: Read>(mut reader: R) -> Result<G> {
+    let mut repr = G::Uncompressed::empty();
+    reader.read_exact(repr.as_mut())?;
+    let point = repr
+        .into_affine()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(point)
+}
+
+fn read_points<G: CurveAffine, R: Read>(mut reader: R, n: usize) -> Result<Vec<G>> {
+    let mut points = Vec::with_capacity(n);
+    for _ in 0..n {
+        points.push(read_point(&mut reader)?);
+    }
+    Ok(points)
+}
+
+fn write_points<G: CurveAffine, W: Write>(points: &[G], mut writer: W) -> Result<()> {
+    for point in points.iter() {
+        writer.write_all(point.into_uncompressed().as_ref())?;
+    }
+    Ok(())
+}
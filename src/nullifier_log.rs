@@ -0,0 +1,179 @@
+use crate::sss::Share;
+use sapling_crypto::bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
+use sapling_crypto::bellman::pairing::Engine;
+use std::collections::HashMap;
+
+// Verifier side registry of accepted signals
+
+pub enum MessageStatus<E>
+where
+    E: Engine,
+{
+    // share is within the allowed number of shares
+    // for the nullifier in this epoch
+    Fresh,
+    // same share is already recorded, message is a replay
+    Duplicate,
+    // nullifier is reused with more distinct shares than allowed,
+    // carries every recorded share including the new one
+    Spam(Vec<Share<E>>),
+}
+
+impl<E> MessageStatus<E>
+where
+    E: Engine,
+{
+    pub fn is_fresh(&self) -> bool {
+        match self {
+            MessageStatus::Fresh => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_duplicate(&self) -> bool {
+        match self {
+            MessageStatus::Duplicate => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_spam(&self) -> bool {
+        match self {
+            MessageStatus::Spam(_) => true,
+            _ => false,
+        }
+    }
+}
+
+pub struct NullifierLog<E>
+where
+    E: Engine,
+{
+    // number of distinct shares allowed per nullifier,
+    // the degree of the secret polynomial
+    share_degree: usize,
+    // epoch -> nullifier -> shares
+    epochs: HashMap<Vec<u64>, HashMap<Vec<u64>, Vec<Share<E>>>>,
+}
+
+pub(crate) fn fr_key<E: Engine>(e: &E::Fr) -> Vec<u64> {
+    e.into_repr().as_ref().to_vec()
+}
+
+impl<E> NullifierLog<E>
+where
+    E: Engine,
+{
+    pub fn new() -> NullifierLog<E> {
+        Self::with_share_degree(1)
+    }
+
+    pub fn with_share_degree(share_degree: usize) -> NullifierLog<E> {
+        assert!(share_degree > 0, "share degree must be at least one");
+        NullifierLog {
+            share_degree,
+            epochs: HashMap::new(),
+        }
+    }
+
+    /// records the share of an accepted proof and classifies the message
+    pub fn record(
+        &mut self,
+        epoch: E::Fr,
+        nullifier: E::Fr,
+        share_x: E::Fr,
+        share_y: E::Fr,
+    ) -> MessageStatus<E> {
+        let share = Share::new(share_x, share_y);
+        let shares = self
+            .epochs
+            .entry(fr_key::<E>(&epoch))
+            .or_insert_with(HashMap::new)
+            .entry(fr_key::<E>(&nullifier))
+            .or_insert_with(Vec::new);
+
+        if shares.iter().any(|s| s.x == share.x && s.y == share.y) {
+            return MessageStatus::Duplicate;
+        }
+        shares.push(share);
+        if shares.len() <= self.share_degree {
+            return MessageStatus::Fresh;
+        }
+        MessageStatus::Spam(shares.clone())
+    }
+
+    /// returns the shares recorded for a nullifier in an epoch
+    pub fn shares(&self, epoch: E::Fr, nullifier: E::Fr) -> Vec<Share<E>> {
+        self.epochs
+            .get(&fr_key::<E>(&epoch))
+            .and_then(|nullifiers| nullifiers.get(&fr_key::<E>(&nullifier)))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// drops every record of an epoch once it is no longer accepted
+    pub fn remove_epoch(&mut self, epoch: E::Fr) {
+        self.epochs.remove(&fr_key::<E>(&epoch));
+    }
+
+    pub fn len(&self) -> usize {
+        self.epochs.values().map(|nullifiers| nullifiers.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[test]
+fn test_nullifier_log() {
+    use rand::{Rand, SeedableRng, XorShiftRng};
+    use sapling_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    let mut rng = XorShiftRng::from_seed([0x3dbe6258, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let mut log = NullifierLog::<Bn256>::new();
+    let epoch = Fr::rand(&mut rng);
+    let nullifier = Fr::rand(&mut rng);
+    let (x0, y0) = (Fr::rand(&mut rng), Fr::rand(&mut rng));
+    let (x1, y1) = (Fr::rand(&mut rng), Fr::rand(&mut rng));
+
+    assert!(log.record(epoch, nullifier, x0, y0).is_fresh());
+    assert!(log.record(epoch, nullifier, x0, y0).is_duplicate());
+    match log.record(epoch, nullifier, x1, y1) {
+        MessageStatus::Spam(shares) => {
+            assert_eq!(shares.len(), 2);
+            assert_eq!((shares[0].x, shares[0].y), (x0, y0));
+            assert_eq!((shares[1].x, shares[1].y), (x1, y1));
+        }
+        _ => panic!("expected spam"),
+    }
+
+    // same nullifier in another epoch is a fresh message
+    let other_epoch = Fr::rand(&mut rng);
+    assert!(log.record(other_epoch, nullifier, x1, y1).is_fresh());
+    assert_eq!(log.len(), 2);
+
+    log.remove_epoch(epoch);
+    assert!(log.shares(epoch, nullifier).is_empty());
+    assert_eq!(log.len(), 1);
+}
+
+#[test]
+fn test_nullifier_log_with_share_degree() {
+    use rand::{Rand, SeedableRng, XorShiftRng};
+    use sapling_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    let mut rng = XorShiftRng::from_seed([0x3dbe6258, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let mut log = NullifierLog::<Bn256>::with_share_degree(3);
+    let epoch = Fr::rand(&mut rng);
+    let nullifier = Fr::rand(&mut rng);
+    for _ in 0..3 {
+        let (x, y) = (Fr::rand(&mut rng), Fr::rand(&mut rng));
+        assert!(log.record(epoch, nullifier, x, y).is_fresh());
+    }
+    let (x, y) = (Fr::rand(&mut rng), Fr::rand(&mut rng));
+    match log.record(epoch, nullifier, x, y) {
+        MessageStatus::Spam(shares) => assert_eq!(shares.len(), 4),
+        _ => panic!("expected spam"),
+    }
+}
@@ -0,0 +1,449 @@
+use crate::error::{RLNError, Result};
+use crate::poseidon::PoseidonParams;
+use bellman::groth16::Parameters;
+use bellman::pairing::ff::{PrimeField, PrimeFieldRepr};
+use bellman::pairing::Engine;
+use blake2::{Blake2s, Digest};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{Read, Write};
+
+// Self describing container of circuit parameters
+//
+// [ magic<4> | version<2> | header<var> | digest<32> | parameters<var> ]
+// header is serialized as
+// [ engine_id<8> | circuit_id<8> | merkle_depth<8> | share_degree<8> | rf<8> | rp<8> | t<8> | seed_len<8> | seed<var> | poseidon_digest<32> ]
+// and digest is Blake2s of the header and the raw bellman parameters that follow it. Poseidon parameters
+// are regenerated from rf, rp, t and seed, and are identified by `PoseidonParams::digest`
+// as in tree snapshots.
+
+pub const PARAMETERS_MAGIC: [u8; 4] = *b"RLNP";
+pub const PARAMETERS_VERSION: u16 = 2;
+
+// bounds of poseidon parameters regenerated from a header
+const MAX_FULL_ROUNDS: usize = 64;
+const MAX_PARTIAL_ROUNDS: usize = 1024;
+const MAX_WIDTH: usize = 16;
+const MAX_SEED_LEN: usize = 1024;
+
+/// identifies the curve of the parameters by its scalar and base field moduli
+pub fn engine_id<E: Engine>() -> u64 {
+    let mut hasher = Blake2s::new();
+    hasher.input(b"rlnengin");
+    let mut modulus: Vec<u8> = Vec::new();
+    E::Fr::char().write_le(&mut modulus).unwrap();
+    E::Fq::char().write_le(&mut modulus).unwrap();
+    hasher.input(modulus);
+    let digest = hasher.result();
+    (&digest[..8]).read_u64::<LittleEndian>().unwrap()
+}
+
+// validation of the points of circuit parameters on read
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParametersCheck {
+    // every point is checked to be on the curve and in the subgroup
+    Full,
+    // key material must hash to the pinned digest, points are read unchecked
+    PinnedDigest([u8; 32]),
+}
+
+// circuit the parameters were generated for
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CircuitKind {
+    RLN,
+    RLNv2,
+    Semaphore,
+    JoinSplit,
+}
+
+impl CircuitKind {
+    pub fn id(&self) -> u64 {
+        match self {
+            CircuitKind::RLN => 0,
+            CircuitKind::RLNv2 => 1,
+            CircuitKind::Semaphore => 2,
+            CircuitKind::JoinSplit => 3,
+        }
+    }
+
+    pub fn from_id(id: u64) -> Result<CircuitKind> {
+        match id {
+            0 => Ok(CircuitKind::RLN),
+            1 => Ok(CircuitKind::RLNv2),
+            2 => Ok(CircuitKind::Semaphore),
+            3 => Ok(CircuitKind::JoinSplit),
+            _ => Err(RLNError::InvalidParameters("unknown circuit")),
+        }
+    }
+}
+
+// circuit shape the parameters were generated for
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParametersHeader {
+    pub engine_id: u64,
+    pub circuit: CircuitKind,
+    pub merkle_depth: usize,
+    pub share_degree: usize,
+    pub rf: usize,
+    pub rp: usize,
+    pub t: usize,
+    pub seed: Vec<u8>,
+    pub poseidon_digest: [u8; 32],
+}
+
+impl ParametersHeader {
+    pub fn new<E: Engine>(
+        circuit: CircuitKind,
+        merkle_depth: usize,
+        share_degree: usize,
+        poseidon_params: &PoseidonParams<E>,
+    ) -> ParametersHeader {
+        ParametersHeader {
+            engine_id: engine_id::<E>(),
+            circuit,
+            merkle_depth,
+            share_degree,
+            rf: poseidon_params.full_rounds(),
+            rp: poseidon_params.partial_round_len(),
+            t: poseidon_params.width(),
+            seed: poseidon_params.seed().to_vec(),
+            poseidon_digest: poseidon_params.digest(),
+        }
+    }
+
+    /// poseidon parameters generated from the recorded seed
+    /// * parameters with constants not generated from the seed do not match the recorded digest
+    /// * round numbers, width or seed out of bounds are rejected before anything is generated
+    pub fn poseidon_params<E: Engine>(&self) -> Result<PoseidonParams<E>> {
+        if self.rf == 0 || self.rf > MAX_FULL_ROUNDS {
+            return Err(RLNError::InvalidParameters("full rounds out of range"));
+        }
+        if self.rp > MAX_PARTIAL_ROUNDS {
+            return Err(RLNError::InvalidParameters("partial rounds out of range"));
+        }
+        if self.t < 2 || self.t > MAX_WIDTH {
+            return Err(RLNError::InvalidParameters("width out of range"));
+        }
+        if self.seed.len() > MAX_SEED_LEN {
+            return Err(RLNError::InvalidParameters("seed is too long"));
+        }
+        Ok(PoseidonParams::new(
+            self.rf,
+            self.rp,
+            self.t,
+            None,
+            None,
+            Some(self.seed.clone()),
+        ))
+    }
+
+    /// rejects parameters that were not generated for the given circuit
+    pub fn check<E: Engine>(
+        &self,
+        circuit: CircuitKind,
+        merkle_depth: usize,
+        share_degree: usize,
+        poseidon_params: &PoseidonParams<E>,
+    ) -> Result<()> {
+        let expected = Self::new(circuit, merkle_depth, share_degree, poseidon_params);
+        if self.engine_id != expected.engine_id {
+            return Err(RLNError::ParametersMismatch("engine"));
+        }
+        if self.circuit != expected.circuit {
+            return Err(RLNError::ParametersMismatch("circuit"));
+        }
+        if self.merkle_depth != expected.merkle_depth {
+            return Err(RLNError::ParametersMismatch("merkle depth"));
+        }
+        if self.share_degree != expected.share_degree {
+            return Err(RLNError::ParametersMismatch("share degree"));
+        }
+        if self.poseidon_digest != expected.poseidon_digest {
+            return Err(RLNError::ParametersMismatch("poseidon parameters"));
+        }
+        Ok(())
+    }
+
+    fn read<R: Read>(mut reader: R) -> Result<ParametersHeader> {
+        let engine_id = reader.read_u64::<LittleEndian>()?;
+        let circuit = CircuitKind::from_id(reader.read_u64::<LittleEndian>()?)?;
+        let merkle_depth = reader.read_u64::<LittleEndian>()? as usize;
+        let share_degree = reader.read_u64::<LittleEndian>()? as usize;
+        let rf = reader.read_u64::<LittleEndian>()? as usize;
+        let rp = reader.read_u64::<LittleEndian>()? as usize;
+        let t = reader.read_u64::<LittleEndian>()? as usize;
+        // seed length is untrusted, the seed is read as far as it goes
+        let seed_len = reader.read_u64::<LittleEndian>()?;
+        let mut seed: Vec<u8> = Vec::new();
+        (&mut reader).take(seed_len).read_to_end(&mut seed)?;
+        if seed.len() as u64 != seed_len {
+            return Err(RLNError::InvalidParameters("truncated header"));
+        }
+        let mut poseidon_digest = [0u8; 32];
+        reader.read_exact(&mut poseidon_digest)?;
+        Ok(ParametersHeader {
+            engine_id,
+            circuit,
+            merkle_depth,
+            share_degree,
+            rf,
+            rp,
+            t,
+            seed,
+            poseidon_digest,
+        })
+    }
+
+    fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_u64::<LittleEndian>(self.engine_id)?;
+        writer.write_u64::<LittleEndian>(self.circuit.id())?;
+        writer.write_u64::<LittleEndian>(self.merkle_depth as u64)?;
+        writer.write_u64::<LittleEndian>(self.share_degree as u64)?;
+        writer.write_u64::<LittleEndian>(self.rf as u64)?;
+        writer.write_u64::<LittleEndian>(self.rp as u64)?;
+        writer.write_u64::<LittleEndian>(self.t as u64)?;
+        writer.write_u64::<LittleEndian>(self.seed.len() as u64)?;
+        writer.write_all(&self.seed[..])?;
+        writer.write_all(&self.poseidon_digest)?;
+        Ok(())
+    }
+}
+
+/// writes circuit parameters in the container format
+pub fn write_parameters<E: Engine, W: Write>(
+    header: &ParametersHeader,
+    parameters: &Parameters<E>,
+    mut writer: W,
+) -> Result<()> {
+    let mut raw_parameters: Vec<u8> = Vec::new();
+    parameters.write(&mut raw_parameters)?;
+
+    writer.write_all(&PARAMETERS_MAGIC)?;
+    writer.write_u16::<LittleEndian>(PARAMETERS_VERSION)?;
+    header.write(&mut writer)?;
+    writer.write_all(&parameters_digest(header, &raw_parameters)?)?;
+    writer.write_all(&raw_parameters)?;
+    Ok(())
+}
+
+/// reads circuit parameters in the container format with full point checks
+/// * the digest is checked before the parameters are decoded
+pub fn read_parameters<E: Engine, R: Read>(reader: R) -> Result<(ParametersHeader, Parameters<E>)> {
+    read_parameters_with(reader, ParametersCheck::Full)
+}
+
+/// reads circuit parameters in the container format
+/// * with `ParametersCheck::PinnedDigest` the key material must match the pinned digest
+/// and the points are not checked, which is much faster for deep trees
+pub fn read_parameters_with<E: Engine, R: Read>(
+    mut reader: R,
+    check: ParametersCheck,
+) -> Result<(ParametersHeader, Parameters<E>)> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != PARAMETERS_MAGIC {
+        return Err(RLNError::InvalidParameters("not a parameters file"));
+    }
+    if reader.read_u16::<LittleEndian>()? != PARAMETERS_VERSION {
+        return Err(RLNError::InvalidParameters("unsupported version"));
+    }
+    let header = ParametersHeader::read(&mut reader)?;
+    if header.engine_id != engine_id::<E>() {
+        return Err(RLNError::ParametersMismatch("engine"));
+    }
+
+    let mut digest = [0u8; 32];
+    reader.read_exact(&mut digest)?;
+    let mut raw_parameters: Vec<u8> = Vec::new();
+    reader.read_to_end(&mut raw_parameters)?;
+    if parameters_digest(&header, &raw_parameters)? != digest {
+        return Err(RLNError::InvalidParameters("digest mismatch"));
+    }
+
+    let checked = match check {
+        ParametersCheck::Full => true,
+        ParametersCheck::PinnedDigest(pinned) => {
+            if pinned != digest {
+                return Err(RLNError::InvalidParameters("pinned digest mismatch"));
+            }
+            false
+        }
+    };
+    let parameters = Parameters::<E>::read(raw_parameters.as_slice(), checked)?;
+    Ok((header, parameters))
+}
+
+/// Blake2s digest of the header and raw bellman parameters, the value to pin for unchecked loading
+pub fn parameters_digest(header: &ParametersHeader, raw_parameters: &[u8]) -> Result<[u8; 32]> {
+    let mut raw_header: Vec<u8> = Vec::new();
+    header.write(&mut raw_header)?;
+    let mut hasher = Blake2s::new();
+    hasher.input(&raw_header);
+    hasher.input(raw_parameters);
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(&hasher.result()[..]);
+    Ok(digest)
+}
+
+#[test]
+fn test_parameters_container() {
+    use crate::public::{Semaphore, ShieldedPool, RLN};
+    use bellman::pairing::bn256::Bn256;
+
+    let merkle_depth = 3usize;
+    let rln = RLN::<Bn256>::new(merkle_depth, None).unwrap();
+    let mut raw_parameters: Vec<u8> = Vec::new();
+    rln.export_circuit_parameters(&mut raw_parameters).unwrap();
+
+    let (header, _) = read_parameters::<Bn256, _>(raw_parameters.as_slice()).unwrap();
+    assert_eq!(
+        header,
+        ParametersHeader::new(CircuitKind::RLN, merkle_depth, 1, &rln.poseidon_params())
+    );
+    assert!(
+        RLN::<Bn256>::new_with_raw_params(merkle_depth, raw_parameters.as_slice(), None).is_ok()
+    );
+
+    // wrong depth
+    match RLN::<Bn256>::new_with_raw_params(merkle_depth + 1, raw_parameters.as_slice(), None) {
+        Err(RLNError::ParametersMismatch(_)) => {}
+        _ => panic!("depth mismatch must be rejected"),
+    }
+
+    // wrong poseidon parameters
+    let poseidon_params = PoseidonParams::<Bn256>::new(8, 57, 3, None, None, None);
+    match RLN::<Bn256>::new_with_raw_params(
+        merkle_depth,
+        raw_parameters.as_slice(),
+        Some(poseidon_params),
+    ) {
+        Err(RLNError::ParametersMismatch(_)) => {}
+        _ => panic!("poseidon mismatch must be rejected"),
+    }
+
+    // round constants that are not generated from the seed
+    let round_constants = PoseidonParams::<Bn256>::generate_constants(b"rlntestc", vec![], 63);
+    let poseidon_params = PoseidonParams::<Bn256>::new(8, 55, 3, Some(round_constants), None, None);
+    let custom = RLN::<Bn256>::new(merkle_depth, Some(poseidon_params.clone())).unwrap();
+    let mut custom_parameters: Vec<u8> = Vec::new();
+    custom
+        .export_circuit_parameters(&mut custom_parameters)
+        .unwrap();
+    let (header, _) = read_parameters::<Bn256, _>(custom_parameters.as_slice()).unwrap();
+    assert_eq!(header.poseidon_digest, custom.hasher().params_digest());
+    match RLN::<Bn256>::new_with_raw_params(merkle_depth, custom_parameters.as_slice(), None) {
+        Err(RLNError::ParametersMismatch(_)) => {}
+        _ => panic!("regenerated poseidon parameters must not match"),
+    }
+    assert!(RLN::<Bn256>::new_with_raw_params(
+        merkle_depth,
+        custom_parameters.as_slice(),
+        Some(poseidon_params)
+    )
+    .is_ok());
+
+    // parameters of another circuit
+    let semaphore = Semaphore::<Bn256>::new(merkle_depth, None).unwrap();
+    let mut semaphore_parameters: Vec<u8> = Vec::new();
+    semaphore
+        .export_circuit_parameters(&mut semaphore_parameters)
+        .unwrap();
+    match RLN::<Bn256>::new_with_raw_params(merkle_depth, semaphore_parameters.as_slice(), None) {
+        Err(RLNError::ParametersMismatch("circuit")) => {}
+        _ => panic!("circuit mismatch must be rejected"),
+    }
+    match ShieldedPool::<Bn256>::new_with_raw_params(merkle_depth, raw_parameters.as_slice(), None)
+    {
+        Err(RLNError::ParametersMismatch("circuit")) => {}
+        _ => panic!("circuit mismatch must be rejected"),
+    }
+    assert!(Semaphore::<Bn256>::new_with_raw_params(
+        merkle_depth,
+        semaphore_parameters.as_slice(),
+        None
+    )
+    .is_ok());
+
+    // pinned digest loads the points unchecked
+    let digest = rln.circuit_parameters_digest().unwrap();
+    assert!(RLN::<Bn256>::new_with_checked_params(
+        merkle_depth,
+        1,
+        raw_parameters.as_slice(),
+        None,
+        ParametersCheck::PinnedDigest(digest),
+    )
+    .is_ok());
+    match read_parameters_with::<Bn256, _>(
+        raw_parameters.as_slice(),
+        ParametersCheck::PinnedDigest([0u8; 32]),
+    ) {
+        Err(RLNError::InvalidParameters(_)) => {}
+        _ => panic!("unpinned parameters must be rejected"),
+    }
+
+    // the pinned digest covers the header
+    let (header, parameters) = read_parameters::<Bn256, _>(raw_parameters.as_slice()).unwrap();
+    let other_header = ParametersHeader {
+        share_degree: 2,
+        ..header.clone()
+    };
+    let mut other_parameters: Vec<u8> = Vec::new();
+    write_parameters(&other_header, &parameters, &mut other_parameters).unwrap();
+    match read_parameters_with::<Bn256, _>(
+        other_parameters.as_slice(),
+        ParametersCheck::PinnedDigest(digest),
+    ) {
+        Err(RLNError::InvalidParameters("pinned digest mismatch")) => {}
+        _ => panic!("parameters with another header must not match the pinned digest"),
+    }
+
+    // poseidon parameters out of bounds are not regenerated
+    for (rf, rp, t) in [
+        (usize::MAX, 1, 3),
+        (8, usize::MAX, 3),
+        (8, 55, 1 << 20),
+        (0, 55, 3),
+    ]
+    .iter()
+    {
+        let other_header = ParametersHeader {
+            rf: *rf,
+            rp: *rp,
+            t: *t,
+            ..header.clone()
+        };
+        assert!(other_header.poseidon_params::<Bn256>().is_err());
+        let mut other_parameters: Vec<u8> = Vec::new();
+        write_parameters(&other_header, &parameters, &mut other_parameters).unwrap();
+        match RLN::<Bn256>::new_with_raw_params(merkle_depth, other_parameters.as_slice(), None) {
+            Err(RLNError::InvalidParameters(_)) => {}
+            _ => panic!("poseidon parameters out of bounds must be rejected"),
+        }
+    }
+
+    // odd number of full rounds is recorded as is
+    let poseidon_params = PoseidonParams::<Bn256>::new(7, 55, 3, None, None, None);
+    let odd_header = ParametersHeader::new(CircuitKind::RLN, merkle_depth, 1, &poseidon_params);
+    assert_eq!(odd_header.rf, 7);
+    assert_eq!(
+        odd_header.poseidon_params::<Bn256>().unwrap().digest(),
+        poseidon_params.digest()
+    );
+
+    // corrupted key material
+    let last = raw_parameters.len() - 1;
+    raw_parameters[last] ^= 1;
+    match read_parameters::<Bn256, _>(raw_parameters.as_slice()) {
+        Err(RLNError::InvalidParameters(_)) => {}
+        _ => panic!("corrupted parameters must be rejected"),
+    }
+
+    // raw bellman dump has no header
+    match read_parameters::<Bn256, _>(&[0u8; 64][..]) {
+        Err(RLNError::InvalidParameters(_)) => {}
+        _ => panic!("raw parameters must be rejected"),
+    }
+}
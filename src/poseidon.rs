@@ -8,6 +8,7 @@ pub struct PoseidonParams<E: Engine> {
     rf: usize,
     rp: usize,
     t: usize,
+    seed: Vec<u8>,
     round_constants: Vec<E::Fr>,
     mds_matrix: Vec<E::Fr>,
 }
@@ -45,6 +46,7 @@ impl<E: Engine> PoseidonParams<E> {
             rf,
             rp,
             t,
+            seed,
             round_constants: _round_constants,
             mds_matrix: _mds_matrix,
         }
@@ -62,10 +64,18 @@ impl<E: Engine> PoseidonParams<E> {
         return self.rf / 2;
     }
 
+    pub fn full_rounds(&self) -> usize {
+        return self.rf;
+    }
+
     pub fn total_rounds(&self) -> usize {
         return self.rf + self.rp;
     }
 
+    pub fn seed(&self) -> &[u8] {
+        &self.seed
+    }
+
     pub fn round_constant(&self, round: usize) -> E::Fr {
         return self.round_constants[round];
     }
@@ -79,6 +89,23 @@ impl<E: Engine> PoseidonParams<E> {
         self.mds_matrix.clone()
     }
 
+    /// Blake2s digest of the round numbers, width, round constants and mds matrix
+    /// * identifies the parameters in parameter files and tree snapshots
+    pub fn digest(&self) -> [u8; 32] {
+        let mut hasher = Blake2s::new();
+        for n in [self.rf, self.rp, self.t].iter() {
+            hasher.input(&(*n as u64).to_le_bytes());
+        }
+        let mut buf: Vec<u8> = Vec::new();
+        for fe in self.round_constants.iter().chain(self.mds_matrix.iter()) {
+            fe.into_repr().write_le(&mut buf).unwrap();
+        }
+        hasher.input(&buf);
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(&hasher.result()[..]);
+        digest
+    }
+
     pub fn generate_mds_matrix(persona: &[u8; 8], seed: Vec<u8>, t: usize) -> Vec<E::Fr> {
         let v: Vec<E::Fr> = PoseidonParams::<E>::generate_constants(persona, seed, t * 2);
         let mut matrix: Vec<E::Fr> = Vec::with_capacity(t * t);
@@ -133,6 +160,11 @@ impl<E: Engine> Poseidon<E> {
         state[0]
     }
 
+    /// digest of the parameters, see `PoseidonParams::digest`
+    pub fn params_digest(&self) -> [u8; 32] {
+        self.params.digest()
+    }
+
     fn t(&self) -> usize {
         self.params.t
     }
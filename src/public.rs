@@ -1,18 +1,39 @@
+use crate::batch;
+use crate::circuit::join_split::{
+    InputNote, JoinSplitCircuit, JoinSplitInputs, OutputNote, JOIN_SPLIT_INPUTS, JOIN_SPLIT_OUTPUTS,
+};
 use crate::circuit::rln::{RLNCircuit, RLNInputs};
+use crate::circuit::rln_v2::{RLNv2Circuit, RLNv2Inputs, MESSAGE_LIMIT_BITS};
+use crate::circuit::semaphore::{semaphore_nullifier, SemaphoreCircuit, SemaphoreInputs};
+use crate::error::{RLNError, Result};
 use crate::hash_to_field::hash_to_field;
 use crate::merkle::MerkleTree;
+use crate::nullifier_log::{fr_key, MessageStatus, NullifierLog};
+pub use crate::parameters::ParametersCheck;
+use crate::parameters::{
+    parameters_digest, read_parameters_with, write_parameters, CircuitKind, ParametersHeader,
+};
 use crate::poseidon::{Poseidon as PoseidonHasher, PoseidonParams};
-use crate::utils::{read_fr, read_signal_hash, read_uncompressed_proof, write_uncompressed_proof};
+use crate::snapshot::{read_snapshot, write_snapshot};
+use crate::sss::{evaluate, polynomial_coefficients, recover_secret_from_shares, Share};
+pub use crate::utils::ProofFormat;
+use crate::utils::{
+    fr_from_u64, read_fr, read_signal, read_signal_hash, read_tagged_proof,
+    read_uncompressed_proof, write_tagged_proof, write_uncompressed_proof,
+};
 use crate::{circuit::poseidon::PoseidonCircuit, merkle::IncrementalMerkleTree};
 use bellman::groth16::generate_random_parameters;
 use bellman::groth16::{create_proof, prepare_verifying_key, verify_proof};
-use bellman::groth16::{create_random_proof, Parameters, Proof};
+use bellman::groth16::{
+    create_random_proof, Parameters, PreparedVerifyingKey, Proof, VerifyingKey,
+};
 use bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr, ScalarEngine};
 use bellman::pairing::{CurveAffine, EncodedPoint, Engine};
 use bellman::{Circuit, ConstraintSystem, SynthesisError};
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use rand::{thread_rng, Rand, Rng};
 use std::{
+    collections::HashSet,
     io::{self, Error, ErrorKind, Read, Write},
     ptr::null,
 };
@@ -32,12 +53,11 @@ impl<E> RLNSignal<E>
 where
     E: Engine,
 {
-    pub fn read<R: Read>(mut reader: R) -> io::Result<RLNSignal<E>> {
+    pub fn read<R: Read>(mut reader: R) -> Result<RLNSignal<E>> {
         let mut buf = <E::Fr as PrimeField>::Repr::default();
 
         buf.read_le(&mut reader)?;
-        let epoch =
-            E::Fr::from_repr(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let epoch = E::Fr::from_repr(buf).map_err(|_| RLNError::InvalidFieldElement)?;
 
         let hash = read_signal_hash::<R, E>(reader)?;
 
@@ -45,10 +65,194 @@ where
     }
 }
 
+// identity of a member, id commitment is the membership leaf
+
+#[derive(Clone)]
+pub struct Identity<E>
+where
+    E: Engine,
+{
+    pub id_key: E::Fr,
+    pub id_commitment: E::Fr,
+}
+
+impl<E> Identity<E>
+where
+    E: Engine,
+{
+    pub fn new(hasher: &PoseidonHasher<E>, id_key: E::Fr) -> Identity<E> {
+        let id_commitment = hasher.hash(vec![id_key]);
+        Identity {
+            id_key,
+            id_commitment,
+        }
+    }
+
+    pub fn random<R: Rng>(hasher: &PoseidonHasher<E>, rng: &mut R) -> Identity<E> {
+        Self::new(hasher, E::Fr::rand(rng))
+    }
+
+    /// * expect `reader` serialized as [ id_key<32> | id_commitment<32> ]
+    pub fn read<R: Read>(reader: R) -> Result<Identity<E>> {
+        let values = read_fr::<_, E>(reader, 2)?;
+        Ok(Identity {
+            id_key: values[0],
+            id_commitment: values[1],
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        self.id_key.into_repr().write_le(&mut writer)?;
+        self.id_commitment.into_repr().write_le(&mut writer)?;
+        Ok(())
+    }
+}
+
+// private inputs of a proof together with the signal
+
+#[derive(Clone)]
+pub struct RLNWitnessInput<E>
+where
+    E: Engine,
+{
+    pub id_key: E::Fr,
+    pub id_index: usize,
+    pub epoch: E::Fr,
+    pub signal: Vec<u8>,
+}
+
+impl<E> RLNWitnessInput<E>
+where
+    E: Engine,
+{
+    /// * expect `reader` serialized as [ id_key<32> | id_index<8> | epoch<32> | signal_len<8> | signal<var> ]
+    pub fn read<R: Read>(mut reader: R) -> Result<RLNWitnessInput<E>> {
+        let id_key = read_fr::<_, E>(&mut reader, 1)?[0];
+        let id_index = reader.read_u64::<LittleEndian>()? as usize;
+        let epoch = read_fr::<_, E>(&mut reader, 1)?[0];
+        let signal = read_signal(reader)?;
+        Ok(RLNWitnessInput {
+            id_key,
+            id_index,
+            epoch,
+            signal,
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        self.id_key.into_repr().write_le(&mut writer)?;
+        writer.write_u64::<LittleEndian>(self.id_index as u64)?;
+        self.epoch.into_repr().write_le(&mut writer)?;
+        writer.write_u64::<LittleEndian>(self.signal.len() as u64)?;
+        writer.write_all(&self.signal[..])?;
+        Ok(())
+    }
+
+    pub fn signal_hash(&self) -> E::Fr {
+        hash_to_field::<E>(&self.signal[..])
+    }
+}
+
+// public values of a proof
+
+#[derive(Clone)]
+pub struct RLNProofValues<E>
+where
+    E: Engine,
+{
+    pub root: E::Fr,
+    pub epoch: E::Fr,
+    pub share_x: E::Fr,
+    pub share_y: E::Fr,
+    pub nullifier: E::Fr,
+}
+
+impl<E> RLNProofValues<E>
+where
+    E: Engine,
+{
+    /// * expect `reader` serialized as [ root<32>| epoch<32>| share_x<32>| share_y<32>| nullifier<32> ]
+    pub fn read<R: Read>(reader: R) -> Result<RLNProofValues<E>> {
+        let values = read_fr::<_, E>(reader, 5)?;
+        Ok(Self::from_public_inputs(&values))
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        for value in self.public_inputs().iter() {
+            value.into_repr().write_le(&mut writer)?;
+        }
+        Ok(())
+    }
+
+    /// public inputs of the circuit, [ root, epoch, share_x, share_y, nullifier ]
+    pub fn public_inputs(&self) -> Vec<E::Fr> {
+        vec![
+            self.root,
+            self.epoch,
+            self.share_x,
+            self.share_y,
+            self.nullifier,
+        ]
+    }
+
+    fn from_public_inputs(public_inputs: &[E::Fr]) -> RLNProofValues<E> {
+        RLNProofValues {
+            root: public_inputs[0],
+            epoch: public_inputs[1],
+            share_x: public_inputs[2],
+            share_y: public_inputs[3],
+            nullifier: public_inputs[4],
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RLNProof<E>
+where
+    E: Engine,
+{
+    pub proof: Proof<E>,
+    pub values: RLNProofValues<E>,
+}
+
+impl<E> RLNProof<E>
+where
+    E: Engine,
+{
+    /// * expect `reader` serialized as [ proof<256>| root<32>| epoch<32>| share_x<32>| share_y<32>| nullifier<32> ]
+    pub fn read<R: Read>(mut reader: R) -> Result<RLNProof<E>> {
+        let proof = read_uncompressed_proof(&mut reader)?;
+        let values = RLNProofValues::read(&mut reader)?;
+        Ok(RLNProof { proof, values })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        write_uncompressed_proof(self.proof.clone(), &mut writer)?;
+        self.values.write(&mut writer)
+    }
+
+    /// * expect `reader` serialized as [ format<1> | proof<var>| root<32>| epoch<32>| share_x<32>| share_y<32>| nullifier<32> ]
+    /// where proof is 256 bytes if uncompressed and 128 bytes if compressed
+    pub fn read_tagged<R: Read>(mut reader: R) -> Result<RLNProof<E>> {
+        let proof = read_tagged_proof(&mut reader)?;
+        let values = RLNProofValues::read(&mut reader)?;
+        Ok(RLNProof { proof, values })
+    }
+
+    pub fn write_tagged<W: Write>(&self, format: ProofFormat, mut writer: W) -> Result<()> {
+        write_tagged_proof(self.proof.clone(), format, &mut writer)?;
+        self.values.write(&mut writer)
+    }
+}
+
 pub struct RLN<E: Engine> {
     circuit_parameters: Parameters<E>,
+    prepared_verifying_key: PreparedVerifyingKey<E>,
     poseidon_params: PoseidonParams<E>,
     tree: IncrementalMerkleTree<E>,
+    merkle_depth: usize,
+    share_degree: usize,
+    circuit: CircuitKind,
 }
 
 impl<E: Engine> RLN<E> {
@@ -56,51 +260,144 @@ impl<E: Engine> RLN<E> {
         PoseidonParams::<E>::new(8, 55, 3, None, None, None)
     }
 
-    fn new_circuit(merkle_depth: usize, poseidon_params: PoseidonParams<E>) -> Parameters<E> {
+    fn new_circuit(
+        merkle_depth: usize,
+        share_degree: usize,
+        poseidon_params: PoseidonParams<E>,
+    ) -> Result<Parameters<E>> {
         let mut rng = thread_rng();
         let inputs = RLNInputs::<E>::empty(merkle_depth);
         let circuit = RLNCircuit::<E> {
             inputs,
             hasher: PoseidonCircuit::new(poseidon_params.clone()),
+            degree: share_degree,
         };
-        generate_random_parameters(circuit, &mut rng).unwrap()
+        let circuit_parameters = generate_random_parameters(circuit, &mut rng)?;
+        Ok(circuit_parameters)
     }
 
     fn new_with_params(
+        circuit: CircuitKind,
         merkle_depth: usize,
+        share_degree: usize,
         circuit_parameters: Parameters<E>,
         poseidon_params: PoseidonParams<E>,
     ) -> RLN<E> {
         let hasher = PoseidonHasher::new(poseidon_params.clone());
         let tree = IncrementalMerkleTree::empty(hasher, merkle_depth);
+        let prepared_verifying_key = prepare_verifying_key(&circuit_parameters.vk);
         RLN {
             circuit_parameters,
+            prepared_verifying_key,
             poseidon_params,
             tree,
+            merkle_depth,
+            share_degree,
+            circuit,
         }
     }
 
-    pub fn new(merkle_depth: usize, poseidon_params: Option<PoseidonParams<E>>) -> RLN<E> {
+    pub fn new(merkle_depth: usize, poseidon_params: Option<PoseidonParams<E>>) -> Result<RLN<E>> {
+        Self::new_with_degree(merkle_depth, 1, poseidon_params)
+    }
+
+    /// creates an instance where members can signal `share_degree` times per epoch
+    pub fn new_with_degree(
+        merkle_depth: usize,
+        share_degree: usize,
+        poseidon_params: Option<PoseidonParams<E>>,
+    ) -> Result<RLN<E>> {
+        if share_degree == 0 {
+            return Err(RLNError::InvalidShareDegree);
+        }
         let poseidon_params = match poseidon_params {
             Some(params) => params,
             None => Self::default_poseidon_params(),
         };
-        let circuit_parameters = Self::new_circuit(merkle_depth, poseidon_params.clone());
-        Self::new_with_params(merkle_depth, circuit_parameters, poseidon_params)
+        let circuit_parameters =
+            Self::new_circuit(merkle_depth, share_degree, poseidon_params.clone())?;
+        Ok(Self::new_with_params(
+            CircuitKind::RLN,
+            merkle_depth,
+            share_degree,
+            circuit_parameters,
+            poseidon_params,
+        ))
     }
 
+    /// * expect `raw_circuit_parameters` as written by `export_circuit_parameters`
+    /// * parameters generated for another circuit, merkle depth, share degree or poseidon parameters are rejected,
+    /// poseidon parameters recorded in the file are used if `poseidon_params` is none
     pub fn new_with_raw_params<R: Read>(
         merkle_depth: usize,
         raw_circuit_parameters: R,
         poseidon_params: Option<PoseidonParams<E>>,
-    ) -> io::Result<RLN<E>> {
-        let circuit_parameters = Parameters::<E>::read(raw_circuit_parameters, true)?;
+    ) -> Result<RLN<E>> {
+        Self::new_with_raw_params_and_degree(
+            merkle_depth,
+            1,
+            raw_circuit_parameters,
+            poseidon_params,
+        )
+    }
+
+    pub fn new_with_raw_params_and_degree<R: Read>(
+        merkle_depth: usize,
+        share_degree: usize,
+        raw_circuit_parameters: R,
+        poseidon_params: Option<PoseidonParams<E>>,
+    ) -> Result<RLN<E>> {
+        Self::new_with_checked_params(
+            merkle_depth,
+            share_degree,
+            raw_circuit_parameters,
+            poseidon_params,
+            ParametersCheck::Full,
+        )
+    }
+
+    /// creates an instance from parameters validated as given by `check`
+    /// * `ParametersCheck::PinnedDigest` skips point checks of parameters matching the pinned digest
+    pub fn new_with_checked_params<R: Read>(
+        merkle_depth: usize,
+        share_degree: usize,
+        raw_circuit_parameters: R,
+        poseidon_params: Option<PoseidonParams<E>>,
+        check: ParametersCheck,
+    ) -> Result<RLN<E>> {
+        Self::new_with_circuit_params(
+            CircuitKind::RLN,
+            merkle_depth,
+            share_degree,
+            raw_circuit_parameters,
+            poseidon_params,
+            check,
+        )
+    }
+
+    // loads parameters recorded for the given circuit
+    fn new_with_circuit_params<R: Read>(
+        circuit: CircuitKind,
+        merkle_depth: usize,
+        share_degree: usize,
+        raw_circuit_parameters: R,
+        poseidon_params: Option<PoseidonParams<E>>,
+        check: ParametersCheck,
+    ) -> Result<RLN<E>> {
+        if share_degree == 0 {
+            return Err(RLNError::InvalidShareDegree);
+        }
+        let (header, circuit_parameters) =
+            read_parameters_with::<E, _>(raw_circuit_parameters, check)?;
         let poseidon_params = match poseidon_params {
             Some(params) => params,
-            None => Self::default_poseidon_params(),
+            None => header.poseidon_params()?,
         };
+        header.check(circuit, merkle_depth, share_degree, &poseidon_params)?;
         Ok(Self::new_with_params(
+            circuit,
             merkle_depth,
+            share_degree,
             circuit_parameters,
             poseidon_params,
         ))
@@ -108,7 +405,7 @@ impl<E: Engine> RLN<E> {
 
     /// returns current membership root
     /// * `root` is a scalar field element in 32 bytes
-    pub fn get_root<W: Write>(&self, mut result_data: W) -> io::Result<()> {
+    pub fn get_root<W: Write>(&self, mut result_data: W) -> Result<()> {
         let root = self.tree.get_root();
         root.into_repr().write_le(&mut result_data)?;
         Ok(())
@@ -116,27 +413,58 @@ impl<E: Engine> RLN<E> {
 
     /// inserts new member with given public key
     /// * `input_data` is a 32 scalar field element in 32 bytes
-    pub fn update_next_member<R: Read>(&mut self, input_data: R) -> io::Result<()> {
+    pub fn update_next_member<R: Read>(&mut self, input_data: R) -> Result<()> {
         let mut buf = <E::Fr as PrimeField>::Repr::default();
         buf.read_le(input_data)?;
-        let leaf =
-            E::Fr::from_repr(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let leaf = E::Fr::from_repr(buf).map_err(|_| RLNError::InvalidFieldElement)?;
         self.tree.update_next(leaf)?;
         Ok(())
     }
 
+    /// inserts new members with given public keys after the last member
+    /// * expect `input_data` serialized as [ count<8> | id_commitment<32> * count ]
+    pub fn append_members<R: Read>(&mut self, mut input_data: R) -> Result<()> {
+        let count = input_data.read_u64::<LittleEndian>()? as usize;
+        let leaves = read_fr::<_, E>(input_data, count)?;
+        self.tree.append_many(&leaves)?;
+        Ok(())
+    }
+
+    /// sets members with given public keys from `start_index` on
+    /// * expect `input_data` serialized as [ count<8> | id_commitment<32> * count ]
+    pub fn set_members<R: Read>(&mut self, start_index: usize, mut input_data: R) -> Result<()> {
+        let count = input_data.read_u64::<LittleEndian>()? as usize;
+        let leaves = read_fr::<_, E>(input_data, count)?;
+        self.tree.update_batch(start_index, &leaves)?;
+        Ok(())
+    }
+
     //// deletes member with given index
-    pub fn delete_member(&mut self, index: usize) -> io::Result<()> {
+    pub fn delete_member(&mut self, index: usize) -> Result<()> {
         self.tree.delete(index)?;
         Ok(())
     }
 
+    /// returns the index of the member with given public key
+    /// * `input_data` is id commitment in 32 bytes
+    pub fn find_member_index<R: Read>(&self, input_data: R) -> Result<usize> {
+        let id_commitment: E::Fr = read_fr::<_, E>(input_data, 1)?[0];
+        self.tree
+            .leaf_index(id_commitment)
+            .ok_or(RLNError::MemberNotFound)
+    }
+
+    /// sets the number of most recent membership roots that proofs are accepted against
+    pub fn set_root_window(&mut self, size: usize) {
+        self.tree.set_root_history_size(size);
+    }
+
     /// hashes plain text to a field element
     pub fn signal_to_field<R: Read, W: Write>(
         &self,
         input_data: R,
         mut result_data: W,
-    ) -> io::Result<()> {
+    ) -> Result<()> {
         let result = read_signal_hash::<R, E>(input_data)?;
         result.into_repr().write_le(&mut result_data)?;
         Ok(())
@@ -145,99 +473,312 @@ impl<E: Engine> RLN<E> {
     /// given public inputs and autharization data generates public inputs and proof
     /// * expect `input_data`  serialized as  [ id_key<32> | id_index<8> | epoch<32> | signal_len<8> | signal<var> ]
     /// * `result_data` is proof data serialized as [ proof<256>| root<32>| epoch<32>| share_x<32>| share_y<32>| nullifier<32> ]
-    pub fn generate_proof<R: Read, W: Write>(
-        &self,
-        mut input_data: R,
-        mut result_data: W,
-    ) -> io::Result<()> {
-        let id_key: E::Fr = read_fr::<_, E>(&mut input_data, 1)?[0];
-        let id_index = input_data.read_u64::<LittleEndian>()? as usize;
-
-        let signal = RLNSignal::<E>::read(input_data)?;
+    pub fn generate_proof<R: Read, W: Write>(&self, input_data: R, result_data: W) -> Result<()> {
+        let witness = RLNWitnessInput::<E>::read(input_data)?;
+        self.prove(&witness)?.write(result_data)
+    }
 
-        use hex;
+    /// generates proof in given format
+    /// * expect `input_data` serialized as in `generate_proof`
+    /// * `result_data` is proof data serialized as
+    /// [ format<1> | proof<var>| root<32>| epoch<32>| share_x<32>| share_y<32>| nullifier<32> ]
+    pub fn generate_tagged_proof<R: Read, W: Write>(
+        &self,
+        input_data: R,
+        format: ProofFormat,
+        result_data: W,
+    ) -> Result<()> {
+        let witness = RLNWitnessInput::<E>::read(input_data)?;
+        self.prove(&witness)?.write_tagged(format, result_data)
+    }
 
+    /// generates the proof of a signal
+    pub fn prove(&self, witness: &RLNWitnessInput<E>) -> Result<RLNProof<E>> {
         let hasher = self.hasher();
-        let share_x = signal.hash.clone();
+        let id_key = witness.id_key;
+        let epoch = witness.epoch;
+        let share_x = witness.signal_hash();
 
-        // line equation
-        let a_0 = id_key.clone();
-        let a_1: E::Fr = hasher.hash(vec![a_0, signal.epoch]);
-        // evaluate line equation
-        let mut share_y = a_1.clone();
-        share_y.mul_assign(&share_x);
-        share_y.add_assign(&a_0);
-        let nullifier = hasher.hash(vec![a_1]);
+        // secret polynomial
+        let coeffs = polynomial_coefficients(&hasher, id_key, epoch, self.share_degree);
+        // evaluate the polynomial
+        let share_y = evaluate::<E>(&coeffs, share_x);
+        let nullifier = hasher.hash(vec![coeffs[1]]);
 
-        let root = self.tree.get_root();
-        // TODO: check id key here
-        let auth_path = self.tree.get_witness(id_index)?;
+        // id key must be the preimage of the member leaf
+        let id_commitment: E::Fr = hasher.hash(vec![id_key]);
+        let (root, auth_path) = self.member_path(witness.id_index, id_commitment)?;
 
         let inputs = RLNInputs::<E> {
             share_x: Some(share_x),
             share_y: Some(share_y),
-            epoch: Some(signal.epoch),
+            epoch: Some(epoch),
             nullifier: Some(nullifier),
             root: Some(root),
             id_key: Some(id_key),
-            auth_path: auth_path.into_iter().map(|w| Some(w)).collect(),
+            auth_path,
         };
 
         let circuit = RLNCircuit {
             inputs: inputs.clone(),
             hasher: PoseidonCircuit::new(self.poseidon_params.clone()),
+            degree: self.share_degree,
         };
 
-        let mut rng = thread_rng();
-        let proof = create_random_proof(circuit, &self.circuit_parameters, &mut rng).unwrap();
-        write_uncompressed_proof(proof.clone(), &mut result_data)?;
-        root.into_repr().write_le(&mut result_data)?;
-        signal.epoch.into_repr().write_le(&mut result_data)?;
-        share_x.into_repr().write_le(&mut result_data)?;
-        share_y.into_repr().write_le(&mut result_data)?;
-        nullifier.into_repr().write_le(&mut result_data)?;
+        let proof = self.prove_circuit(circuit)?;
+        Ok(RLNProof {
+            proof,
+            values: RLNProofValues {
+                root,
+                epoch,
+                share_x,
+                share_y,
+                nullifier,
+            },
+        })
+    }
 
-        Ok(())
+    // current root and authentication path of the member at `id_index`
+    // * `leaf` is the member leaf derived from the witness, another leaf at the index is an id key mismatch
+    fn member_path(
+        &self,
+        id_index: usize,
+        leaf: E::Fr,
+    ) -> Result<(E::Fr, Vec<Option<(E::Fr, bool)>>)> {
+        if self.tree.get_leaf(id_index)? != leaf {
+            return Err(RLNError::IdKeyMismatch);
+        }
+        let auth_path = self.tree.get_witness(id_index)?;
+        Ok((
+            self.tree.get_root(),
+            auth_path.into_iter().map(|w| Some(w)).collect(),
+        ))
+    }
+
+    // proves a circuit with the circuit parameters of the instance
+    fn prove_circuit<C: Circuit<E>>(&self, circuit: C) -> Result<Proof<E>> {
+        let mut rng = thread_rng();
+        let proof = create_random_proof(circuit, &self.circuit_parameters, &mut rng)?;
+        Ok(proof)
     }
 
     /// given proof and public data verifies the signal
     /// * expect `input_data` is serialized as:
     /// [ proof<256>| root<32>| epoch<32>| share_x<32>| share_y<32>| nullifier<32> | signal_len<8> | signal<var> ]
-    pub fn verify<R: Read>(&self, mut input_data: R) -> io::Result<bool> {
-        let proof = read_uncompressed_proof(&mut input_data)?;
-        let public_inputs = RLNInputs::<E>::read_public_inputs(&mut input_data)?;
-        let signal_hash = read_signal_hash::<R, E>(input_data)?;
-
-        if signal_hash != public_inputs[2] {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "signal hash mismatch",
-            ));
+    /// * proofs against a root outside of the recent root window are rejected
+    pub fn verify<R: Read>(&self, input_data: R) -> Result<bool> {
+        let (proof, public_inputs) = read_verification_input(input_data)?;
+        self.verify_public_inputs(&proof, &public_inputs)
+    }
+
+    /// verifies the signal of a proof in either format
+    /// * expect `input_data` is serialized as:
+    /// [ format<1> | proof<var>| root<32>| epoch<32>| share_x<32>| share_y<32>| nullifier<32> | signal_len<8> | signal<var> ]
+    pub fn verify_tagged<R: Read>(&self, input_data: R) -> Result<bool> {
+        let (proof, public_inputs) = read_tagged_verification_input(input_data)?;
+        self.verify_public_inputs(&proof, &public_inputs)
+    }
+
+    /// verifies the proof of a signal
+    /// * proofs against a root outside of the recent root window are rejected
+    pub fn verify_rln_proof(&self, rln_proof: &RLNProof<E>, signal: &[u8]) -> Result<bool> {
+        if hash_to_field::<E>(signal) != rln_proof.values.share_x {
+            return Err(RLNError::SignalHashMismatch);
+        }
+        self.verify_public_inputs(&rln_proof.proof, &rln_proof.values.public_inputs())
+    }
+
+    /// verifies the signal and records its share to the nullifier log
+    /// * expect `input_data` is serialized as in `verify`
+    /// * returns `None` if the proof is not valid, otherwise the status of the message
+    pub fn verify_and_record<R: Read>(
+        &self,
+        input_data: R,
+        log: &mut NullifierLog<E>,
+    ) -> Result<Option<MessageStatus<E>>> {
+        let (proof, public_inputs) = read_verification_input(input_data)?;
+        if !self.verify_public_inputs(&proof, &public_inputs)? {
+            return Ok(None);
+        }
+        let values = RLNProofValues::<E>::from_public_inputs(&public_inputs);
+        let status = log.record(
+            values.epoch,
+            values.nullifier,
+            values.share_x,
+            values.share_y,
+        );
+        Ok(Some(status))
+    }
+
+    /// verifies many signals with a single batched pairing check
+    /// * expect `input_data` is serialized as [ count<8> | input<var> * count ]
+    /// where each input is serialized as in `verify`
+    /// * returns the result of each proof in order, a signal hash mismatch
+    /// or a root outside of the recent root window is an invalid proof
+    pub fn verify_batch<R: Read>(&self, input_data: R) -> Result<Vec<bool>> {
+        let entries = read_batch_verification_input::<_, E>(input_data)?
+            .into_iter()
+            .map(|entry| {
+                entry.filter(|(_, public_inputs)| self.tree.is_recent_root(&public_inputs[0]))
+            })
+            .collect();
+        Ok(verify_batch_entries(
+            &self.circuit_parameters.vk,
+            &self.prepared_verifying_key,
+            entries,
+        ))
+    }
+
+    fn verify_public_inputs(&self, proof: &Proof<E>, public_inputs: &[E::Fr]) -> Result<bool> {
+        // proof must be built against one of the recent membership roots
+        if !self.tree.is_recent_root(&public_inputs[0]) {
+            return Ok(false);
         }
 
-        let verifing_key = prepare_verifying_key(&self.circuit_parameters.vk);
-        let success = verify_proof(&verifing_key, &proof, &public_inputs).unwrap();
+        let success = verify_proof(&self.prepared_verifying_key, proof, public_inputs)?;
         Ok(success)
     }
 
+    /// recovers the secret of a member who signalled twice in the same epoch
+    /// * applicable when share degree is one, see `recover_secret_from_proofs` otherwise
+    /// * expect `proof_data_0` and `proof_data_1` as outputs of `generate_proof`:
+    /// [ proof<256>| root<32>| epoch<32>| share_x<32>| share_y<32>| nullifier<32> ]
+    /// * `result_data` is serialized as [ id_key<32> | id_commitment<32> | id_index<8> ]
+    pub fn recover_secret<R0: Read, R1: Read, W: Write>(
+        &self,
+        proof_data_0: R0,
+        proof_data_1: R1,
+        result_data: W,
+    ) -> Result<()> {
+        let public_inputs = vec![
+            Self::read_proof_public_inputs(proof_data_0)?,
+            Self::read_proof_public_inputs(proof_data_1)?,
+        ];
+        self.recover_secret_from_public_inputs(public_inputs, result_data)
+    }
+
+    /// recovers the secret of a member who signalled more than `share_degree` times in the same epoch
+    /// * expect `proof_data` as `share_degree + 1` outputs of `generate_proof`
+    /// * `result_data` is serialized as [ id_key<32> | id_commitment<32> | id_index<8> ]
+    pub fn recover_secret_from_proofs<R: Read, W: Write>(
+        &self,
+        proof_data: Vec<R>,
+        result_data: W,
+    ) -> Result<()> {
+        let public_inputs = proof_data
+            .into_iter()
+            .map(Self::read_proof_public_inputs)
+            .collect::<Result<Vec<_>>>()?;
+        self.recover_secret_from_public_inputs(public_inputs, result_data)
+    }
+
+    fn read_proof_public_inputs<R: Read>(proof_data: R) -> Result<Vec<E::Fr>> {
+        Ok(RLNProof::<E>::read(proof_data)?.values.public_inputs())
+    }
+
+    fn recover_secret_from_public_inputs<W: Write>(
+        &self,
+        public_inputs: Vec<Vec<E::Fr>>,
+        mut result_data: W,
+    ) -> Result<()> {
+        if public_inputs.len() != self.share_degree + 1 {
+            return Err(RLNError::InvalidShares("expected share degree + 1 shares"));
+        }
+
+        // public inputs are [ root, epoch, share_x, share_y, nullifier ]
+        let epoch = public_inputs[0][1];
+        let nullifier = public_inputs[0][4];
+        if public_inputs.iter().any(|inputs| inputs[1] != epoch) {
+            return Err(RLNError::InvalidShares("shares are from different epochs"));
+        }
+        if public_inputs.iter().any(|inputs| inputs[4] != nullifier) {
+            return Err(RLNError::InvalidShares("shares have different nullifiers"));
+        }
+
+        let shares: Vec<Share<E>> = public_inputs
+            .iter()
+            .map(|inputs| Share::new(inputs[2], inputs[3]))
+            .collect();
+        let id_key = recover_secret_from_shares(&shares)?;
+
+        // recovered polynomial must be the one committed by the nullifier
+        let hasher = self.hasher();
+        let coeffs = polynomial_coefficients(&hasher, id_key, epoch, self.share_degree);
+        if hasher.hash(vec![coeffs[1]]) != nullifier
+            || shares.iter().any(|s| evaluate::<E>(&coeffs, s.x) != s.y)
+        {
+            return Err(RLNError::InvalidShares(
+                "shares do not belong to the same secret polynomial",
+            ));
+        }
+
+        let id_commitment: E::Fr = hasher.hash(vec![id_key]);
+        let id_index = self
+            .tree
+            .leaf_index(id_commitment)
+            .ok_or(RLNError::MemberNotFound)?;
+
+        id_key.into_repr().write_le(&mut result_data)?;
+        id_commitment.into_repr().write_le(&mut result_data)?;
+        result_data.write_u64::<LittleEndian>(id_index as u64)?;
+        Ok(())
+    }
+
+    /// degree of the secret polynomial, number of signals allowed per epoch
+    pub fn share_degree(&self) -> usize {
+        self.share_degree
+    }
+
+    /// creates an empty nullifier log matching the share degree
+    pub fn new_nullifier_log(&self) -> NullifierLog<E> {
+        NullifierLog::with_share_degree(self.share_degree)
+    }
+
     /// generates public private key pair
     /// * `key_pair_data` is seralized as [ secret<32> | public<32> ]
-    pub fn key_gen<W: Write>(&self, mut input_data: W) -> io::Result<()> {
+    pub fn key_gen<W: Write>(&self, input_data: W) -> Result<()> {
         let mut rng = thread_rng();
-        let hasher = self.hasher();
-        let secret = E::Fr::rand(&mut rng);
-        let public: E::Fr = hasher.hash(vec![secret.clone()]);
-        secret.into_repr().write_le(&mut input_data)?;
-        public.into_repr().write_le(&mut input_data)?;
+        Identity::random(&self.hasher(), &mut rng).write(input_data)
+    }
+
+    pub fn export_verifier_key<W: Write>(&self, w: W) -> Result<()> {
+        self.circuit_parameters.vk.write(w)?;
+        Ok(())
+    }
+
+    /// writes circuit parameters in the container format of `parameters` module
+    pub fn export_circuit_parameters<W: Write>(&self, w: W) -> Result<()> {
+        write_parameters(&self.parameters_header(), &self.circuit_parameters, w)
+    }
+
+    /// writes the membership tree in the snapshot format of `snapshot` module
+    pub fn export_tree<W: Write>(&self, w: W) -> Result<()> {
+        write_snapshot(&self.tree, w)
+    }
+
+    /// replaces the membership tree with the snapshot, the root window size is kept
+    /// * proofs against roots of the replaced tree are no longer accepted
+    pub fn import_tree<R: Read>(&mut self, r: R) -> Result<()> {
+        let mut tree = read_snapshot(self.hasher(), self.merkle_depth, r)?;
+        tree.set_root_history_size(self.tree.root_history_size());
+        self.tree = tree;
         Ok(())
     }
 
-    pub fn export_verifier_key<W: Write>(&self, w: W) -> io::Result<()> {
-        self.circuit_parameters.vk.write(w)
+    /// digest of the circuit parameters to pin for `ParametersCheck::PinnedDigest`
+    pub fn circuit_parameters_digest(&self) -> Result<[u8; 32]> {
+        let mut raw_parameters: Vec<u8> = Vec::new();
+        self.circuit_parameters.write(&mut raw_parameters)?;
+        parameters_digest(&self.parameters_header(), &raw_parameters)
     }
 
-    pub fn export_circuit_parameters<W: Write>(&self, w: W) -> io::Result<()> {
-        self.circuit_parameters.write(w)
+    fn parameters_header(&self) -> ParametersHeader {
+        ParametersHeader::new(
+            self.circuit,
+            self.merkle_depth,
+            self.share_degree,
+            &self.poseidon_params,
+        )
     }
 
     pub fn hasher(&self) -> PoseidonHasher<E> {
@@ -249,6 +790,1664 @@ impl<E: Engine> RLN<E> {
     }
 }
 
+// reads [ proof<256>| root<32>| epoch<32>| share_x<32>| share_y<32>| nullifier<32> | signal_len<8> | signal<var> ]
+// and checks the signal against share_x
+fn read_verification_input<R: Read, E: Engine>(
+    mut input_data: R,
+) -> Result<(Proof<E>, Vec<E::Fr>)> {
+    let rln_proof = RLNProof::<E>::read(&mut input_data)?;
+    check_signal_hash(rln_proof, input_data)
+}
+
+// reads [ format<1> | proof<var>| root<32>| epoch<32>| share_x<32>| share_y<32>| nullifier<32> | signal_len<8> | signal<var> ]
+// and checks the signal against share_x
+fn read_tagged_verification_input<R: Read, E: Engine>(
+    mut input_data: R,
+) -> Result<(Proof<E>, Vec<E::Fr>)> {
+    let rln_proof = RLNProof::<E>::read_tagged(&mut input_data)?;
+    check_signal_hash(rln_proof, input_data)
+}
+
+fn check_signal_hash<R: Read, E: Engine>(
+    rln_proof: RLNProof<E>,
+    input_data: R,
+) -> Result<(Proof<E>, Vec<E::Fr>)> {
+    let signal_hash = read_signal_hash::<_, E>(input_data)?;
+    if signal_hash != rln_proof.values.share_x {
+        return Err(RLNError::SignalHashMismatch);
+    }
+    Ok((rln_proof.proof, rln_proof.values.public_inputs()))
+}
+
+// reads [ count<8> | input<var> * count ],
+// an input with a signal hash mismatch is read as None
+fn read_batch_verification_input<R: Read, E: Engine>(
+    mut input_data: R,
+) -> Result<Vec<Option<(Proof<E>, Vec<E::Fr>)>>> {
+    // count is untrusted, entries are pushed as they are read
+    let count = input_data.read_u64::<LittleEndian>()?;
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        let entry = match read_verification_input::<_, E>(&mut input_data) {
+            Ok(entry) => Some(entry),
+            Err(RLNError::SignalHashMismatch) => None,
+            Err(e) => return Err(e),
+        };
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+// batch verifies the entries, None entries are invalid
+fn verify_batch_entries<E: Engine>(
+    verifying_key: &VerifyingKey<E>,
+    prepared_verifying_key: &PreparedVerifyingKey<E>,
+    entries: Vec<Option<(Proof<E>, Vec<E::Fr>)>>,
+) -> Vec<bool> {
+    let mut results = vec![false; entries.len()];
+    let (indexes, entries): (Vec<usize>, Vec<(Proof<E>, Vec<E::Fr>)>) = entries
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, entry)| entry.map(|entry| (i, entry)))
+        .unzip();
+    for (i, success) in indexes.into_iter().zip(batch::verify_proofs(
+        verifying_key,
+        prepared_verifying_key,
+        &entries,
+    )) {
+        results[i] = success;
+    }
+    results
+}
+
+// Verifier only instance of Rate Limit Nullifier
+//
+// Holds the verifying key only, membership of the proof root
+// must be checked by the caller against its view of the membership set.
+
+pub struct RLNVerifier<E: Engine> {
+    verifying_key: VerifyingKey<E>,
+    prepared_verifying_key: PreparedVerifyingKey<E>,
+}
+
+impl<E: Engine> RLNVerifier<E> {
+    pub fn new(verifying_key: VerifyingKey<E>) -> RLNVerifier<E> {
+        let prepared_verifying_key = prepare_verifying_key(&verifying_key);
+        RLNVerifier {
+            verifying_key,
+            prepared_verifying_key,
+        }
+    }
+
+    /// * expect `raw_verifier_key` as written by `RLN::export_verifier_key`
+    pub fn new_with_raw_verifier_key<R: Read>(raw_verifier_key: R) -> Result<RLNVerifier<E>> {
+        let verifying_key = VerifyingKey::<E>::read(raw_verifier_key)?;
+        Ok(Self::new(verifying_key))
+    }
+
+    /// given proof and public data verifies the signal
+    /// * expect `input_data` is serialized as:
+    /// [ proof<256>| root<32>| epoch<32>| share_x<32>| share_y<32>| nullifier<32> | signal_len<8> | signal<var> ]
+    pub fn verify<R: Read>(&self, input_data: R) -> Result<bool> {
+        let (proof, public_inputs) = read_verification_input(input_data)?;
+        let success = verify_proof(&self.prepared_verifying_key, &proof, &public_inputs)?;
+        Ok(success)
+    }
+
+    /// verifies the signal of a proof in either format
+    /// * expect `input_data` is serialized as in `RLN::verify_tagged`
+    pub fn verify_tagged<R: Read>(&self, input_data: R) -> Result<bool> {
+        let (proof, public_inputs) = read_tagged_verification_input(input_data)?;
+        let success = verify_proof(&self.prepared_verifying_key, &proof, &public_inputs)?;
+        Ok(success)
+    }
+
+    /// verifies the proof of a signal
+    pub fn verify_rln_proof(&self, rln_proof: &RLNProof<E>, signal: &[u8]) -> Result<bool> {
+        if hash_to_field::<E>(signal) != rln_proof.values.share_x {
+            return Err(RLNError::SignalHashMismatch);
+        }
+        let public_inputs = rln_proof.values.public_inputs();
+        let success = verify_proof(
+            &self.prepared_verifying_key,
+            &rln_proof.proof,
+            &public_inputs,
+        )?;
+        Ok(success)
+    }
+
+    /// verifies many signals
+    /// * expect `input_data` is serialized as [ count<8> | input<var> * count ]
+    /// where each input is serialized as in `verify`
+    /// * returns the result of each proof in order, a signal hash mismatch is an invalid proof
+    pub fn verify_batch<R: Read>(&self, input_data: R) -> Result<Vec<bool>> {
+        let entries = read_batch_verification_input(input_data)?;
+        Ok(verify_batch_entries(
+            &self.verifying_key,
+            &self.prepared_verifying_key,
+            entries,
+        ))
+    }
+
+    pub fn export_verifier_key<W: Write>(&self, w: W) -> Result<()> {
+        self.verifying_key.write(w)?;
+        Ok(())
+    }
+}
+
+// Rate Limit Nullifier with a per epoch message limit
+
+// private inputs of a proof together with the signal
+
+#[derive(Clone)]
+pub struct RLNv2WitnessInput<E>
+where
+    E: Engine,
+{
+    pub id_key: E::Fr,
+    pub id_index: usize,
+    pub user_message_limit: u64,
+    pub message_id: u64,
+    pub epoch: E::Fr,
+    pub signal: Vec<u8>,
+}
+
+impl<E> RLNv2WitnessInput<E>
+where
+    E: Engine,
+{
+    /// * expect `reader` serialized as
+    /// [ id_key<32> | id_index<8> | user_message_limit<8> | message_id<8> | epoch<32> | signal_len<8> | signal<var> ]
+    pub fn read<R: Read>(mut reader: R) -> Result<RLNv2WitnessInput<E>> {
+        let id_key = read_fr::<_, E>(&mut reader, 1)?[0];
+        let id_index = reader.read_u64::<LittleEndian>()? as usize;
+        let user_message_limit = reader.read_u64::<LittleEndian>()?;
+        let message_id = reader.read_u64::<LittleEndian>()?;
+        let epoch = read_fr::<_, E>(&mut reader, 1)?[0];
+        let signal = read_signal(reader)?;
+        Ok(RLNv2WitnessInput {
+            id_key,
+            id_index,
+            user_message_limit,
+            message_id,
+            epoch,
+            signal,
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        self.id_key.into_repr().write_le(&mut writer)?;
+        writer.write_u64::<LittleEndian>(self.id_index as u64)?;
+        writer.write_u64::<LittleEndian>(self.user_message_limit)?;
+        writer.write_u64::<LittleEndian>(self.message_id)?;
+        self.epoch.into_repr().write_le(&mut writer)?;
+        writer.write_u64::<LittleEndian>(self.signal.len() as u64)?;
+        writer.write_all(&self.signal[..])?;
+        Ok(())
+    }
+
+    pub fn signal_hash(&self) -> E::Fr {
+        hash_to_field::<E>(&self.signal[..])
+    }
+}
+
+pub struct RLNv2<E: Engine> {
+    rln: RLN<E>,
+}
+
+impl<E: Engine> RLNv2<E> {
+    fn new_circuit(
+        merkle_depth: usize,
+        poseidon_params: PoseidonParams<E>,
+    ) -> Result<Parameters<E>> {
+        let mut rng = thread_rng();
+        let inputs = RLNv2Inputs::<E>::empty(merkle_depth);
+        let circuit = RLNv2Circuit::<E> {
+            inputs,
+            hasher: PoseidonCircuit::new(poseidon_params.clone()),
+        };
+        let circuit_parameters = generate_random_parameters(circuit, &mut rng)?;
+        Ok(circuit_parameters)
+    }
+
+    pub fn new(
+        merkle_depth: usize,
+        poseidon_params: Option<PoseidonParams<E>>,
+    ) -> Result<RLNv2<E>> {
+        let poseidon_params = match poseidon_params {
+            Some(params) => params,
+            None => RLN::<E>::default_poseidon_params(),
+        };
+        let circuit_parameters = Self::new_circuit(merkle_depth, poseidon_params.clone())?;
+        Ok(RLNv2 {
+            rln: RLN::new_with_params(
+                CircuitKind::RLNv2,
+                merkle_depth,
+                1,
+                circuit_parameters,
+                poseidon_params,
+            ),
+        })
+    }
+
+    pub fn new_with_raw_params<R: Read>(
+        merkle_depth: usize,
+        raw_circuit_parameters: R,
+        poseidon_params: Option<PoseidonParams<E>>,
+    ) -> Result<RLNv2<E>> {
+        let rln = RLN::new_with_circuit_params(
+            CircuitKind::RLNv2,
+            merkle_depth,
+            1,
+            raw_circuit_parameters,
+            poseidon_params,
+            ParametersCheck::Full,
+        )?;
+        Ok(RLNv2 { rln })
+    }
+
+    /// returns current membership root
+    /// * `root` is a scalar field element in 32 bytes
+    pub fn get_root<W: Write>(&self, result_data: W) -> Result<()> {
+        self.rln.get_root(result_data)
+    }
+
+    /// inserts new member with given rate commitment
+    /// * `input_data` is a 32 scalar field element in 32 bytes
+    pub fn update_next_member<R: Read>(&mut self, input_data: R) -> Result<()> {
+        self.rln.update_next_member(input_data)
+    }
+
+    //// deletes member with given index
+    pub fn delete_member(&mut self, index: usize) -> Result<()> {
+        self.rln.delete_member(index)
+    }
+
+    /// returns the index of the member with given rate commitment
+    /// * `input_data` is rate commitment in 32 bytes
+    pub fn find_member_index<R: Read>(&self, input_data: R) -> Result<usize> {
+        self.rln.find_member_index(input_data)
+    }
+
+    /// sets the number of most recent membership roots that proofs are accepted against
+    pub fn set_root_window(&mut self, size: usize) {
+        self.rln.set_root_window(size)
+    }
+
+    /// computes the membership leaf of an identity with given message limit
+    /// * expect `input_data` serialized as [ id_commitment<32> | user_message_limit<8> ]
+    /// * `result_data` is the rate commitment in 32 bytes
+    pub fn rate_commitment<R: Read, W: Write>(
+        &self,
+        mut input_data: R,
+        mut result_data: W,
+    ) -> Result<()> {
+        let id_commitment: E::Fr = read_fr::<_, E>(&mut input_data, 1)?[0];
+        let user_message_limit = fr_from_u64::<E>(read_message_limit(&mut input_data)?);
+        let rate_commitment = self
+            .rln
+            .hasher()
+            .hash(vec![id_commitment, user_message_limit]);
+        rate_commitment.into_repr().write_le(&mut result_data)?;
+        Ok(())
+    }
+
+    /// given public inputs and autharization data generates public inputs and proof
+    /// * expect `input_data`  serialized as
+    /// [ id_key<32> | id_index<8> | user_message_limit<8> | message_id<8> | epoch<32> | signal_len<8> | signal<var> ]
+    /// * `result_data` is proof data serialized as [ proof<256>| root<32>| epoch<32>| share_x<32>| share_y<32>| nullifier<32> ]
+    pub fn generate_proof<R: Read, W: Write>(&self, input_data: R, result_data: W) -> Result<()> {
+        let witness = RLNv2WitnessInput::<E>::read(input_data)?;
+        self.prove(&witness)?.write(result_data)
+    }
+
+    /// generates proof in given format
+    /// * expect `input_data` serialized as in `generate_proof`
+    /// * `result_data` is proof data serialized as
+    /// [ format<1> | proof<var>| root<32>| epoch<32>| share_x<32>| share_y<32>| nullifier<32> ]
+    pub fn generate_tagged_proof<R: Read, W: Write>(
+        &self,
+        input_data: R,
+        format: ProofFormat,
+        result_data: W,
+    ) -> Result<()> {
+        let witness = RLNv2WitnessInput::<E>::read(input_data)?;
+        self.prove(&witness)?.write_tagged(format, result_data)
+    }
+
+    /// generates the proof of a signal
+    pub fn prove(&self, witness: &RLNv2WitnessInput<E>) -> Result<RLNProof<E>> {
+        check_message_limit(witness.user_message_limit)?;
+        if witness.message_id >= witness.user_message_limit {
+            return Err(RLNError::MessageIdOutOfRange);
+        }
+        let hasher = self.rln.hasher();
+        let id_key = witness.id_key;
+        let epoch = witness.epoch;
+        let share_x = witness.signal_hash();
+        let user_message_limit = fr_from_u64::<E>(witness.user_message_limit);
+        let message_id = fr_from_u64::<E>(witness.message_id);
+
+        // line equation
+        let a_1 = Self::slope(&hasher, id_key, epoch, message_id);
+        // evaluate line equation
+        let share_y = evaluate::<E>(&[id_key, a_1], share_x);
+        let nullifier = hasher.hash(vec![a_1]);
+
+        // id key and message limit must be the preimage of the member leaf
+        let id_commitment: E::Fr = hasher.hash(vec![id_key]);
+        let rate_commitment: E::Fr = hasher.hash(vec![id_commitment, user_message_limit]);
+        let (root, auth_path) = self.rln.member_path(witness.id_index, rate_commitment)?;
+
+        let inputs = RLNv2Inputs::<E> {
+            share_x: Some(share_x),
+            share_y: Some(share_y),
+            epoch: Some(epoch),
+            nullifier: Some(nullifier),
+            root: Some(root),
+            id_key: Some(id_key),
+            user_message_limit: Some(user_message_limit),
+            message_id: Some(message_id),
+            auth_path,
+        };
+
+        let circuit = RLNv2Circuit {
+            inputs: inputs.clone(),
+            hasher: PoseidonCircuit::new(self.rln.poseidon_params.clone()),
+        };
+
+        let proof = self.rln.prove_circuit(circuit)?;
+        Ok(RLNProof {
+            proof,
+            values: RLNProofValues {
+                root,
+                epoch,
+                share_x,
+                share_y,
+                nullifier,
+            },
+        })
+    }
+
+    // slope of the secret line, a_1 = H(H(a_0, epoch), message_id)
+    fn slope(hasher: &PoseidonHasher<E>, a_0: E::Fr, epoch: E::Fr, message_id: E::Fr) -> E::Fr {
+        let epoch_secret: E::Fr = hasher.hash(vec![a_0, epoch]);
+        hasher.hash(vec![epoch_secret, message_id])
+    }
+
+    /// given proof and public data verifies the signal
+    /// * expect `input_data` is serialized as:
+    /// [ proof<256>| root<32>| epoch<32>| share_x<32>| share_y<32>| nullifier<32> | signal_len<8> | signal<var> ]
+    pub fn verify<R: Read>(&self, input_data: R) -> Result<bool> {
+        self.rln.verify(input_data)
+    }
+
+    /// verifies the signal of a proof in either format
+    pub fn verify_tagged<R: Read>(&self, input_data: R) -> Result<bool> {
+        self.rln.verify_tagged(input_data)
+    }
+
+    /// verifies the signal and records its share to the nullifier log
+    pub fn verify_and_record<R: Read>(
+        &self,
+        input_data: R,
+        log: &mut NullifierLog<E>,
+    ) -> Result<Option<MessageStatus<E>>> {
+        self.rln.verify_and_record(input_data, log)
+    }
+
+    /// recovers the secret of a member who reused a message id in the same epoch
+    /// * expect `proof_data_0` and `proof_data_1` as outputs of `generate_proof`
+    /// * `message_id` is the message id the member reused
+    /// * `result_data` is serialized as [ id_key<32> | id_commitment<32> ]
+    pub fn recover_secret<R0: Read, R1: Read, W: Write>(
+        &self,
+        proof_data_0: R0,
+        proof_data_1: R1,
+        message_id: u64,
+        mut result_data: W,
+    ) -> Result<()> {
+        if message_id >= 1 << MESSAGE_LIMIT_BITS {
+            return Err(RLNError::MessageIdOutOfRange);
+        }
+        let values = [
+            RLNProof::<E>::read(proof_data_0)?.values,
+            RLNProof::<E>::read(proof_data_1)?.values,
+        ];
+        let epoch = values[0].epoch;
+        let nullifier = values[0].nullifier;
+        if values[1].epoch != epoch {
+            return Err(RLNError::InvalidShares("shares are from different epochs"));
+        }
+        if values[1].nullifier != nullifier {
+            return Err(RLNError::InvalidShares("shares have different nullifiers"));
+        }
+        let shares: Vec<Share<E>> = values
+            .iter()
+            .map(|v| Share::new(v.share_x, v.share_y))
+            .collect();
+        let id_key = recover_secret_from_shares(&shares)?;
+
+        // recovered line must be the one committed by the nullifier for the message id
+        let hasher = self.hasher();
+        let a_1 = Self::slope(&hasher, id_key, epoch, fr_from_u64::<E>(message_id));
+        if hasher.hash(vec![a_1]) != nullifier
+            || shares
+                .iter()
+                .any(|s| evaluate::<E>(&[id_key, a_1], s.x) != s.y)
+        {
+            return Err(RLNError::InvalidShares(
+                "shares do not belong to the same secret line",
+            ));
+        }
+
+        let id_commitment: E::Fr = hasher.hash(vec![id_key]);
+        id_key.into_repr().write_le(&mut result_data)?;
+        id_commitment.into_repr().write_le(&mut result_data)?;
+        Ok(())
+    }
+
+    /// generates public private key pair
+    /// * `key_pair_data` is seralized as [ secret<32> | public<32> ]
+    pub fn key_gen<W: Write>(&self, input_data: W) -> Result<()> {
+        self.rln.key_gen(input_data)
+    }
+
+    pub fn export_verifier_key<W: Write>(&self, w: W) -> Result<()> {
+        self.rln.export_verifier_key(w)
+    }
+
+    pub fn export_circuit_parameters<W: Write>(&self, w: W) -> Result<()> {
+        self.rln.export_circuit_parameters(w)
+    }
+
+    pub fn hasher(&self) -> PoseidonHasher<E> {
+        self.rln.hasher()
+    }
+}
+
+fn read_message_limit<R: Read>(mut reader: R) -> Result<u64> {
+    check_message_limit(reader.read_u64::<LittleEndian>()?)
+}
+
+// user message limit must be non zero and fit in the message limit bits of the circuit
+fn check_message_limit(user_message_limit: u64) -> Result<u64> {
+    if user_message_limit == 0 || user_message_limit >= 1 << MESSAGE_LIMIT_BITS {
+        return Err(RLNError::InvalidMessageLimit);
+    }
+    Ok(user_message_limit)
+}
+
+// Semaphore style membership with a topic nullifier
+//
+// Shares the membership tree layout of RLN, proves membership and reveals
+// a nullifier per topic without rate limiting shares.
+
+#[derive(Clone)]
+pub struct SemaphoreWitnessInput<E>
+where
+    E: Engine,
+{
+    pub id_key: E::Fr,
+    pub id_index: usize,
+    pub external_nullifier: E::Fr,
+    pub signal: Vec<u8>,
+}
+
+impl<E> SemaphoreWitnessInput<E>
+where
+    E: Engine,
+{
+    /// * expect `reader` serialized as [ id_key<32> | id_index<8> | external_nullifier<32> | signal_len<8> | signal<var> ]
+    pub fn read<R: Read>(mut reader: R) -> Result<SemaphoreWitnessInput<E>> {
+        let id_key = read_fr::<_, E>(&mut reader, 1)?[0];
+        let id_index = reader.read_u64::<LittleEndian>()? as usize;
+        let external_nullifier = read_fr::<_, E>(&mut reader, 1)?[0];
+        let signal = read_signal(reader)?;
+        Ok(SemaphoreWitnessInput {
+            id_key,
+            id_index,
+            external_nullifier,
+            signal,
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        self.id_key.into_repr().write_le(&mut writer)?;
+        writer.write_u64::<LittleEndian>(self.id_index as u64)?;
+        self.external_nullifier.into_repr().write_le(&mut writer)?;
+        writer.write_u64::<LittleEndian>(self.signal.len() as u64)?;
+        writer.write_all(&self.signal[..])?;
+        Ok(())
+    }
+
+    pub fn signal_hash(&self) -> E::Fr {
+        hash_to_field::<E>(&self.signal[..])
+    }
+}
+
+#[derive(Clone)]
+pub struct SemaphoreProofValues<E>
+where
+    E: Engine,
+{
+    pub root: E::Fr,
+    pub external_nullifier: E::Fr,
+    pub signal_hash: E::Fr,
+    pub nullifier: E::Fr,
+}
+
+impl<E> SemaphoreProofValues<E>
+where
+    E: Engine,
+{
+    /// * expect `reader` serialized as [ root<32>| external_nullifier<32>| signal_hash<32>| nullifier<32> ]
+    pub fn read<R: Read>(reader: R) -> Result<SemaphoreProofValues<E>> {
+        let values = read_fr::<_, E>(reader, 4)?;
+        Ok(SemaphoreProofValues {
+            root: values[0],
+            external_nullifier: values[1],
+            signal_hash: values[2],
+            nullifier: values[3],
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        for value in self.public_inputs().iter() {
+            value.into_repr().write_le(&mut writer)?;
+        }
+        Ok(())
+    }
+
+    /// public inputs of the circuit, [ root, external_nullifier, signal_hash, nullifier ]
+    pub fn public_inputs(&self) -> Vec<E::Fr> {
+        vec![
+            self.root,
+            self.external_nullifier,
+            self.signal_hash,
+            self.nullifier,
+        ]
+    }
+}
+
+#[derive(Clone)]
+pub struct SemaphoreProof<E>
+where
+    E: Engine,
+{
+    pub proof: Proof<E>,
+    pub values: SemaphoreProofValues<E>,
+}
+
+impl<E> SemaphoreProof<E>
+where
+    E: Engine,
+{
+    /// * expect `reader` serialized as [ proof<256>| root<32>| external_nullifier<32>| signal_hash<32>| nullifier<32> ]
+    pub fn read<R: Read>(mut reader: R) -> Result<SemaphoreProof<E>> {
+        let proof = read_uncompressed_proof(&mut reader)?;
+        let values = SemaphoreProofValues::read(&mut reader)?;
+        Ok(SemaphoreProof { proof, values })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        write_uncompressed_proof(self.proof.clone(), &mut writer)?;
+        self.values.write(&mut writer)
+    }
+}
+
+pub struct Semaphore<E: Engine> {
+    rln: RLN<E>,
+}
+
+impl<E: Engine> Semaphore<E> {
+    fn new_circuit(
+        merkle_depth: usize,
+        poseidon_params: PoseidonParams<E>,
+    ) -> Result<Parameters<E>> {
+        let mut rng = thread_rng();
+        let inputs = SemaphoreInputs::<E>::empty(merkle_depth);
+        let circuit = SemaphoreCircuit::<E> {
+            inputs,
+            hasher: PoseidonCircuit::new(poseidon_params.clone()),
+        };
+        let circuit_parameters = generate_random_parameters(circuit, &mut rng)?;
+        Ok(circuit_parameters)
+    }
+
+    pub fn new(
+        merkle_depth: usize,
+        poseidon_params: Option<PoseidonParams<E>>,
+    ) -> Result<Semaphore<E>> {
+        let poseidon_params = match poseidon_params {
+            Some(params) => params,
+            None => RLN::<E>::default_poseidon_params(),
+        };
+        let circuit_parameters = Self::new_circuit(merkle_depth, poseidon_params.clone())?;
+        Ok(Semaphore {
+            rln: RLN::new_with_params(
+                CircuitKind::Semaphore,
+                merkle_depth,
+                1,
+                circuit_parameters,
+                poseidon_params,
+            ),
+        })
+    }
+
+    /// * expect `raw_circuit_parameters` as written by `export_circuit_parameters`
+    pub fn new_with_raw_params<R: Read>(
+        merkle_depth: usize,
+        raw_circuit_parameters: R,
+        poseidon_params: Option<PoseidonParams<E>>,
+    ) -> Result<Semaphore<E>> {
+        let rln = RLN::new_with_circuit_params(
+            CircuitKind::Semaphore,
+            merkle_depth,
+            1,
+            raw_circuit_parameters,
+            poseidon_params,
+            ParametersCheck::Full,
+        )?;
+        Ok(Semaphore { rln })
+    }
+
+    /// returns current membership root
+    /// * `root` is a scalar field element in 32 bytes
+    pub fn get_root<W: Write>(&self, result_data: W) -> Result<()> {
+        self.rln.get_root(result_data)
+    }
+
+    /// inserts new member with given public key
+    /// * `input_data` is a 32 scalar field element in 32 bytes
+    pub fn update_next_member<R: Read>(&mut self, input_data: R) -> Result<()> {
+        self.rln.update_next_member(input_data)
+    }
+
+    //// deletes member with given index
+    pub fn delete_member(&mut self, index: usize) -> Result<()> {
+        self.rln.delete_member(index)
+    }
+
+    /// returns the index of the member with given public key
+    /// * `input_data` is id commitment in 32 bytes
+    pub fn find_member_index<R: Read>(&self, input_data: R) -> Result<usize> {
+        self.rln.find_member_index(input_data)
+    }
+
+    /// sets the number of most recent membership roots that proofs are accepted against
+    pub fn set_root_window(&mut self, size: usize) {
+        self.rln.set_root_window(size)
+    }
+
+    /// given authorization data and the topic generates public inputs and proof
+    /// * expect `input_data` serialized as [ id_key<32> | id_index<8> | external_nullifier<32> | signal_len<8> | signal<var> ]
+    /// * `result_data` is proof data serialized as [ proof<256>| root<32>| external_nullifier<32>| signal_hash<32>| nullifier<32> ]
+    pub fn generate_proof<R: Read, W: Write>(&self, input_data: R, result_data: W) -> Result<()> {
+        let witness = SemaphoreWitnessInput::<E>::read(input_data)?;
+        self.prove(&witness)?.write(result_data)
+    }
+
+    /// generates the proof of a signal
+    pub fn prove(&self, witness: &SemaphoreWitnessInput<E>) -> Result<SemaphoreProof<E>> {
+        let hasher = self.rln.hasher();
+        let id_key = witness.id_key;
+        let external_nullifier = witness.external_nullifier;
+        let signal_hash = witness.signal_hash();
+        let nullifier = semaphore_nullifier(&hasher, id_key, external_nullifier);
+
+        // id key must be the preimage of the member leaf
+        let id_commitment: E::Fr = hasher.hash(vec![id_key]);
+        let (root, auth_path) = self.rln.member_path(witness.id_index, id_commitment)?;
+
+        let inputs = SemaphoreInputs::<E> {
+            root: Some(root),
+            external_nullifier: Some(external_nullifier),
+            signal_hash: Some(signal_hash),
+            nullifier: Some(nullifier),
+            id_key: Some(id_key),
+            auth_path,
+        };
+
+        let circuit = SemaphoreCircuit {
+            inputs,
+            hasher: PoseidonCircuit::new(self.rln.poseidon_params.clone()),
+        };
+
+        let proof = self.rln.prove_circuit(circuit)?;
+        Ok(SemaphoreProof {
+            proof,
+            values: SemaphoreProofValues {
+                root,
+                external_nullifier,
+                signal_hash,
+                nullifier,
+            },
+        })
+    }
+
+    /// given proof and public data verifies the signal
+    /// * expect `input_data` is serialized as:
+    /// [ proof<256>| root<32>| external_nullifier<32>| signal_hash<32>| nullifier<32> | signal_len<8> | signal<var> ]
+    pub fn verify<R: Read>(&self, mut input_data: R) -> Result<bool> {
+        let semaphore_proof = SemaphoreProof::<E>::read(&mut input_data)?;
+        let signal_hash = read_signal_hash::<_, E>(input_data)?;
+        if signal_hash != semaphore_proof.values.signal_hash {
+            return Err(RLNError::SignalHashMismatch);
+        }
+        self.rln.verify_public_inputs(
+            &semaphore_proof.proof,
+            &semaphore_proof.values.public_inputs(),
+        )
+    }
+
+    /// verifies the proof of a signal
+    /// * proofs against a root outside of the recent root window are rejected
+    pub fn verify_semaphore_proof(
+        &self,
+        semaphore_proof: &SemaphoreProof<E>,
+        signal: &[u8],
+    ) -> Result<bool> {
+        if hash_to_field::<E>(signal) != semaphore_proof.values.signal_hash {
+            return Err(RLNError::SignalHashMismatch);
+        }
+        self.rln.verify_public_inputs(
+            &semaphore_proof.proof,
+            &semaphore_proof.values.public_inputs(),
+        )
+    }
+
+    /// generates public private key pair
+    /// * `key_pair_data` is seralized as [ secret<32> | public<32> ]
+    pub fn key_gen<W: Write>(&self, input_data: W) -> Result<()> {
+        self.rln.key_gen(input_data)
+    }
+
+    pub fn export_verifier_key<W: Write>(&self, w: W) -> Result<()> {
+        self.rln.export_verifier_key(w)
+    }
+
+    pub fn export_circuit_parameters<W: Write>(&self, w: W) -> Result<()> {
+        self.rln.export_circuit_parameters(w)
+    }
+
+    pub fn hasher(&self) -> PoseidonHasher<E> {
+        self.rln.hasher()
+    }
+}
+
+// Shielded transfers
+//
+// Notes of the pool are leaves of the note tree. A join split proof spends
+// up to two notes and creates two new ones, the owner key of a note is an
+// `Identity` where owner_pk is the id commitment. The nullifier does not bind
+// the position of a note, so the pool keeps commitments unique and a nullifier
+// spends a single note.
+
+#[derive(Clone)]
+pub struct Note<E>
+where
+    E: Engine,
+{
+    pub value: u64,
+    pub owner_pk: E::Fr,
+    pub blinding: E::Fr,
+}
+
+impl<E> Note<E>
+where
+    E: Engine,
+{
+    /// creates a note to given owner with a random blinding
+    pub fn new<R: Rng>(value: u64, owner_pk: E::Fr, rng: &mut R) -> Note<E> {
+        Note {
+            value,
+            owner_pk,
+            blinding: E::Fr::rand(rng),
+        }
+    }
+
+    /// H(H(value, owner_pk), blinding), the leaf of the note in the note tree
+    pub fn commitment(&self, hasher: &PoseidonHasher<E>) -> E::Fr {
+        let value_owner = hasher.hash(vec![fr_from_u64::<E>(self.value), self.owner_pk]);
+        hasher.hash(vec![value_owner, self.blinding])
+    }
+
+    /// H(commitment, owner_sk), revealed when the note is spent
+    pub fn nullifier(&self, hasher: &PoseidonHasher<E>, owner_sk: E::Fr) -> E::Fr {
+        hasher.hash(vec![self.commitment(hasher), owner_sk])
+    }
+
+    /// * expect `reader` serialized as [ value<8> | owner_pk<32> | blinding<32> ]
+    pub fn read<R: Read>(mut reader: R) -> Result<Note<E>> {
+        let value = reader.read_u64::<LittleEndian>()?;
+        let keys = read_fr::<_, E>(&mut reader, 2)?;
+        Ok(Note {
+            value,
+            owner_pk: keys[0],
+            blinding: keys[1],
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_u64::<LittleEndian>(self.value)?;
+        self.owner_pk.into_repr().write_le(&mut writer)?;
+        self.blinding.into_repr().write_le(&mut writer)?;
+        Ok(())
+    }
+}
+
+// note to spend together with the key of its owner and its index in the note tree
+
+#[derive(Clone)]
+pub struct Spend<E>
+where
+    E: Engine,
+{
+    pub note: Note<E>,
+    pub owner_sk: E::Fr,
+    pub index: usize,
+}
+
+// spent and created notes of a transaction before it is proven
+
+#[derive(Clone)]
+pub struct TransactionBuilder<E>
+where
+    E: Engine,
+{
+    spends: Vec<Spend<E>>,
+    outputs: Vec<Note<E>>,
+    value_delta: i64,
+}
+
+impl<E> TransactionBuilder<E>
+where
+    E: Engine,
+{
+    pub fn new() -> TransactionBuilder<E> {
+        TransactionBuilder {
+            spends: Vec::new(),
+            outputs: Vec::new(),
+            value_delta: 0,
+        }
+    }
+
+    /// spends the note at `index` of the note tree
+    pub fn spend(&mut self, note: Note<E>, owner_sk: E::Fr, index: usize) -> Result<&mut Self> {
+        if self.spends.len() == JOIN_SPLIT_INPUTS {
+            return Err(RLNError::InvalidTransaction("too many spent notes"));
+        }
+        self.spends.push(Spend {
+            note,
+            owner_sk,
+            index,
+        });
+        Ok(self)
+    }
+
+    /// creates a new note
+    pub fn output(&mut self, note: Note<E>) -> Result<&mut Self> {
+        if self.outputs.len() == JOIN_SPLIT_OUTPUTS {
+            return Err(RLNError::InvalidTransaction("too many created notes"));
+        }
+        self.outputs.push(note);
+        Ok(self)
+    }
+
+    /// sets the value added to the pool, negative if value is withdrawn
+    pub fn value_delta(&mut self, value_delta: i64) -> &mut Self {
+        self.value_delta = value_delta;
+        self
+    }
+
+    fn check_balance(&self) -> Result<()> {
+        let inputs = self.spends.iter().fold(self.value_delta as i128, |acc, s| {
+            acc + s.note.value as i128
+        });
+        let outputs = self
+            .outputs
+            .iter()
+            .fold(0i128, |acc, n| acc + n.value as i128);
+        if inputs != outputs {
+            return Err(RLNError::InvalidTransaction("value is not conserved"));
+        }
+        Ok(())
+    }
+}
+
+fn value_delta_to_field<E: Engine>(value_delta: i64) -> E::Fr {
+    let mut value = fr_from_u64::<E>(value_delta.unsigned_abs());
+    if value_delta < 0 {
+        value.negate();
+    }
+    value
+}
+
+#[derive(Clone)]
+pub struct JoinSplitProofValues<E>
+where
+    E: Engine,
+{
+    pub root: E::Fr,
+    pub value_delta: i64,
+    pub nullifiers: Vec<E::Fr>,
+    pub commitments: Vec<E::Fr>,
+}
+
+impl<E> JoinSplitProofValues<E>
+where
+    E: Engine,
+{
+    /// * expect `reader` serialized as [ root<32>| value_delta<8>| nullifier<32> * 2| commitment<32> * 2 ]
+    pub fn read<R: Read>(mut reader: R) -> Result<JoinSplitProofValues<E>> {
+        let root = read_fr::<_, E>(&mut reader, 1)?[0];
+        let value_delta = reader.read_i64::<LittleEndian>()?;
+        let nullifiers = read_fr::<_, E>(&mut reader, JOIN_SPLIT_INPUTS)?;
+        let commitments = read_fr::<_, E>(&mut reader, JOIN_SPLIT_OUTPUTS)?;
+        Ok(JoinSplitProofValues {
+            root,
+            value_delta,
+            nullifiers,
+            commitments,
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        self.root.into_repr().write_le(&mut writer)?;
+        writer.write_i64::<LittleEndian>(self.value_delta)?;
+        for e in self.nullifiers.iter().chain(self.commitments.iter()) {
+            e.into_repr().write_le(&mut writer)?;
+        }
+        Ok(())
+    }
+
+    /// public inputs in the order of `JoinSplitInputs::public_inputs`
+    pub fn public_inputs(&self) -> Vec<E::Fr> {
+        let mut inputs = vec![self.root, value_delta_to_field::<E>(self.value_delta)];
+        inputs.extend(self.nullifiers.iter().cloned());
+        inputs.extend(self.commitments.iter().cloned());
+        inputs
+    }
+}
+
+#[derive(Clone)]
+pub struct JoinSplitProof<E>
+where
+    E: Engine,
+{
+    pub proof: Proof<E>,
+    pub values: JoinSplitProofValues<E>,
+}
+
+impl<E> JoinSplitProof<E>
+where
+    E: Engine,
+{
+    /// * expect `reader` serialized as [ proof<256>| root<32>| value_delta<8>| nullifier<32> * 2| commitment<32> * 2 ]
+    pub fn read<R: Read>(mut reader: R) -> Result<JoinSplitProof<E>> {
+        let proof = read_uncompressed_proof(&mut reader)?;
+        let values = JoinSplitProofValues::read(&mut reader)?;
+        Ok(JoinSplitProof { proof, values })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        write_uncompressed_proof(self.proof.clone(), &mut writer)?;
+        self.values.write(&mut writer)
+    }
+}
+
+pub struct ShieldedPool<E: Engine> {
+    rln: RLN<E>,
+    // keys of spent nullifiers
+    nullifiers: HashSet<Vec<u64>>,
+    // keys of commitments in the note tree
+    commitments: HashSet<Vec<u64>>,
+}
+
+impl<E: Engine> ShieldedPool<E> {
+    fn new_circuit(
+        merkle_depth: usize,
+        poseidon_params: PoseidonParams<E>,
+    ) -> Result<Parameters<E>> {
+        let mut rng = thread_rng();
+        let inputs = JoinSplitInputs::<E>::empty(merkle_depth);
+        let circuit = JoinSplitCircuit::<E> {
+            inputs,
+            hasher: PoseidonCircuit::new(poseidon_params.clone()),
+        };
+        let circuit_parameters = generate_random_parameters(circuit, &mut rng)?;
+        Ok(circuit_parameters)
+    }
+
+    pub fn new(
+        merkle_depth: usize,
+        poseidon_params: Option<PoseidonParams<E>>,
+    ) -> Result<ShieldedPool<E>> {
+        let poseidon_params = match poseidon_params {
+            Some(params) => params,
+            None => RLN::<E>::default_poseidon_params(),
+        };
+        let circuit_parameters = Self::new_circuit(merkle_depth, poseidon_params.clone())?;
+        Ok(ShieldedPool {
+            rln: RLN::new_with_params(
+                CircuitKind::JoinSplit,
+                merkle_depth,
+                1,
+                circuit_parameters,
+                poseidon_params,
+            ),
+            nullifiers: HashSet::new(),
+            commitments: HashSet::new(),
+        })
+    }
+
+    /// * expect `raw_circuit_parameters` as written by `export_circuit_parameters`
+    pub fn new_with_raw_params<R: Read>(
+        merkle_depth: usize,
+        raw_circuit_parameters: R,
+        poseidon_params: Option<PoseidonParams<E>>,
+    ) -> Result<ShieldedPool<E>> {
+        let rln = RLN::new_with_circuit_params(
+            CircuitKind::JoinSplit,
+            merkle_depth,
+            1,
+            raw_circuit_parameters,
+            poseidon_params,
+            ParametersCheck::Full,
+        )?;
+        Ok(ShieldedPool {
+            rln,
+            nullifiers: HashSet::new(),
+            commitments: HashSet::new(),
+        })
+    }
+
+    /// returns current root of the note tree
+    /// * `root` is a scalar field element in 32 bytes
+    pub fn get_root<W: Write>(&self, result_data: W) -> Result<()> {
+        self.rln.get_root(result_data)
+    }
+
+    /// sets the number of most recent note tree roots that transactions are accepted against
+    pub fn set_root_window(&mut self, size: usize) {
+        self.rln.set_root_window(size)
+    }
+
+    /// returns the index of the note in the note tree
+    pub fn note_index(&self, note: &Note<E>) -> Option<usize> {
+        self.rln.tree.leaf_index(note.commitment(&self.hasher()))
+    }
+
+    /// returns true if a note with given nullifier is spent
+    pub fn is_spent(&self, nullifier: &E::Fr) -> bool {
+        self.nullifiers.contains(&fr_key::<E>(nullifier))
+    }
+
+    /// proves the transaction against the current note tree
+    /// * missing spends and outputs are filled with notes of zero value
+    pub fn prove(&self, transaction: &TransactionBuilder<E>) -> Result<JoinSplitProof<E>> {
+        transaction.check_balance()?;
+        let hasher = self.hasher();
+        let mut rng = thread_rng();
+
+        let mut spends = transaction.spends.clone();
+        while spends.len() < JOIN_SPLIT_INPUTS {
+            let owner = Identity::random(&hasher, &mut rng);
+            spends.push(Spend {
+                note: Note::new(0, owner.id_commitment, &mut rng),
+                owner_sk: owner.id_key,
+                index: 0,
+            });
+        }
+        let mut outputs = transaction.outputs.clone();
+        while outputs.len() < JOIN_SPLIT_OUTPUTS {
+            outputs.push(Note::new(0, E::Fr::rand(&mut rng), &mut rng));
+        }
+
+        let mut input_notes = Vec::with_capacity(JOIN_SPLIT_INPUTS);
+        let mut nullifiers = Vec::with_capacity(JOIN_SPLIT_INPUTS);
+        for spend in spends.iter() {
+            // notes of zero value are not checked against the note tree,
+            // their path may run through empty leaves
+            let auth_path = if spend.note.value == 0 {
+                self.rln.tree.merkle_tree.get_witness(spend.index)?
+            } else {
+                if self.rln.tree.get_leaf(spend.index)? != spend.note.commitment(&hasher) {
+                    return Err(RLNError::InvalidTransaction("note is not at given index"));
+                }
+                self.rln.tree.get_witness(spend.index)?
+            };
+            input_notes.push(InputNote {
+                value: Some(fr_from_u64::<E>(spend.note.value)),
+                owner_sk: Some(spend.owner_sk),
+                blinding: Some(spend.note.blinding),
+                auth_path: auth_path.into_iter().map(|w| Some(w)).collect(),
+            });
+            nullifiers.push(spend.note.nullifier(&hasher, spend.owner_sk));
+        }
+
+        let commitments: Vec<E::Fr> = outputs.iter().map(|n| n.commitment(&hasher)).collect();
+        let output_notes = outputs
+            .iter()
+            .map(|n| OutputNote {
+                value: Some(fr_from_u64::<E>(n.value)),
+                owner_pk: Some(n.owner_pk),
+                blinding: Some(n.blinding),
+            })
+            .collect();
+
+        let root = self.rln.tree.get_root();
+        let inputs = JoinSplitInputs::<E> {
+            root: Some(root),
+            value_delta: Some(value_delta_to_field::<E>(transaction.value_delta)),
+            nullifiers: nullifiers.iter().map(|n| Some(*n)).collect(),
+            commitments: commitments.iter().map(|c| Some(*c)).collect(),
+            input_notes,
+            output_notes,
+        };
+
+        let circuit = JoinSplitCircuit {
+            inputs,
+            hasher: PoseidonCircuit::new(self.rln.poseidon_params.clone()),
+        };
+        let proof = self.rln.prove_circuit(circuit)?;
+        Ok(JoinSplitProof {
+            proof,
+            values: JoinSplitProofValues {
+                root,
+                value_delta: transaction.value_delta,
+                nullifiers,
+                commitments,
+            },
+        })
+    }
+
+    /// given proof and public data verifies the transaction
+    /// * expect `input_data` is serialized as:
+    /// [ proof<256>| root<32>| value_delta<8>| nullifier<32> * 2| commitment<32> * 2 ]
+    pub fn verify<R: Read>(&self, input_data: R) -> Result<bool> {
+        let transaction = JoinSplitProof::<E>::read(input_data)?;
+        self.verify_transaction(&transaction)
+    }
+
+    /// verifies the transaction proof
+    /// * transactions spending a note twice, creating a note already in the note tree,
+    /// against a root outside of the root window or not spending and creating two notes are rejected
+    pub fn verify_transaction(&self, transaction: &JoinSplitProof<E>) -> Result<bool> {
+        let nullifiers = &transaction.values.nullifiers;
+        let commitments = &transaction.values.commitments;
+        if nullifiers.len() != JOIN_SPLIT_INPUTS || commitments.len() != JOIN_SPLIT_OUTPUTS {
+            return Ok(false);
+        }
+        if nullifiers.iter().any(|n| self.is_spent(n)) || nullifiers[0] == nullifiers[1] {
+            return Ok(false);
+        }
+        if commitments
+            .iter()
+            .any(|c| self.commitments.contains(&fr_key::<E>(c)))
+            || commitments[0] == commitments[1]
+        {
+            return Ok(false);
+        }
+        self.rln
+            .verify_public_inputs(&transaction.proof, &transaction.values.public_inputs())
+    }
+
+    /// verifies the transaction, marks spent notes and appends created notes to the note tree
+    /// * returns false and leaves the pool unchanged if the transaction is not valid
+    /// * the pool is also unchanged if the note tree has no room for the created notes
+    pub fn apply_transaction(&mut self, transaction: &JoinSplitProof<E>) -> Result<bool> {
+        if !self.verify_transaction(transaction)? {
+            return Ok(false);
+        }
+        self.rln.tree.append_many(&transaction.values.commitments)?;
+        for commitment in transaction.values.commitments.iter() {
+            self.commitments.insert(fr_key::<E>(commitment));
+        }
+        for nullifier in transaction.values.nullifiers.iter() {
+            self.nullifiers.insert(fr_key::<E>(nullifier));
+        }
+        Ok(true)
+    }
+
+    /// generates owner key pair of notes
+    /// * `key_pair_data` is seralized as [ secret<32> | public<32> ]
+    pub fn key_gen<W: Write>(&self, input_data: W) -> Result<()> {
+        self.rln.key_gen(input_data)
+    }
+
+    pub fn export_verifier_key<W: Write>(&self, w: W) -> Result<()> {
+        self.rln.export_verifier_key(w)
+    }
+
+    pub fn export_circuit_parameters<W: Write>(&self, w: W) -> Result<()> {
+        self.rln.export_circuit_parameters(w)
+    }
+
+    pub fn hasher(&self) -> PoseidonHasher<E> {
+        self.rln.hasher()
+    }
+}
+
+#[test]
+fn test_typed_proof() {
+    use crate::circuit::bench;
+    use bellman::pairing::bn256::{Bn256, Fr};
+
+    let poseidon_params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+    let rln_test = bench::RLNTest::<Bn256>::new(3, Some(poseidon_params));
+    let rln = &rln_test.rln;
+
+    // the test member is inserted at index 0 with secret 1001
+    let witness = RLNWitnessInput::<Bn256> {
+        id_key: Fr::from_str("1001").unwrap(),
+        id_index: 0,
+        epoch: Fr::from_str("7").unwrap(),
+        signal: b"rln signal test xyz abc".to_vec(),
+    };
+    let mut witness_data: Vec<u8> = Vec::new();
+    witness.write(&mut witness_data).unwrap();
+    let read_witness = RLNWitnessInput::<Bn256>::read(witness_data.as_slice()).unwrap();
+    assert_eq!(read_witness.signal, witness.signal);
+    assert_eq!(read_witness.epoch, witness.epoch);
+
+    // signal length is not trusted for allocation
+    let mut truncated: Vec<u8> = witness_data[..32 + 8 + 32].to_vec();
+    truncated.write_u64::<LittleEndian>(u64::MAX).unwrap();
+    truncated.extend_from_slice(b"short");
+    assert!(RLNWitnessInput::<Bn256>::read(truncated.as_slice()).is_err());
+
+    let rln_proof = rln.prove(&witness).unwrap();
+    assert!(rln.verify_rln_proof(&rln_proof, &witness.signal).unwrap());
+    assert!(rln.verify_rln_proof(&rln_proof, b"other signal").is_err());
+
+    // typed proof is serialized as the output of generate_proof
+    let mut proof_data: Vec<u8> = Vec::new();
+    rln_proof.write(&mut proof_data).unwrap();
+    let read_proof = RLNProof::<Bn256>::read(proof_data.as_slice()).unwrap();
+    assert_eq!(
+        read_proof.values.public_inputs(),
+        rln_proof.values.public_inputs()
+    );
+    proof_data
+        .write_u64::<LittleEndian>(witness.signal.len() as u64)
+        .unwrap();
+    proof_data.write_all(&witness.signal[..]).unwrap();
+    assert!(rln.verify(proof_data.as_slice()).unwrap());
+
+    let mut tagged_data: Vec<u8> = Vec::new();
+    rln_proof
+        .write_tagged(ProofFormat::Compressed, &mut tagged_data)
+        .unwrap();
+    tagged_data
+        .write_u64::<LittleEndian>(witness.signal.len() as u64)
+        .unwrap();
+    tagged_data.write_all(&witness.signal[..]).unwrap();
+    assert!(rln.verify_tagged(tagged_data.as_slice()).unwrap());
+
+    let wrong_witness = RLNWitnessInput {
+        id_key: Fr::from_str("1002").unwrap(),
+        ..witness
+    };
+    match rln.prove(&wrong_witness) {
+        Err(RLNError::IdKeyMismatch) => (),
+        _ => panic!("expected id key mismatch"),
+    }
+}
+
+#[test]
+fn test_root_window() {
+    use crate::circuit::bench;
+    use bellman::pairing::bn256::{Bn256, Fr};
+
+    let poseidon_params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+    let mut rln_test = bench::RLNTest::<Bn256>::new(3, Some(poseidon_params));
+    let rln = &mut rln_test.rln;
+    rln.set_root_window(2);
+
+    // the test member is inserted at index 0 with secret 1001
+    let witness = RLNWitnessInput::<Bn256> {
+        id_key: Fr::from_str("1001").unwrap(),
+        id_index: 0,
+        epoch: Fr::from_str("7").unwrap(),
+        signal: b"rln signal test xyz abc".to_vec(),
+    };
+    let rln_proof = rln.prove(&witness).unwrap();
+    let mut proof_data: Vec<u8> = Vec::new();
+    rln_proof.write(&mut proof_data).unwrap();
+    proof_data
+        .write_u64::<LittleEndian>(witness.signal.len() as u64)
+        .unwrap();
+    proof_data.write_all(&witness.signal[..]).unwrap();
+
+    let mut member_data: Vec<u8> = Vec::new();
+    Fr::from_str("11")
+        .unwrap()
+        .into_repr()
+        .write_le(&mut member_data)
+        .unwrap();
+    rln.update_next_member(member_data.as_slice()).unwrap();
+    assert!(rln.verify_rln_proof(&rln_proof, &witness.signal).unwrap());
+    assert!(rln.verify(proof_data.as_slice()).unwrap());
+
+    // the root of the proof has left the window
+    rln.delete_member(1).unwrap();
+    assert!(!rln.verify_rln_proof(&rln_proof, &witness.signal).unwrap());
+    assert!(!rln.verify(proof_data.as_slice()).unwrap());
+}
+
+#[test]
+fn test_invalid_share_degree() {
+    use bellman::pairing::bn256::Bn256;
+
+    match RLN::<Bn256>::new_with_degree(3, 0, None) {
+        Err(RLNError::InvalidShareDegree) => (),
+        _ => panic!("expected invalid share degree"),
+    }
+}
+
+#[test]
+fn test_rln_v2_recover_secret() {
+    use bellman::pairing::bn256::{Bn256, Fr};
+
+    let mut rln = RLNv2::<Bn256>::new(3, None).unwrap();
+    let hasher = rln.hasher();
+    let id_key = Fr::from_str("1001").unwrap();
+    let mut input_data: Vec<u8> = Vec::new();
+    hasher
+        .hash(vec![id_key])
+        .into_repr()
+        .write_le(&mut input_data)
+        .unwrap();
+    input_data.write_u64::<LittleEndian>(4).unwrap();
+    let mut rate_commitment: Vec<u8> = Vec::new();
+    rln.rate_commitment(input_data.as_slice(), &mut rate_commitment)
+        .unwrap();
+    rln.update_next_member(rate_commitment.as_slice()).unwrap();
+
+    let witness = |message_id: u64, signal: &[u8]| RLNv2WitnessInput::<Bn256> {
+        id_key,
+        id_index: 0,
+        user_message_limit: 4,
+        message_id,
+        epoch: Fr::from_str("7").unwrap(),
+        signal: signal.to_vec(),
+    };
+    let prove = |message_id: u64, signal: &[u8]| {
+        let mut input_data: Vec<u8> = Vec::new();
+        witness(message_id, signal).write(&mut input_data).unwrap();
+        let mut proof_data: Vec<u8> = Vec::new();
+        rln.generate_proof(input_data.as_slice(), &mut proof_data)
+            .unwrap();
+        proof_data
+    };
+
+    // typed proof is serialized as the output of generate_proof
+    let first = witness(2, b"first signal");
+    let rln_proof = rln.prove(&first).unwrap();
+    let mut proof_0: Vec<u8> = Vec::new();
+    rln_proof.write(&mut proof_0).unwrap();
+    let mut tagged_data: Vec<u8> = Vec::new();
+    rln_proof
+        .write_tagged(ProofFormat::Compressed, &mut tagged_data)
+        .unwrap();
+    tagged_data
+        .write_u64::<LittleEndian>(first.signal.len() as u64)
+        .unwrap();
+    tagged_data.write_all(&first.signal[..]).unwrap();
+    assert!(rln.verify_tagged(tagged_data.as_slice()).unwrap());
+
+    // message id 2 is reused
+    let proof_1 = prove(2, b"second signal");
+    let mut result_data: Vec<u8> = Vec::new();
+    rln.recover_secret(proof_0.as_slice(), proof_1.as_slice(), 2, &mut result_data)
+        .unwrap();
+    let recovered = read_fr::<_, Bn256>(result_data.as_slice(), 2).unwrap();
+    assert_eq!(recovered[0], id_key);
+    assert_eq!(recovered[1], hasher.hash(vec![id_key]));
+
+    // the line of another message id is not committed by the nullifier
+    match rln.recover_secret(proof_0.as_slice(), proof_1.as_slice(), 1, Vec::new()) {
+        Err(RLNError::InvalidShares(_)) => (),
+        _ => panic!("expected a line of another message id"),
+    }
+
+    // distinct message ids within the limit do not reveal the secret
+    let proof_2 = prove(3, b"third signal");
+    match rln.recover_secret(proof_0.as_slice(), proof_2.as_slice(), 2, Vec::new()) {
+        Err(RLNError::InvalidShares(_)) => (),
+        _ => panic!("expected different nullifiers"),
+    }
+
+    // message id must be below the limit of the member
+    match rln.prove(&witness(4, b"fifth signal")) {
+        Err(RLNError::MessageIdOutOfRange) => (),
+        _ => panic!("expected message id out of range"),
+    }
+}
+
+#[test]
+fn test_semaphore_proof() {
+    use bellman::pairing::bn256::{Bn256, Fr};
+
+    let merkle_depth = 3usize;
+    let semaphore = {
+        let mut semaphore = Semaphore::<Bn256>::new(merkle_depth, None).unwrap();
+        let identity = Identity::new(&semaphore.hasher(), Fr::from_str("1001").unwrap());
+        let mut commitment: Vec<u8> = Vec::new();
+        identity
+            .id_commitment
+            .into_repr()
+            .write_le(&mut commitment)
+            .unwrap();
+        semaphore.update_next_member(commitment.as_slice()).unwrap();
+        semaphore
+    };
+
+    let witness = SemaphoreWitnessInput::<Bn256> {
+        id_key: Fr::from_str("1001").unwrap(),
+        id_index: 0,
+        external_nullifier: Fr::from_str("42").unwrap(),
+        signal: b"semaphore signal".to_vec(),
+    };
+    let mut witness_data: Vec<u8> = Vec::new();
+    witness.write(&mut witness_data).unwrap();
+    let mut truncated: Vec<u8> = witness_data[..32 + 8 + 32].to_vec();
+    truncated.write_u64::<LittleEndian>(u64::MAX).unwrap();
+    assert!(SemaphoreWitnessInput::<Bn256>::read(truncated.as_slice()).is_err());
+    let mut proof_data: Vec<u8> = Vec::new();
+    semaphore
+        .generate_proof(witness_data.as_slice(), &mut proof_data)
+        .unwrap();
+
+    let semaphore_proof = SemaphoreProof::<Bn256>::read(proof_data.as_slice()).unwrap();
+    let hasher = semaphore.hasher();
+    let nullifier = semaphore_nullifier(&hasher, witness.id_key, witness.external_nullifier);
+    assert_eq!(semaphore_proof.values.nullifier, nullifier);
+
+    // the nullifier is not the first RLN coefficient of an epoch equal to the topic
+    let coeffs = polynomial_coefficients(&hasher, witness.id_key, witness.external_nullifier, 1);
+    assert_ne!(semaphore_proof.values.nullifier, coeffs[1]);
+    assert_ne!(
+        semaphore_proof.values.nullifier,
+        hasher.hash(vec![coeffs[1]])
+    );
+    assert!(semaphore
+        .verify_semaphore_proof(&semaphore_proof, &witness.signal)
+        .unwrap());
+    assert!(semaphore
+        .verify_semaphore_proof(&semaphore_proof, b"other signal")
+        .is_err());
+
+    proof_data
+        .write_u64::<LittleEndian>(witness.signal.len() as u64)
+        .unwrap();
+    proof_data.write_all(&witness.signal[..]).unwrap();
+    assert!(semaphore.verify(proof_data.as_slice()).unwrap());
+
+    let wrong_witness = SemaphoreWitnessInput {
+        id_key: Fr::from_str("1002").unwrap(),
+        ..witness
+    };
+    match semaphore.prove(&wrong_witness) {
+        Err(RLNError::IdKeyMismatch) => (),
+        _ => panic!("expected id key mismatch"),
+    }
+}
+
+#[test]
+fn test_shielded_transfer() {
+    use bellman::pairing::bn256::Bn256;
+    use rand::{SeedableRng, XorShiftRng};
+
+    let mut rng = XorShiftRng::from_seed([0x3dbe6258, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let mut pool = ShieldedPool::<Bn256>::new(3, None).unwrap();
+    let hasher = pool.hasher();
+    let alice = Identity::random(&hasher, &mut rng);
+    let bob = Identity::random(&hasher, &mut rng);
+
+    // deposit
+    let deposit = Note::new(100, alice.id_commitment, &mut rng);
+    let mut transaction = TransactionBuilder::<Bn256>::new();
+    transaction
+        .output(deposit.clone())
+        .unwrap()
+        .value_delta(100);
+    let proof = pool.prove(&transaction).unwrap();
+    assert!(pool.apply_transaction(&proof).unwrap());
+    let deposit_index = pool.note_index(&deposit).unwrap();
+
+    // transfer with change
+    let payment = Note::new(60, bob.id_commitment, &mut rng);
+    let change = Note::new(40, alice.id_commitment, &mut rng);
+    let mut transaction = TransactionBuilder::<Bn256>::new();
+    transaction
+        .spend(deposit.clone(), alice.id_key, deposit_index)
+        .unwrap()
+        .output(payment.clone())
+        .unwrap()
+        .output(change)
+        .unwrap();
+    let proof = pool.prove(&transaction).unwrap();
+    let mut proof_data: Vec<u8> = Vec::new();
+    proof.write(&mut proof_data).unwrap();
+    assert!(pool.verify(proof_data.as_slice()).unwrap());
+    assert!(pool.apply_transaction(&proof).unwrap());
+    assert!(pool.is_spent(&deposit.nullifier(&hasher, alice.id_key)));
+
+    // double spend
+    assert!(!pool.apply_transaction(&proof).unwrap());
+    let proof = pool.prove(&transaction).unwrap();
+    assert!(!pool.verify_transaction(&proof).unwrap());
+
+    // withdrawal
+    let payment_index = pool.note_index(&payment).unwrap();
+    let mut transaction = TransactionBuilder::<Bn256>::new();
+    transaction
+        .spend(payment.clone(), bob.id_key, payment_index)
+        .unwrap()
+        .value_delta(-60);
+    let proof = pool.prove(&transaction).unwrap();
+    assert!(pool.apply_transaction(&proof).unwrap());
+
+    // notes already in the note tree and equal outputs are not created
+    let mut transaction = TransactionBuilder::<Bn256>::new();
+    transaction.output(payment.clone()).unwrap().value_delta(60);
+    let proof = pool.prove(&transaction).unwrap();
+    assert!(!pool.apply_transaction(&proof).unwrap());
+    let twin = Note::new(5, bob.id_commitment, &mut rng);
+    let mut transaction = TransactionBuilder::<Bn256>::new();
+    transaction
+        .output(twin.clone())
+        .unwrap()
+        .output(twin)
+        .unwrap()
+        .value_delta(10);
+    let proof = pool.prove(&transaction).unwrap();
+    assert!(!pool.verify_transaction(&proof).unwrap());
+
+    // fills the note tree, a transaction that does not fit leaves the pool unchanged
+    let mut transaction = TransactionBuilder::<Bn256>::new();
+    transaction
+        .output(Note::new(1, bob.id_commitment, &mut rng))
+        .unwrap()
+        .value_delta(1);
+    let proof = pool.prove(&transaction).unwrap();
+    assert!(pool.apply_transaction(&proof).unwrap());
+    let mut root: Vec<u8> = Vec::new();
+    pool.get_root(&mut root).unwrap();
+    let mut transaction = TransactionBuilder::<Bn256>::new();
+    transaction
+        .output(Note::new(1, bob.id_commitment, &mut rng))
+        .unwrap()
+        .value_delta(1);
+    let proof = pool.prove(&transaction).unwrap();
+    assert!(pool.apply_transaction(&proof).is_err());
+    let mut root_after: Vec<u8> = Vec::new();
+    pool.get_root(&mut root_after).unwrap();
+    assert_eq!(root, root_after);
+    assert!(!pool.is_spent(&proof.values.nullifiers[0]));
+
+    // outputs exceed inputs
+    let mut transaction = TransactionBuilder::<Bn256>::new();
+    transaction
+        .output(Note::new(10, bob.id_commitment, &mut rng))
+        .unwrap();
+    match pool.prove(&transaction) {
+        Err(RLNError::InvalidTransaction(_)) => (),
+        _ => panic!("expected unbalanced transaction"),
+    }
+
+    // note is not in the note tree
+    let change_index = payment_index + 1;
+    let mut transaction = TransactionBuilder::<Bn256>::new();
+    transaction
+        .spend(
+            Note::new(40, bob.id_commitment, &mut rng),
+            bob.id_key,
+            change_index,
+        )
+        .unwrap()
+        .value_delta(-40);
+    match pool.prove(&transaction) {
+        Err(RLNError::InvalidTransaction(_)) => (),
+        _ => panic!("expected unknown note"),
+    }
+}
+
+#[test]
+fn test_shielded_deposit_into_empty_pool() {
+    use bellman::pairing::bn256::Bn256;
+    use rand::{SeedableRng, XorShiftRng};
+
+    let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let mut pool = ShieldedPool::<Bn256>::new(3, None).unwrap();
+    let hasher = pool.hasher();
+    let alice = Identity::random(&hasher, &mut rng);
+
+    // both spends are padding notes of zero value on an empty note tree
+    let deposit = Note::new(100, alice.id_commitment, &mut rng);
+    let mut transaction = TransactionBuilder::<Bn256>::new();
+    transaction
+        .output(deposit.clone())
+        .unwrap()
+        .value_delta(100);
+    let proof = pool.prove(&transaction).unwrap();
+    assert!(pool.verify_transaction(&proof).unwrap());
+
+    // public values must hold two nullifiers and two commitments
+    let mut short = proof.clone();
+    short.values.nullifiers.pop();
+    assert!(!pool.verify_transaction(&short).unwrap());
+    let mut short = proof.clone();
+    short.values.commitments.truncate(0);
+    assert!(!pool.verify_transaction(&short).unwrap());
+
+    assert!(pool.apply_transaction(&proof).unwrap());
+    assert_eq!(pool.note_index(&deposit), Some(0));
+}
+
 // #[cfg(test)]
 // mod tests {
 //     use crate::{circuit::bench, public::RLNSignal};
@@ -0,0 +1,256 @@
+use crate::error::{RLNError, Result};
+use crate::merkle::IncrementalMerkleTree;
+use crate::poseidon::Poseidon as Hasher;
+use crate::storage::MerkleStorage;
+use crate::utils::read_fr;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use sapling_crypto::bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
+use sapling_crypto::bellman::pairing::Engine;
+use std::io::{self, Read, Write};
+
+// Snapshot of an incremental merkle tree
+//
+// [ magic<4> | version<2> | depth<8> | poseidon_digest<32> | next_index<8> | count<8> | (index<8> | leaf<32>) * count | root<32> ]
+// Only non zero leaves are written in ascending index order. The root is a checksum,
+// a snapshot is imported only if the rebuilt tree has the same root.
+
+pub const SNAPSHOT_MAGIC: [u8; 4] = *b"RLNS";
+pub const SNAPSHOT_VERSION: u16 = 1;
+
+/// writes the snapshot of the tree
+pub fn write_snapshot<E, S, W>(tree: &IncrementalMerkleTree<E, S>, mut writer: W) -> Result<()>
+where
+    E: Engine,
+    S: MerkleStorage<E>,
+    W: Write,
+{
+    writer.write_all(&SNAPSHOT_MAGIC)?;
+    writer.write_u16::<LittleEndian>(SNAPSHOT_VERSION)?;
+    writer.write_u64::<LittleEndian>(tree.depth() as u64)?;
+    writer.write_all(&tree.hasher().params_digest())?;
+    writer.write_u64::<LittleEndian>(tree.current_index as u64)?;
+
+    let mut count = 0u64;
+    for index in 0..tree.current_index {
+        if !tree.get_leaf(index)?.is_zero() {
+            count += 1;
+        }
+    }
+    writer.write_u64::<LittleEndian>(count)?;
+    for index in 0..tree.current_index {
+        let leaf = tree.get_leaf(index)?;
+        if !leaf.is_zero() {
+            writer.write_u64::<LittleEndian>(index as u64)?;
+            leaf.into_repr().write_le(&mut writer)?;
+        }
+    }
+    tree.get_root().into_repr().write_le(&mut writer)?;
+    Ok(())
+}
+
+/// reads the snapshot of a tree of given depth
+pub fn read_snapshot<E, R>(
+    hasher: Hasher<E>,
+    depth: usize,
+    reader: R,
+) -> Result<IncrementalMerkleTree<E>>
+where
+    E: Engine,
+    R: Read,
+{
+    let snapshot = Snapshot::read(&hasher, depth, reader)?;
+    snapshot.build(hasher, depth)
+}
+
+/// reads the snapshot of a tree of given depth into an empty storage
+/// * the tree is rebuilt in memory and checked against the root before the storage is written
+pub fn read_snapshot_with_storage<E, S, R>(
+    hasher: Hasher<E>,
+    depth: usize,
+    storage: S,
+    reader: R,
+) -> Result<IncrementalMerkleTree<E, S>>
+where
+    E: Engine,
+    S: MerkleStorage<E>,
+    R: Read,
+{
+    if storage.next_index() != 0 {
+        return Err(invalid_data("storage is not empty"));
+    }
+    read_snapshot(hasher, depth, reader)?.into_storage(storage)
+}
+
+// non zero leaves of a snapshot with the recorded root
+struct Snapshot<E>
+where
+    E: Engine,
+{
+    next_index: usize,
+    leaves: Vec<(usize, E::Fr)>,
+    root: E::Fr,
+}
+
+impl<E> Snapshot<E>
+where
+    E: Engine,
+{
+    fn read<R: Read>(hasher: &Hasher<E>, depth: usize, mut reader: R) -> Result<Snapshot<E>> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != SNAPSHOT_MAGIC {
+            return Err(invalid_data("not a tree snapshot"));
+        }
+        if reader.read_u16::<LittleEndian>()? != SNAPSHOT_VERSION {
+            return Err(invalid_data("unsupported snapshot version"));
+        }
+        if reader.read_u64::<LittleEndian>()? != depth as u64 {
+            return Err(RLNError::ParametersMismatch("merkle depth"));
+        }
+        let mut digest = [0u8; 32];
+        reader.read_exact(&mut digest)?;
+        if digest != hasher.params_digest() {
+            return Err(RLNError::ParametersMismatch("poseidon parameters"));
+        }
+        let next_index = reader.read_u64::<LittleEndian>()?;
+        // trees of depth 64 or more hold any index
+        let set_size = 1u64.checked_shl(depth as u32);
+        if set_size.map_or(false, |size| next_index > size) {
+            return Err(invalid_data("next index is out of the tree"));
+        }
+        let next_index = next_index as usize;
+
+        // count is untrusted, leaves are pushed as they are read
+        let count = reader.read_u64::<LittleEndian>()?;
+        let mut leaves: Vec<(usize, E::Fr)> = Vec::new();
+        for _ in 0..count {
+            let index = reader.read_u64::<LittleEndian>()? as usize;
+            let ascending = leaves.last().map_or(true, |(last, _)| index > *last);
+            if !ascending || index >= next_index {
+                return Err(invalid_data(
+                    "leaf indexes are not ascending below next index",
+                ));
+            }
+            let leaf = read_fr::<_, E>(&mut reader, 1)?[0];
+            leaves.push((index, leaf));
+        }
+        let root = read_fr::<_, E>(&mut reader, 1)?[0];
+        Ok(Snapshot {
+            next_index,
+            leaves,
+            root,
+        })
+    }
+
+    // rebuilds the tree in memory from the non zero leaves and checks the root
+    fn build(&self, hasher: Hasher<E>, depth: usize) -> Result<IncrementalMerkleTree<E>> {
+        let mut tree = IncrementalMerkleTree::empty(hasher, depth);
+        tree.import_leaves(&self.leaves, self.next_index)?;
+        if self.root != tree.get_root() {
+            return Err(invalid_data("root checksum mismatch"));
+        }
+        Ok(tree)
+    }
+}
+
+fn invalid_data(reason: &str) -> RLNError {
+    RLNError::Serialization(io::Error::new(io::ErrorKind::InvalidData, reason))
+}
+
+#[test]
+fn test_tree_snapshot() {
+    use crate::merkle::MerkleTree;
+    use crate::poseidon::PoseidonParams;
+    use crate::storage::FileStorage;
+    use sapling_crypto::bellman::pairing::bn256::{Bn256, Fr};
+
+    let params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+    let hasher = Hasher::new(params);
+    let mut tree = IncrementalMerkleTree::empty(hasher.clone(), 4);
+    for i in 1..7 {
+        tree.update_next(Fr::from_str(&i.to_string()).unwrap())
+            .unwrap();
+    }
+    tree.delete(1).unwrap();
+    tree.delete(5).unwrap();
+
+    let mut snapshot: Vec<u8> = Vec::new();
+    write_snapshot(&tree, &mut snapshot).unwrap();
+    // four non zero leaves
+    assert_eq!(snapshot.len(), 4 + 2 + 8 + 32 + 8 + 8 + 4 * 40 + 32);
+
+    let imported = read_snapshot(hasher.clone(), 4, &snapshot[..]).unwrap();
+    assert_eq!(imported.get_root(), tree.get_root());
+    assert_eq!(imported.current_index, 6);
+    assert_eq!(imported.recent_roots(), vec![tree.get_root()]);
+    for index in 0..6 {
+        assert_eq!(
+            imported.get_leaf(index).unwrap(),
+            tree.get_leaf(index).unwrap()
+        );
+    }
+
+    match read_snapshot(hasher.clone(), 5, &snapshot[..]) {
+        Err(RLNError::ParametersMismatch(_)) => (),
+        _ => panic!("expected depth mismatch"),
+    }
+    let other_params = PoseidonParams::<Bn256>::new(8, 57, 3, None, None, None);
+    match read_snapshot(Hasher::new(other_params), 4, &snapshot[..]) {
+        Err(RLNError::ParametersMismatch(_)) => (),
+        _ => panic!("expected poseidon parameters mismatch"),
+    }
+
+    // corrupted leaf does not match the root checksum
+    let mut corrupted = snapshot.clone();
+    corrupted[4 + 2 + 8 + 32 + 8 + 8 + 8] ^= 2;
+    assert!(read_snapshot(hasher.clone(), 4, &corrupted[..]).is_err());
+    let truncated = &snapshot[..snapshot.len() - 1];
+    assert!(read_snapshot(hasher.clone(), 4, truncated).is_err());
+
+    // nothing is written to the storage of a rejected snapshot
+    let path = std::env::temp_dir().join(format!("rln_snapshot_{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let storage = FileStorage::<Bn256>::open(&path, 4).unwrap();
+    let empty_len = std::fs::metadata(&path).unwrap().len();
+    assert!(read_snapshot_with_storage(hasher.clone(), 4, storage, &corrupted[..]).is_err());
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), empty_len);
+    let storage = FileStorage::<Bn256>::open(&path, 4).unwrap();
+    let imported = read_snapshot_with_storage(hasher.clone(), 4, storage, &snapshot[..]).unwrap();
+    assert_eq!(imported.get_root(), tree.get_root());
+    std::fs::remove_file(&path).unwrap();
+
+    // sparse snapshot of a deep tree is imported without its zero leaves
+    let next_index = (1usize << 32) - 1;
+    let leaf = Fr::from_str("9").unwrap();
+    let mut expected = MerkleTree::empty(hasher.clone(), 32);
+    expected.update(next_index - 1, leaf).unwrap();
+    let mut sparse: Vec<u8> = Vec::new();
+    sparse.write_all(&SNAPSHOT_MAGIC).unwrap();
+    sparse.write_u16::<LittleEndian>(SNAPSHOT_VERSION).unwrap();
+    sparse.write_u64::<LittleEndian>(32).unwrap();
+    sparse.write_all(&hasher.params_digest()).unwrap();
+    sparse.write_u64::<LittleEndian>(next_index as u64).unwrap();
+    sparse.write_u64::<LittleEndian>(1).unwrap();
+    sparse
+        .write_u64::<LittleEndian>(next_index as u64 - 1)
+        .unwrap();
+    leaf.into_repr().write_le(&mut sparse).unwrap();
+    expected
+        .get_root()
+        .into_repr()
+        .write_le(&mut sparse)
+        .unwrap();
+    let imported = read_snapshot(hasher.clone(), 32, &sparse[..]).unwrap();
+    assert_eq!(imported.current_index, next_index);
+    assert_eq!(imported.get_root(), expected.get_root());
+    assert_eq!(imported.get_leaf(next_index - 1).unwrap(), leaf);
+    assert!(imported.get_leaf(0).unwrap().is_zero());
+
+    // empty tree
+    let empty = IncrementalMerkleTree::empty(hasher.clone(), 4);
+    let mut snapshot: Vec<u8> = Vec::new();
+    write_snapshot(&empty, &mut snapshot).unwrap();
+    let imported = read_snapshot(hasher.clone(), 4, &snapshot[..]).unwrap();
+    assert_eq!(imported.get_root(), empty.get_root());
+    assert_eq!(imported.current_index, 0);
+}
@@ -0,0 +1,283 @@
+use crate::error::{RLNError, Result};
+use bellman::groth16::{Proof, VerifyingKey};
+use bellman::pairing::bn256::{Bn256, Fq, Fq2, Fr, G1Affine, G2Affine};
+use bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
+use bellman::pairing::CurveAffine;
+use num_bigint::BigUint;
+use serde_json::{json, Value};
+use std::io::{self, Read, Write};
+
+// snarkjs compatible json encoding of groth16 proofs, verifying keys and public inputs
+//
+// Coordinates are decimal strings in projective form,
+// G1 as [ x, y, z ] and G2 as [ [ x.c0, x.c1 ], [ y.c0, y.c1 ], [ z.c0, z.c1 ] ].
+// Public inputs are ordered as in `RLNInputs::public_inputs`
+// [ root, epoch, share_x, share_y, nullifier ].
+
+/// writes proof as snarkjs `proof.json`
+pub fn write_proof_json<W: Write>(proof: &Proof<Bn256>, writer: W) -> Result<()> {
+    let value = json!({
+        "pi_a": g1_to_json(&proof.a),
+        "pi_b": g2_to_json(&proof.b),
+        "pi_c": g1_to_json(&proof.c),
+        "protocol": "groth16",
+        "curve": "bn128",
+    });
+    write_json(&value, writer)
+}
+
+/// reads proof from snarkjs `proof.json`
+pub fn read_proof_json<R: Read>(reader: R) -> Result<Proof<Bn256>> {
+    let value = read_json(reader)?;
+    Ok(Proof {
+        a: g1_from_json(field(&value, "pi_a")?)?,
+        b: g2_from_json(field(&value, "pi_b")?)?,
+        c: g1_from_json(field(&value, "pi_c")?)?,
+    })
+}
+
+/// writes verifying key as snarkjs `verification_key.json`
+pub fn write_verifying_key_json<W: Write>(vk: &VerifyingKey<Bn256>, writer: W) -> Result<()> {
+    let ic: Vec<Value> = vk.ic.iter().map(g1_to_json).collect();
+    let value = json!({
+        "protocol": "groth16",
+        "curve": "bn128",
+        "nPublic": vk.ic.len() - 1,
+        "vk_alpha_1": g1_to_json(&vk.alpha_g1),
+        "vk_beta_2": g2_to_json(&vk.beta_g2),
+        "vk_gamma_2": g2_to_json(&vk.gamma_g2),
+        "vk_delta_2": g2_to_json(&vk.delta_g2),
+        "IC": ic,
+    });
+    write_json(&value, writer)
+}
+
+/// reads verifying key from snarkjs `verification_key.json`
+/// * beta_g1 and delta_g1 are not part of the snarkjs key and are set to the point at infinity,
+/// they are not used by proof verification
+pub fn read_verifying_key_json<R: Read>(reader: R) -> Result<VerifyingKey<Bn256>> {
+    let value = read_json(reader)?;
+    let ic = array(field(&value, "IC")?)?
+        .iter()
+        .map(g1_from_json)
+        .collect::<Result<Vec<_>>>()?;
+    // IC holds a point per public input after the constant one
+    if ic.is_empty() {
+        return Err(invalid_json("IC is empty"));
+    }
+    if let Some(n_public) = value.get("nPublic") {
+        if n_public.as_u64() != Some(ic.len() as u64 - 1) {
+            return Err(invalid_json("nPublic does not match IC"));
+        }
+    }
+    Ok(VerifyingKey {
+        alpha_g1: g1_from_json(field(&value, "vk_alpha_1")?)?,
+        beta_g1: G1Affine::zero(),
+        beta_g2: g2_from_json(field(&value, "vk_beta_2")?)?,
+        gamma_g2: g2_from_json(field(&value, "vk_gamma_2")?)?,
+        delta_g1: G1Affine::zero(),
+        delta_g2: g2_from_json(field(&value, "vk_delta_2")?)?,
+        ic,
+    })
+}
+
+/// writes public inputs as snarkjs `public.json`
+pub fn write_public_inputs_json<W: Write>(public_inputs: &[Fr], writer: W) -> Result<()> {
+    let value = Value::Array(public_inputs.iter().map(fe_to_json).collect());
+    write_json(&value, writer)
+}
+
+/// reads public inputs from snarkjs `public.json`
+pub fn read_public_inputs_json<R: Read>(reader: R) -> Result<Vec<Fr>> {
+    let value = read_json(reader)?;
+    array(&value)?.iter().map(fe_from_json).collect()
+}
+
+fn write_json<W: Write>(value: &Value, mut writer: W) -> Result<()> {
+    serde_json::to_writer_pretty(&mut writer, value).map_err(io::Error::from)?;
+    Ok(())
+}
+
+fn read_json<R: Read>(reader: R) -> Result<Value> {
+    let value = serde_json::from_reader(reader).map_err(io::Error::from)?;
+    Ok(value)
+}
+
+fn invalid_json(reason: &str) -> RLNError {
+    RLNError::Serialization(io::Error::new(io::ErrorKind::InvalidData, reason))
+}
+
+fn field<'a>(value: &'a Value, key: &str) -> Result<&'a Value> {
+    value
+        .get(key)
+        .ok_or_else(|| invalid_json(&format!("missing {}", key)))
+}
+
+fn array(value: &Value) -> Result<&Vec<Value>> {
+    value
+        .as_array()
+        .ok_or_else(|| invalid_json("expected an array"))
+}
+
+pub(crate) fn fe_to_decimal<F: PrimeField>(fe: &F) -> String {
+    let mut buf: Vec<u8> = Vec::new();
+    fe.into_repr().write_be(&mut buf).unwrap();
+    BigUint::from_bytes_be(&buf[..]).to_str_radix(10)
+}
+
+fn fe_to_json<F: PrimeField>(fe: &F) -> Value {
+    Value::String(fe_to_decimal(fe))
+}
+
+pub(crate) fn fe_from_json<F: PrimeField>(value: &Value) -> Result<F> {
+    let decimal = value
+        .as_str()
+        .ok_or_else(|| invalid_json("expected a decimal string"))?;
+    let big = BigUint::parse_bytes(decimal.as_bytes(), 10)
+        .ok_or_else(|| invalid_json("expected a decimal string"))?;
+    let mut repr = F::Repr::default();
+    let repr_len = repr.as_ref().len() * 8;
+    let bytes = big.to_bytes_be();
+    if bytes.len() > repr_len {
+        return Err(RLNError::InvalidFieldElement);
+    }
+    let mut buf = vec![0u8; repr_len - bytes.len()];
+    buf.extend_from_slice(&bytes[..]);
+    repr.read_be(&buf[..])?;
+    F::from_repr(repr).map_err(|_| RLNError::InvalidFieldElement)
+}
+
+fn fq2_to_json(fe: &Fq2) -> Value {
+    json!([fe_to_json(&fe.c0), fe_to_json(&fe.c1)])
+}
+
+fn fq2_from_json(value: &Value) -> Result<Fq2> {
+    let coeffs = array(value)?;
+    if coeffs.len() != 2 {
+        return Err(invalid_json("expected two coefficients"));
+    }
+    Ok(Fq2 {
+        c0: fe_from_json(&coeffs[0])?,
+        c1: fe_from_json(&coeffs[1])?,
+    })
+}
+
+fn g1_to_json(p: &G1Affine) -> Value {
+    if p.is_zero() {
+        return json!([
+            fe_to_json(&Fq::zero()),
+            fe_to_json(&Fq::one()),
+            fe_to_json(&Fq::zero())
+        ]);
+    }
+    let (x, y) = p.into_xy_unchecked();
+    json!([fe_to_json(&x), fe_to_json(&y), fe_to_json(&Fq::one())])
+}
+
+fn g1_from_json(value: &Value) -> Result<G1Affine> {
+    let coords = array(value)?;
+    if coords.len() != 3 {
+        return Err(invalid_json("expected three coordinates"));
+    }
+    let z: Fq = fe_from_json(&coords[2])?;
+    if z.is_zero() {
+        return Ok(G1Affine::zero());
+    }
+    if z != Fq::one() {
+        return Err(invalid_json("expected affine coordinates"));
+    }
+    let x = fe_from_json(&coords[0])?;
+    let y = fe_from_json(&coords[1])?;
+    let p = G1Affine::from_xy_checked(x, y)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(p)
+}
+
+fn g2_to_json(p: &G2Affine) -> Value {
+    if p.is_zero() {
+        return json!([
+            fq2_to_json(&Fq2::zero()),
+            fq2_to_json(&Fq2::one()),
+            fq2_to_json(&Fq2::zero())
+        ]);
+    }
+    let (x, y) = p.into_xy_unchecked();
+    json!([fq2_to_json(&x), fq2_to_json(&y), fq2_to_json(&Fq2::one())])
+}
+
+fn g2_from_json(value: &Value) -> Result<G2Affine> {
+    let coords = array(value)?;
+    if coords.len() != 3 {
+        return Err(invalid_json("expected three coordinates"));
+    }
+    let z = fq2_from_json(&coords[2])?;
+    if z.is_zero() {
+        return Ok(G2Affine::zero());
+    }
+    if z != Fq2::one() {
+        return Err(invalid_json("expected affine coordinates"));
+    }
+    let x = fq2_from_json(&coords[0])?;
+    let y = fq2_from_json(&coords[1])?;
+    let p = G2Affine::from_xy_checked(x, y)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(p)
+}
+
+#[test]
+fn test_snarkjs_json() {
+    use crate::circuit::bench;
+    use crate::poseidon::PoseidonParams;
+    use crate::public::RLNWitnessInput;
+
+    let poseidon_params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+    let rln_test = bench::RLNTest::<Bn256>::new(3, Some(poseidon_params));
+    let rln = &rln_test.rln;
+
+    // the test member is inserted at index 0 with secret 1001
+    let witness = RLNWitnessInput::<Bn256> {
+        id_key: Fr::from_str("1001").unwrap(),
+        id_index: 0,
+        epoch: Fr::from_str("7").unwrap(),
+        signal: b"rln signal test xyz abc".to_vec(),
+    };
+    let rln_proof = rln.prove(&witness).unwrap();
+
+    let mut proof_json: Vec<u8> = Vec::new();
+    write_proof_json(&rln_proof.proof, &mut proof_json).unwrap();
+    assert!(read_proof_json(proof_json.as_slice()).unwrap() == rln_proof.proof);
+
+    let public_inputs = rln_proof.values.public_inputs();
+    let mut public_json: Vec<u8> = Vec::new();
+    write_public_inputs_json(&public_inputs, &mut public_json).unwrap();
+    assert_eq!(
+        read_public_inputs_json(public_json.as_slice()).unwrap(),
+        public_inputs
+    );
+
+    let mut raw_vk: Vec<u8> = Vec::new();
+    rln.export_verifier_key(&mut raw_vk).unwrap();
+    let vk = VerifyingKey::<Bn256>::read(raw_vk.as_slice()).unwrap();
+    let mut vk_json: Vec<u8> = Vec::new();
+    write_verifying_key_json(&vk, &mut vk_json).unwrap();
+    let read_vk = read_verifying_key_json(vk_json.as_slice()).unwrap();
+    assert!(read_vk.alpha_g1 == vk.alpha_g1);
+    assert!(read_vk.delta_g2 == vk.delta_g2);
+    assert!(read_vk.ic == vk.ic);
+
+    // imported key verifies the imported proof
+    let pvk = bellman::groth16::prepare_verifying_key(&read_vk);
+    let proof = read_proof_json(proof_json.as_slice()).unwrap();
+    let public_inputs = read_public_inputs_json(public_json.as_slice()).unwrap();
+    assert!(bellman::groth16::verify_proof(&pvk, &proof, &public_inputs).unwrap());
+
+    let mut empty_ic: Value = serde_json::from_slice(&vk_json).unwrap();
+    empty_ic["IC"] = Value::Array(vec![]);
+    let empty_ic = serde_json::to_vec(&empty_ic).unwrap();
+    assert!(read_verifying_key_json(empty_ic.as_slice()).is_err());
+
+    assert_eq!(
+        fe_to_json(&Fr::from_str("12345").unwrap()),
+        Value::String("12345".to_string())
+    );
+}
@@ -0,0 +1,157 @@
+use crate::error::{RLNError, Result};
+use crate::snarkjs::fe_to_decimal;
+use bellman::groth16::VerifyingKey;
+use bellman::pairing::bn256::{Bn256, G1Affine, G2Affine};
+use bellman::pairing::CurveAffine;
+use std::io::{Read, Write};
+
+// Solidity verifier and membership registry generated from the RLN verifying key
+
+const VERIFIER_TEMPLATE: &str = include_str!("templates/verifier.sol");
+const REGISTRY_TEMPLATE: &str = include_str!("templates/registry.sol");
+
+// public inputs of RLNCircuit, [ root, epoch, share_x, share_y, nullifier ]
+const RLN_PUBLIC_INPUTS: usize = 5;
+
+/// writes the verifier contract of the verifying key
+/// * expect `raw_verifier_key` as written by `RLN::export_verifier_key`
+pub fn generate_verifier<R: Read, W: Write>(raw_verifier_key: R, writer: W) -> Result<()> {
+    let vk = VerifyingKey::<Bn256>::read(raw_verifier_key)?;
+    write_verifier(&vk, writer)
+}
+
+/// writes the registry contract that calls the verifier with RLN public inputs
+/// * expect `raw_verifier_key` as written by `RLN::export_verifier_key`
+pub fn generate_registry<R: Read, W: Write>(raw_verifier_key: R, writer: W) -> Result<()> {
+    let vk = VerifyingKey::<Bn256>::read(raw_verifier_key)?;
+    write_registry(&vk, writer)
+}
+
+pub fn write_verifier<W: Write>(vk: &VerifyingKey<Bn256>, mut writer: W) -> Result<()> {
+    check_public_inputs(vk)?;
+    let (alpha_x, alpha_y) = g1_coordinates(&vk.alpha_g1);
+    let beta = g2_coordinates(&vk.beta_g2);
+    let gamma = g2_coordinates(&vk.gamma_g2);
+    let delta = g2_coordinates(&vk.delta_g2);
+    let ic: Vec<String> = vk
+        .ic
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let (x, y) = g1_coordinates(p);
+            format!("        ic[{}] = [uint256({}), uint256({})];", i, x, y)
+        })
+        .collect();
+
+    let contract = VERIFIER_TEMPLATE
+        .replace("{{alpha_x}}", &alpha_x)
+        .replace("{{alpha_y}}", &alpha_y)
+        .replace("{{beta_x1}}", &beta[0])
+        .replace("{{beta_x0}}", &beta[1])
+        .replace("{{beta_y1}}", &beta[2])
+        .replace("{{beta_y0}}", &beta[3])
+        .replace("{{gamma_x1}}", &gamma[0])
+        .replace("{{gamma_x0}}", &gamma[1])
+        .replace("{{gamma_y1}}", &gamma[2])
+        .replace("{{gamma_y0}}", &gamma[3])
+        .replace("{{delta_x1}}", &delta[0])
+        .replace("{{delta_x0}}", &delta[1])
+        .replace("{{delta_y1}}", &delta[2])
+        .replace("{{delta_y0}}", &delta[3])
+        .replace("{{ic_length}}", &vk.ic.len().to_string())
+        .replace("{{n_public}}", &RLN_PUBLIC_INPUTS.to_string())
+        .replace("{{ic}}", &ic.join("\n"));
+    writer.write_all(contract.as_bytes())?;
+    Ok(())
+}
+
+pub fn write_registry<W: Write>(vk: &VerifyingKey<Bn256>, mut writer: W) -> Result<()> {
+    check_public_inputs(vk)?;
+    let contract = REGISTRY_TEMPLATE.replace("{{n_public}}", &RLN_PUBLIC_INPUTS.to_string());
+    writer.write_all(contract.as_bytes())?;
+    Ok(())
+}
+
+// contracts are generated for RLNCircuit layout only
+fn check_public_inputs(vk: &VerifyingKey<Bn256>) -> Result<()> {
+    if vk.ic.len() != RLN_PUBLIC_INPUTS + 1 {
+        return Err(RLNError::VerifyingKeyMismatch);
+    }
+    Ok(())
+}
+
+fn g1_coordinates(p: &G1Affine) -> (String, String) {
+    let (x, y) = p.into_xy_unchecked();
+    (fe_to_decimal(&x), fe_to_decimal(&y))
+}
+
+// [ x_im, x_re, y_im, y_re ] as in EIP-197
+fn g2_coordinates(p: &G2Affine) -> [String; 4] {
+    let (x, y) = p.into_xy_unchecked();
+    [
+        fe_to_decimal(&x.c1),
+        fe_to_decimal(&x.c0),
+        fe_to_decimal(&y.c1),
+        fe_to_decimal(&y.c0),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::{generate_verifier, write_registry, write_verifier, RLN_PUBLIC_INPUTS};
+    use crate::public::RLN;
+    use bellman::groth16::VerifyingKey;
+    use bellman::pairing::bn256::{Bn256, G1Affine, G2Affine};
+    use bellman::pairing::CurveAffine;
+
+    // verifying key of generator points pins the template rendering
+    fn generator_vk() -> VerifyingKey<Bn256> {
+        VerifyingKey {
+            alpha_g1: G1Affine::one(),
+            beta_g1: G1Affine::one(),
+            beta_g2: G2Affine::one(),
+            gamma_g2: G2Affine::one(),
+            delta_g1: G1Affine::one(),
+            delta_g2: G2Affine::one(),
+            ic: vec![G1Affine::one(); 6],
+        }
+    }
+
+    #[test]
+    fn test_verifier_golden() {
+        let mut contract: Vec<u8> = Vec::new();
+        write_verifier(&generator_vk(), &mut contract).unwrap();
+        assert_eq!(
+            String::from_utf8(contract).unwrap(),
+            include_str!("testdata/RLNVerifier.sol")
+        );
+    }
+
+    #[test]
+    fn test_registry_golden() {
+        let mut contract: Vec<u8> = Vec::new();
+        write_registry(&generator_vk(), &mut contract).unwrap();
+        assert_eq!(
+            String::from_utf8(contract).unwrap(),
+            include_str!("testdata/RLNRegistry.sol")
+        );
+    }
+
+    #[test]
+    fn test_public_inputs_of_rln_circuit() {
+        let rln = RLN::<Bn256>::new(3, None).unwrap();
+        let mut raw_verifier_key: Vec<u8> = Vec::new();
+        rln.export_verifier_key(&mut raw_verifier_key).unwrap();
+        let vk = VerifyingKey::<Bn256>::read(&raw_verifier_key[..]).unwrap();
+        assert_eq!(vk.ic.len(), RLN_PUBLIC_INPUTS + 1);
+        assert!(generate_verifier(&raw_verifier_key[..], Vec::new()).is_ok());
+    }
+
+    #[test]
+    fn test_public_inputs_mismatch() {
+        let mut vk = generator_vk();
+        vk.ic.pop();
+        assert!(write_verifier(&vk, Vec::new()).is_err());
+        assert!(write_registry(&vk, Vec::new()).is_err());
+    }
+}
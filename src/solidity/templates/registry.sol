@@ -0,0 +1,60 @@
+// SPDX-License-Identifier: MIT
+// Generated from the RLN verifying key, do not edit.
+pragma solidity ^0.8.0;
+
+import "./RLNVerifier.sol";
+
+// Membership registry skeleton of rate limit nullifier.
+//
+// Public inputs of a signal are ordered as in RLNCircuit
+// [ root, epoch, share_x, share_y, nullifier ]
+// Poseidon membership roots are computed off chain and
+// published by the owner, slashing of double signals is left to the integrator.
+contract RLNRegistry {
+    uint256 constant SNARK_SCALAR_FIELD =
+        21888242871839275222246405745257275088548364400416034343698204186575808495617;
+
+    RLNVerifier public immutable verifier;
+    address public owner;
+
+    // id commitments in insertion order, index is the leaf index
+    uint256[] public members;
+    mapping(uint256 => bool) public knownRoots;
+
+    event MemberRegistered(uint256 idCommitment, uint256 index);
+    event RootUpdated(uint256 root);
+    event Signal(uint256 root, uint256 epoch, uint256 shareX, uint256 shareY, uint256 nullifier);
+
+    constructor(RLNVerifier _verifier) {
+        verifier = _verifier;
+        owner = msg.sender;
+    }
+
+    function register(uint256 idCommitment) external {
+        require(idCommitment != 0 && idCommitment < SNARK_SCALAR_FIELD, "invalid id commitment");
+        members.push(idCommitment);
+        emit MemberRegistered(idCommitment, members.length - 1);
+    }
+
+    function updateRoot(uint256 root) external {
+        require(msg.sender == owner, "only owner");
+        knownRoots[root] = true;
+        emit RootUpdated(root);
+    }
+
+    function signal(
+        uint256[2] calldata a,
+        uint256[2][2] calldata b,
+        uint256[2] calldata c,
+        uint256 root,
+        uint256 epoch,
+        uint256 shareX,
+        uint256 shareY,
+        uint256 nullifier
+    ) external {
+        require(knownRoots[root], "unknown root");
+        uint256[{{n_public}}] memory input = [root, epoch, shareX, shareY, nullifier];
+        require(verifier.verifyProof(a, b, c, input), "invalid proof");
+        emit Signal(root, epoch, shareX, shareY, nullifier);
+    }
+}
@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: MIT
+// Generated from the RLN verifying key, do not edit.
+pragma solidity ^0.8.0;
+
+// Groth16 verifier of RLNCircuit over BN254 using the
+// ecAdd (0x06), ecMul (0x07) and pairing (0x08) precompiles.
+//
+// G2 points are given as [[x_im, x_re], [y_im, y_re]] following EIP-197.
+contract RLNVerifier {
+    // scalar field of BN254
+    uint256 constant SNARK_SCALAR_FIELD =
+        21888242871839275222246405745257275088548364400416034343698204186575808495617;
+    // base field of BN254
+    uint256 constant PRIME_Q =
+        21888242871839275222246405745257275088696311157297823662689037894645226208583;
+
+    uint256 constant ALPHA_X = {{alpha_x}};
+    uint256 constant ALPHA_Y = {{alpha_y}};
+
+    uint256 constant BETA_X1 = {{beta_x1}};
+    uint256 constant BETA_X0 = {{beta_x0}};
+    uint256 constant BETA_Y1 = {{beta_y1}};
+    uint256 constant BETA_Y0 = {{beta_y0}};
+
+    uint256 constant GAMMA_X1 = {{gamma_x1}};
+    uint256 constant GAMMA_X0 = {{gamma_x0}};
+    uint256 constant GAMMA_Y1 = {{gamma_y1}};
+    uint256 constant GAMMA_Y0 = {{gamma_y0}};
+
+    uint256 constant DELTA_X1 = {{delta_x1}};
+    uint256 constant DELTA_X0 = {{delta_x0}};
+    uint256 constant DELTA_Y1 = {{delta_y1}};
+    uint256 constant DELTA_Y0 = {{delta_y0}};
+
+    function verifyingKeyIC() internal pure returns (uint256[2][{{ic_length}}] memory ic) {
+{{ic}}
+    }
+
+    function verifyProof(
+        uint256[2] calldata a,
+        uint256[2][2] calldata b,
+        uint256[2] calldata c,
+        uint256[{{n_public}}] calldata input
+    ) public view returns (bool) {
+        uint256[2][{{ic_length}}] memory ic = verifyingKeyIC();
+
+        // vk_x = ic[0] + sum input[i] * ic[i + 1]
+        uint256[2] memory vkX = ic[0];
+        for (uint256 i = 0; i < {{n_public}}; i++) {
+            require(input[i] < SNARK_SCALAR_FIELD, "input is not in scalar field");
+            vkX = ecAdd(vkX, ecMul(ic[i + 1], input[i]));
+        }
+
+        // e(-A, B) * e(alpha, beta) * e(vk_x, gamma) * e(C, delta) == 1
+        uint256[24] memory pairs = [
+            a[0], negate(a[1]), b[0][0], b[0][1], b[1][0], b[1][1],
+            ALPHA_X, ALPHA_Y, BETA_X1, BETA_X0, BETA_Y1, BETA_Y0,
+            vkX[0], vkX[1], GAMMA_X1, GAMMA_X0, GAMMA_Y1, GAMMA_Y0,
+            c[0], c[1], DELTA_X1, DELTA_X0, DELTA_Y1, DELTA_Y0
+        ];
+
+        uint256[1] memory out;
+        bool success;
+        assembly {
+            success := staticcall(gas(), 0x08, pairs, 0x300, out, 0x20)
+        }
+        require(success, "pairing failed");
+        return out[0] == 1;
+    }
+
+    function negate(uint256 y) internal pure returns (uint256) {
+        if (y == 0) {
+            return 0;
+        }
+        return PRIME_Q - (y % PRIME_Q);
+    }
+
+    function ecAdd(uint256[2] memory p, uint256[2] memory q) internal view returns (uint256[2] memory r) {
+        uint256[4] memory input = [p[0], p[1], q[0], q[1]];
+        bool success;
+        assembly {
+            success := staticcall(gas(), 0x06, input, 0x80, r, 0x40)
+        }
+        require(success, "ecAdd failed");
+    }
+
+    function ecMul(uint256[2] memory p, uint256 s) internal view returns (uint256[2] memory r) {
+        uint256[3] memory input = [p[0], p[1], s];
+        bool success;
+        assembly {
+            success := staticcall(gas(), 0x07, input, 0x60, r, 0x40)
+        }
+        require(success, "ecMul failed");
+    }
+}
@@ -0,0 +1,178 @@
+use crate::error::{RLNError, Result};
+use crate::poseidon::Poseidon as PoseidonHasher;
+use sapling_crypto::bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
+use sapling_crypto::bellman::pairing::Engine;
+
+// Shamir secret sharing over the secret line of a member
+
+#[derive(Clone)]
+pub struct Share<E>
+where
+    E: Engine,
+{
+    pub x: E::Fr,
+    pub y: E::Fr,
+}
+
+impl<E> Share<E>
+where
+    E: Engine,
+{
+    pub fn new(x: E::Fr, y: E::Fr) -> Share<E> {
+        Share { x, y }
+    }
+}
+
+// interpolates the line a_0 + a_1 * x passing through two shares
+// returns (a_0, a_1)
+pub fn interpolate_line<E: Engine>(s0: &Share<E>, s1: &Share<E>) -> Result<(E::Fr, E::Fr)> {
+    // dx = x_1 - x_0
+    let mut dx = s1.x.clone();
+    dx.sub_assign(&s0.x);
+    let dx_inv = match dx.inverse() {
+        Some(dx_inv) => dx_inv,
+        None => {
+            return Err(RLNError::InvalidShares(
+                "shares must be evaluated at distinct points",
+            ))
+        }
+    };
+
+    // a_1 = (y_1 - y_0) / (x_1 - x_0)
+    let mut a_1 = s1.y.clone();
+    a_1.sub_assign(&s0.y);
+    a_1.mul_assign(&dx_inv);
+
+    // a_0 = y_0 - a_1 * x_0
+    let mut a_1x_0 = a_1.clone();
+    a_1x_0.mul_assign(&s0.x);
+    let mut a_0 = s0.y.clone();
+    a_0.sub_assign(&a_1x_0);
+
+    Ok((a_0, a_1))
+}
+
+// recovers the secret a_0 of the line from two shares
+pub fn recover_secret<E: Engine>(s0: &Share<E>, s1: &Share<E>) -> Result<E::Fr> {
+    let (a_0, _) = interpolate_line(s0, s1)?;
+    Ok(a_0)
+}
+
+// derives the coefficients [a_0, a_1, ..., a_k] of the secret polynomial of an epoch
+// a_1 = H(a_0, epoch) and a_i = H(a_{i-1}, epoch)
+pub fn polynomial_coefficients<E: Engine>(
+    hasher: &PoseidonHasher<E>,
+    a_0: E::Fr,
+    epoch: E::Fr,
+    degree: usize,
+) -> Vec<E::Fr> {
+    let mut coeffs = Vec::with_capacity(degree + 1);
+    coeffs.push(a_0);
+    for i in 0..degree {
+        let a_i = hasher.hash(vec![coeffs[i], epoch]);
+        coeffs.push(a_i);
+    }
+    coeffs
+}
+
+// evaluates the polynomial a_0 + a_1 * x + ... + a_k * x^k with horner method
+pub fn evaluate<E: Engine>(coeffs: &[E::Fr], x: E::Fr) -> E::Fr {
+    let mut acc = E::Fr::zero();
+    for a_i in coeffs.iter().rev() {
+        acc.mul_assign(&x);
+        acc.add_assign(a_i);
+    }
+    acc
+}
+
+// recovers the secret a_0 of a degree k polynomial from k + 1 shares
+// with lagrange interpolation at zero
+pub fn recover_secret_from_shares<E: Engine>(shares: &[Share<E>]) -> Result<E::Fr> {
+    if shares.is_empty() {
+        return Err(RLNError::InvalidShares("no shares to recover from"));
+    }
+    let mut secret = E::Fr::zero();
+    for (j, s_j) in shares.iter().enumerate() {
+        // l_j(0) = prod_{m != j} x_m / (x_m - x_j)
+        let mut num = E::Fr::one();
+        let mut den = E::Fr::one();
+        for (m, s_m) in shares.iter().enumerate() {
+            if m == j {
+                continue;
+            }
+            num.mul_assign(&s_m.x);
+            let mut diff = s_m.x.clone();
+            diff.sub_assign(&s_j.x);
+            den.mul_assign(&diff);
+        }
+        let den_inv = match den.inverse() {
+            Some(den_inv) => den_inv,
+            None => {
+                return Err(RLNError::InvalidShares(
+                    "shares must be evaluated at distinct points",
+                ))
+            }
+        };
+        let mut term = s_j.y.clone();
+        term.mul_assign(&num);
+        term.mul_assign(&den_inv);
+        secret.add_assign(&term);
+    }
+    Ok(secret)
+}
+
+#[test]
+fn test_recover_secret() {
+    use rand::{Rand, SeedableRng, XorShiftRng};
+    use sapling_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    let mut rng = XorShiftRng::from_seed([0x3dbe6258, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let a_0 = Fr::rand(&mut rng);
+    let a_1 = Fr::rand(&mut rng);
+    let eval = |x: Fr| {
+        let mut y = a_1.clone();
+        y.mul_assign(&x);
+        y.add_assign(&a_0);
+        Share::<Bn256>::new(x, y)
+    };
+    let s0 = eval(Fr::rand(&mut rng));
+    let s1 = eval(Fr::rand(&mut rng));
+
+    assert_eq!(interpolate_line(&s0, &s1).unwrap(), (a_0, a_1));
+    assert_eq!(recover_secret(&s1, &s0).unwrap(), a_0);
+    assert!(recover_secret(&s0, &s0).is_err());
+}
+
+#[test]
+fn test_recover_secret_from_shares() {
+    use crate::poseidon::PoseidonParams;
+    use rand::{Rand, SeedableRng, XorShiftRng};
+    use sapling_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    let mut rng = XorShiftRng::from_seed([0x3dbe6258, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let hasher = PoseidonHasher::new(PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None));
+
+    let a_0 = Fr::rand(&mut rng);
+    let epoch = Fr::rand(&mut rng);
+    let degree = 3;
+    let coeffs = polynomial_coefficients(&hasher, a_0, epoch, degree);
+    assert_eq!(coeffs.len(), degree + 1);
+    assert_eq!(coeffs[1], hasher.hash(vec![a_0, epoch]));
+
+    let shares: Vec<Share<Bn256>> = (0..degree + 1)
+        .map(|_| {
+            let x = Fr::rand(&mut rng);
+            Share::new(x, evaluate::<Bn256>(&coeffs, x))
+        })
+        .collect();
+    assert_eq!(recover_secret_from_shares(&shares).unwrap(), a_0);
+    assert_ne!(recover_secret_from_shares(&shares[..degree]).unwrap(), a_0);
+
+    // degree one agrees with the line interpolation
+    let line = polynomial_coefficients(&hasher, a_0, epoch, 1);
+    let s0 = Share::<Bn256>::new(shares[0].x, evaluate::<Bn256>(&line, shares[0].x));
+    let s1 = Share::<Bn256>::new(shares[1].x, evaluate::<Bn256>(&line, shares[1].x));
+    assert_eq!(
+        recover_secret_from_shares(&[s0.clone(), s1.clone()]).unwrap(),
+        recover_secret(&s0, &s1).unwrap()
+    );
+}
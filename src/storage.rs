@@ -0,0 +1,376 @@
+use crate::error::{RLNError, Result};
+use blake2::{Blake2s, Digest};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use sapling_crypto::bellman::pairing::ff::{PrimeField, PrimeFieldRepr};
+use sapling_crypto::bellman::pairing::Engine;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+// Node storage of merkle trees
+//
+// Nodes are addressed by depth from the root and index within the level.
+// Written nodes are visible at once and are made durable by `commit`
+// together with the next leaf index of an incremental tree. A failed commit
+// discards the nodes written since the last commit, so the tree stays at its committed root.
+
+pub trait MerkleStorage<E>
+where
+    E: Engine,
+{
+    /// node at given depth and index, none if it was never written
+    fn get(&self, depth: usize, index: usize) -> Option<E::Fr>;
+
+    /// writes a node to be persisted with the next commit
+    fn put(&mut self, depth: usize, index: usize, node: E::Fr);
+
+    /// persists written nodes together with the next leaf index,
+    /// written nodes are discarded if it fails
+    fn commit(&mut self, next_index: usize) -> Result<()>;
+
+    /// next leaf index of the last commit
+    fn next_index(&self) -> usize;
+}
+
+pub struct MemoryStorage<E>
+where
+    E: Engine,
+{
+    nodes: HashMap<(usize, usize), E::Fr>,
+    next_index: usize,
+}
+
+impl<E> MemoryStorage<E>
+where
+    E: Engine,
+{
+    pub fn new() -> MemoryStorage<E> {
+        MemoryStorage {
+            nodes: HashMap::new(),
+            next_index: 0,
+        }
+    }
+
+    // writes the nodes to the storage and commits them with the next leaf index
+    pub(crate) fn copy_into<S: MerkleStorage<E>>(&self, storage: &mut S) -> Result<()> {
+        for ((depth, index), node) in self.nodes.iter() {
+            storage.put(*depth, *index, *node);
+        }
+        storage.commit(self.next_index)
+    }
+}
+
+impl<E> MerkleStorage<E> for MemoryStorage<E>
+where
+    E: Engine,
+{
+    fn get(&self, depth: usize, index: usize) -> Option<E::Fr> {
+        self.nodes.get(&(depth, index)).cloned()
+    }
+
+    fn put(&mut self, depth: usize, index: usize, node: E::Fr) {
+        self.nodes.insert((depth, index), node);
+    }
+
+    fn commit(&mut self, next_index: usize) -> Result<()> {
+        self.next_index = next_index;
+        Ok(())
+    }
+
+    fn next_index(&self) -> usize {
+        self.next_index
+    }
+}
+
+// Append only file of committed node writes
+//
+// [ magic<4> | tree_depth<8> | record * ]
+// every commit appends a record serialized as
+// [ count<4> | !count<4> | (depth<8> | index<8> | node<32>) * count | next_index<8> | checksum<32> ]
+// where checksum is Blake2s of the record before it. Records are synced before
+// commit returns and a failed commit truncates the file back to the last record.
+// The last record cut short or not matching its checksum is the tail of an interrupted
+// commit and is truncated on open, so the tree reopens at the last committed root.
+// A count not matching its complement or a bad record followed by more data
+// is corruption and fails the open.
+
+pub const STORAGE_MAGIC: [u8; 4] = *b"RLNT";
+
+pub struct FileStorage<E>
+where
+    E: Engine,
+{
+    file: File,
+    // committed nodes
+    nodes: HashMap<(usize, usize), E::Fr>,
+    // nodes written since the last commit
+    pending: HashMap<(usize, usize), E::Fr>,
+    next_index: usize,
+    // length of the file up to the last committed record
+    committed: u64,
+}
+
+impl<E> FileStorage<E>
+where
+    E: Engine,
+{
+    /// opens the storage of a tree of given depth, the file is created if it does not exist
+    pub fn open<P: AsRef<Path>>(path: P, tree_depth: usize) -> Result<FileStorage<E>> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(path)?;
+        let mut data: Vec<u8> = Vec::new();
+        file.read_to_end(&mut data)?;
+
+        let mut storage = FileStorage {
+            file,
+            nodes: HashMap::new(),
+            pending: HashMap::new(),
+            next_index: 0,
+            committed: 0,
+        };
+
+        // a header cut short is the tail of an interrupted create
+        let header_len = STORAGE_MAGIC.len() + 8;
+        if data.len() < header_len && data.iter().zip(STORAGE_MAGIC.iter()).all(|(a, b)| a == b) {
+            storage.file.set_len(0)?;
+            storage.file.seek(SeekFrom::Start(0))?;
+            storage.file.write_all(&STORAGE_MAGIC)?;
+            storage.file.write_u64::<LittleEndian>(tree_depth as u64)?;
+            storage.file.sync_all()?;
+            storage.committed = header_len as u64;
+            return Ok(storage);
+        }
+
+        let mut header = &data[..];
+        let mut magic = [0u8; 4];
+        header.read_exact(&mut magic)?;
+        if magic != STORAGE_MAGIC {
+            return Err(invalid_data("not a merkle storage file"));
+        }
+        if header.read_u64::<LittleEndian>()? != tree_depth as u64 {
+            return Err(invalid_data("storage was written for another tree depth"));
+        }
+
+        let mut committed = data.len() - header.len();
+        while let Some((record_len, nodes, next_index)) = read_record::<E>(&data[committed..])? {
+            for (depth, index, node) in nodes.into_iter() {
+                storage.nodes.insert((depth, index), node);
+            }
+            storage.next_index = next_index;
+            committed += record_len;
+        }
+
+        // drop the tail of an interrupted commit
+        if committed < data.len() {
+            storage.file.set_len(committed as u64)?;
+            storage.file.sync_all()?;
+        }
+        storage.file.seek(SeekFrom::Start(committed as u64))?;
+        storage.committed = committed as u64;
+        Ok(storage)
+    }
+}
+
+impl<E> MerkleStorage<E> for FileStorage<E>
+where
+    E: Engine,
+{
+    fn get(&self, depth: usize, index: usize) -> Option<E::Fr> {
+        self.pending
+            .get(&(depth, index))
+            .or_else(|| self.nodes.get(&(depth, index)))
+            .cloned()
+    }
+
+    fn put(&mut self, depth: usize, index: usize, node: E::Fr) {
+        self.pending.insert((depth, index), node);
+    }
+
+    fn commit(&mut self, next_index: usize) -> Result<()> {
+        if self.pending.is_empty() && self.next_index == next_index {
+            return Ok(());
+        }
+        let mut record: Vec<u8> = Vec::new();
+        let count = self.pending.len() as u32;
+        record.write_u32::<LittleEndian>(count)?;
+        record.write_u32::<LittleEndian>(!count)?;
+        for ((depth, index), node) in self.pending.iter() {
+            record.write_u64::<LittleEndian>(*depth as u64)?;
+            record.write_u64::<LittleEndian>(*index as u64)?;
+            node.into_repr().write_le(&mut record)?;
+        }
+        record.write_u64::<LittleEndian>(next_index as u64)?;
+        let checksum = Blake2s::digest(&record);
+        record.write_all(&checksum)?;
+
+        // a single write followed by sync, a torn record fails its checksum on open
+        if let Err(e) = self
+            .file
+            .write_all(&record)
+            .and_then(|_| self.file.sync_data())
+        {
+            self.pending.clear();
+            self.file.set_len(self.committed)?;
+            self.file.seek(SeekFrom::Start(self.committed))?;
+            return Err(e.into());
+        }
+        self.committed += record.len() as u64;
+        self.nodes.extend(self.pending.drain());
+        self.next_index = next_index;
+        Ok(())
+    }
+
+    fn next_index(&self) -> usize {
+        self.next_index
+    }
+}
+
+fn invalid_data(reason: &str) -> RLNError {
+    RLNError::Serialization(io::Error::new(io::ErrorKind::InvalidData, reason))
+}
+
+// reads the record at the start of `data` as (record length, nodes, next index),
+// none if the record runs past the end of `data` or is the last one and does not match its checksum
+fn read_record<E: Engine>(
+    data: &[u8],
+) -> Result<Option<(usize, Vec<(usize, usize, E::Fr)>, usize)>> {
+    let fr_len = <E::Fr as PrimeField>::Repr::default().as_ref().len() * 8;
+    if data.len() < 8 {
+        return Ok(None);
+    }
+    let mut reader = data;
+    let count = reader.read_u32::<LittleEndian>()?;
+    if reader.read_u32::<LittleEndian>()? != !count {
+        return Err(invalid_data("corrupted record count"));
+    }
+    // the count is intact, a record running past the end of data is cut short
+    let count = count as usize;
+    let record_len = count
+        .checked_mul(16 + fr_len)
+        .and_then(|len| len.checked_add(8 + 8 + 32))
+        .ok_or_else(|| invalid_data("record length overflows"))?;
+    if data.len() < record_len {
+        return Ok(None);
+    }
+    let checksum = Blake2s::digest(&data[..record_len - 32]);
+    if checksum.as_slice() != &data[record_len - 32..record_len] {
+        if data.len() > record_len {
+            return Err(invalid_data("corrupted record before the end of storage"));
+        }
+        return Ok(None);
+    }
+
+    let mut nodes = Vec::with_capacity(count);
+    for _ in 0..count {
+        let depth = reader.read_u64::<LittleEndian>()? as usize;
+        let index = reader.read_u64::<LittleEndian>()? as usize;
+        let mut repr = <E::Fr as PrimeField>::Repr::default();
+        repr.read_le(&mut reader)?;
+        let node = E::Fr::from_repr(repr).map_err(|_| RLNError::InvalidFieldElement)?;
+        nodes.push((depth, index, node));
+    }
+    let next_index = reader.read_u64::<LittleEndian>()? as usize;
+    Ok(Some((record_len, nodes, next_index)))
+}
+
+#[test]
+fn test_file_storage() {
+    use crate::merkle::IncrementalMerkleTree;
+    use crate::poseidon::{Poseidon as Hasher, PoseidonParams};
+    use sapling_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use std::fs::{remove_file, OpenOptions};
+
+    let path = std::env::temp_dir().join(format!("rln_file_storage_{}", std::process::id()));
+    let _ = remove_file(&path);
+    let params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+    let hasher = Hasher::new(params);
+    let open = |depth| {
+        let storage = FileStorage::<Bn256>::open(&path, depth).unwrap();
+        IncrementalMerkleTree::with_storage(hasher.clone(), depth, storage)
+    };
+
+    let mut memory_tree = IncrementalMerkleTree::empty(hasher.clone(), 3);
+    {
+        let mut tree = open(3);
+        for i in 1..4 {
+            let leaf = Fr::from_str(&i.to_string()).unwrap();
+            tree.update_next(leaf).unwrap();
+            memory_tree.update_next(leaf).unwrap();
+        }
+        tree.delete(1).unwrap();
+        memory_tree.delete(1).unwrap();
+        assert_eq!(tree.get_root(), memory_tree.get_root());
+    }
+
+    // reopens at the last committed root
+    let committed_len = std::fs::metadata(&path).unwrap().len();
+    let mut tree = open(3);
+    assert_eq!(tree.get_root(), memory_tree.get_root());
+    assert_eq!(tree.current_index, 3);
+    tree.update_next(Fr::from_str("4").unwrap()).unwrap();
+    drop(tree);
+
+    // torn write of the last commit
+    let file = OpenOptions::new().write(true).open(&path).unwrap();
+    let len = file.metadata().unwrap().len();
+    file.set_len(len - 10).unwrap();
+    drop(file);
+    let tree = open(3);
+    assert_eq!(tree.get_root(), memory_tree.get_root());
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), committed_len);
+    drop(tree);
+
+    assert!(FileStorage::<Bn256>::open(&path, 4).is_err());
+
+    // corrupted record followed by more records
+    let data = std::fs::read(&path).unwrap();
+    let mut corrupted = data.clone();
+    corrupted[STORAGE_MAGIC.len() + 8 + 8] ^= 1;
+    std::fs::write(&path, &corrupted).unwrap();
+    assert!(FileStorage::<Bn256>::open(&path, 3).is_err());
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), committed_len);
+
+    // corrupted count pointing past the end of the storage
+    let mut corrupted = data.clone();
+    corrupted[STORAGE_MAGIC.len() + 8 + 1] ^= 1;
+    std::fs::write(&path, &corrupted).unwrap();
+    assert!(FileStorage::<Bn256>::open(&path, 3).is_err());
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), committed_len);
+    remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_failed_commit() {
+    use crate::merkle::IncrementalMerkleTree;
+    use crate::poseidon::{Poseidon as Hasher, PoseidonParams};
+    use sapling_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use std::fs::remove_file;
+
+    let path = std::env::temp_dir().join(format!("rln_failed_commit_{}", std::process::id()));
+    let _ = remove_file(&path);
+    drop(FileStorage::<Bn256>::open(&path, 3).unwrap());
+    let params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+    let hasher = Hasher::new(params);
+
+    // writes to a read only handle fail
+    let storage = FileStorage::<Bn256> {
+        file: File::open(&path).unwrap(),
+        nodes: HashMap::new(),
+        pending: HashMap::new(),
+        next_index: 0,
+        committed: (STORAGE_MAGIC.len() + 8) as u64,
+    };
+    let mut tree = IncrementalMerkleTree::with_storage(hasher, 3, storage);
+    let empty_root = tree.get_root();
+    let leaf = Fr::from_str("1").unwrap();
+    assert!(tree.update_next(leaf).is_err());
+    assert!(tree.append_many(&[leaf, leaf]).is_err());
+    assert_eq!(tree.get_root(), empty_root);
+    assert_eq!(tree.current_index, 0);
+    assert_eq!(tree.recent_roots(), vec![empty_root]);
+    remove_file(&path).unwrap();
+}
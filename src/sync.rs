@@ -0,0 +1,379 @@
+use crate::error::{RLNError, Result};
+use crate::merkle::IncrementalMerkleTree;
+use crate::snarkjs::fe_from_json;
+use crate::storage::MerkleStorage;
+use sapling_crypto::bellman::pairing::Engine;
+use serde_json::Value;
+use std::io::{self, BufRead};
+
+// Synchronization of the membership tree from an ordered log of membership events
+//
+// Bootstrap mode builds the tree from the whole log, consecutive registrations are
+// appended as a batch. Maintain mode applies events one by one and yields the root
+// after each block. Every registration must take the next free index and deletions
+// must target a registered index, so the synced tree matches the one that emitted the log.
+// A failed sync leaves the tree partially synced, the last block is the block of the last
+// applied event.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SyncMode {
+    Bootstrap,
+    Maintain,
+}
+
+pub enum MembershipEvent<E>
+where
+    E: Engine,
+{
+    Register { index: usize, commitment: E::Fr },
+    Delete { index: usize },
+}
+
+// event with the block it was emitted in, blocks do not decrease along the log
+pub struct LoggedEvent<E>
+where
+    E: Engine,
+{
+    pub block: u64,
+    pub event: MembershipEvent<E>,
+}
+
+pub trait EventSource<E>
+where
+    E: Engine,
+{
+    /// next event of the log, none at the end of the log
+    fn next_event(&mut self) -> Result<Option<LoggedEvent<E>>>;
+}
+
+// Event log with an event per line, commitments are decimal strings
+//
+// {"block": 7, "event": "register", "index": 0, "commitment": "1234"}
+// {"block": 8, "event": "delete", "index": 0}
+
+pub struct JsonLinesSource<R>
+where
+    R: BufRead,
+{
+    reader: R,
+    line: String,
+}
+
+impl<R> JsonLinesSource<R>
+where
+    R: BufRead,
+{
+    pub fn new(reader: R) -> JsonLinesSource<R> {
+        JsonLinesSource {
+            reader,
+            line: String::new(),
+        }
+    }
+}
+
+impl<E, R> EventSource<E> for JsonLinesSource<R>
+where
+    E: Engine,
+    R: BufRead,
+{
+    fn next_event(&mut self) -> Result<Option<LoggedEvent<E>>> {
+        // blank lines are skipped
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                return Ok(None);
+            }
+            if !self.line.trim().is_empty() {
+                break;
+            }
+        }
+        let value: Value = serde_json::from_str(&self.line).map_err(io::Error::from)?;
+        let block = value
+            .get("block")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| invalid_line("missing block"))?;
+        let index = value
+            .get("index")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| invalid_line("missing index"))? as usize;
+        let event = match value.get("event").and_then(Value::as_str) {
+            Some("register") => {
+                let commitment = value
+                    .get("commitment")
+                    .ok_or_else(|| invalid_line("missing commitment"))?;
+                MembershipEvent::Register {
+                    index,
+                    commitment: fe_from_json(commitment)?,
+                }
+            }
+            Some("delete") => MembershipEvent::Delete { index },
+            _ => return Err(invalid_line("unknown event")),
+        };
+        Ok(Some(LoggedEvent { block, event }))
+    }
+}
+
+fn invalid_line(reason: &str) -> RLNError {
+    RLNError::Serialization(io::Error::new(io::ErrorKind::InvalidData, reason))
+}
+
+pub struct TreeSync {
+    mode: SyncMode,
+    last_block: Option<u64>,
+}
+
+impl TreeSync {
+    pub fn new(mode: SyncMode) -> TreeSync {
+        TreeSync {
+            mode,
+            last_block: None,
+        }
+    }
+
+    pub fn mode(&self) -> SyncMode {
+        self.mode
+    }
+
+    /// last block of the applied events
+    pub fn last_block(&self) -> Option<u64> {
+        self.last_block
+    }
+
+    /// applies the events of the source to the tree
+    /// * returns (block, root) after each block, bootstrap returns the root after the last block only
+    /// and switches to maintain mode
+    pub fn sync<E, S, Src>(
+        &mut self,
+        tree: &mut IncrementalMerkleTree<E, S>,
+        source: &mut Src,
+    ) -> Result<Vec<(u64, E::Fr)>>
+    where
+        E: Engine,
+        S: MerkleStorage<E>,
+        Src: EventSource<E>,
+    {
+        match self.mode {
+            SyncMode::Bootstrap => {
+                let roots = self.bootstrap(tree, source)?;
+                self.mode = SyncMode::Maintain;
+                Ok(roots)
+            }
+            SyncMode::Maintain => self.maintain(tree, source),
+        }
+    }
+
+    fn bootstrap<E, S, Src>(
+        &mut self,
+        tree: &mut IncrementalMerkleTree<E, S>,
+        source: &mut Src,
+    ) -> Result<Vec<(u64, E::Fr)>>
+    where
+        E: Engine,
+        S: MerkleStorage<E>,
+        Src: EventSource<E>,
+    {
+        let mut pending: Vec<E::Fr> = Vec::new();
+        // block of the last pending registration, synced once the batch is appended
+        let mut pending_block: Option<u64> = None;
+        while let Some(logged) = source.next_event()? {
+            check_block(pending_block.or(self.last_block), logged.block)?;
+            match logged.event {
+                MembershipEvent::Register { index, commitment } => {
+                    if index != tree.current_index + pending.len() {
+                        return Err(RLNError::InvalidEvent(
+                            "registration is not at the next index",
+                        ));
+                    }
+                    pending.push(commitment);
+                    pending_block = Some(logged.block);
+                }
+                MembershipEvent::Delete { index } => {
+                    self.append_pending(tree, &mut pending, &mut pending_block)?;
+                    delete(tree, index)?;
+                    self.last_block = Some(logged.block);
+                }
+            }
+        }
+        self.append_pending(tree, &mut pending, &mut pending_block)?;
+        Ok(self
+            .last_block
+            .map(|block| vec![(block, tree.get_root())])
+            .unwrap_or_default())
+    }
+
+    fn maintain<E, S, Src>(
+        &mut self,
+        tree: &mut IncrementalMerkleTree<E, S>,
+        source: &mut Src,
+    ) -> Result<Vec<(u64, E::Fr)>>
+    where
+        E: Engine,
+        S: MerkleStorage<E>,
+        Src: EventSource<E>,
+    {
+        let mut roots: Vec<(u64, E::Fr)> = Vec::new();
+        let mut block: Option<u64> = None;
+        while let Some(logged) = source.next_event()? {
+            check_block(self.last_block, logged.block)?;
+            match block {
+                Some(block) if block != logged.block => roots.push((block, tree.get_root())),
+                _ => (),
+            }
+            block = Some(logged.block);
+            match logged.event {
+                MembershipEvent::Register { index, commitment } => {
+                    if index != tree.current_index {
+                        return Err(RLNError::InvalidEvent(
+                            "registration is not at the next index",
+                        ));
+                    }
+                    tree.update_next(commitment)?;
+                }
+                MembershipEvent::Delete { index } => delete(tree, index)?,
+            }
+            self.last_block = Some(logged.block);
+        }
+        if let Some(block) = block {
+            roots.push((block, tree.get_root()));
+        }
+        Ok(roots)
+    }
+
+    // appends the pending registrations and syncs the block of the last one
+    fn append_pending<E, S>(
+        &mut self,
+        tree: &mut IncrementalMerkleTree<E, S>,
+        pending: &mut Vec<E::Fr>,
+        pending_block: &mut Option<u64>,
+    ) -> Result<()>
+    where
+        E: Engine,
+        S: MerkleStorage<E>,
+    {
+        if let Some(block) = pending_block.take() {
+            tree.append_many(&pending[..])?;
+            pending.clear();
+            self.last_block = Some(block);
+        }
+        Ok(())
+    }
+}
+
+// blocks do not decrease along the log
+fn check_block(last_block: Option<u64>, block: u64) -> Result<()> {
+    if last_block.map_or(false, |last| block < last) {
+        return Err(RLNError::InvalidEvent(
+            "block is before the last synced block",
+        ));
+    }
+    Ok(())
+}
+
+fn delete<E, S>(tree: &mut IncrementalMerkleTree<E, S>, index: usize) -> Result<()>
+where
+    E: Engine,
+    S: MerkleStorage<E>,
+{
+    if index >= tree.current_index {
+        return Err(RLNError::InvalidEvent("deleted index was never registered"));
+    }
+    tree.delete(index)
+}
+
+#[test]
+fn test_tree_sync() {
+    use crate::poseidon::{Poseidon as Hasher, PoseidonParams};
+    use sapling_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use sapling_crypto::bellman::pairing::ff::PrimeField;
+
+    let params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+    let hasher = Hasher::new(params);
+    let log = "{\"block\": 1, \"event\": \"register\", \"index\": 0, \"commitment\": \"11\"}
+{\"block\": 1, \"event\": \"register\", \"index\": 1, \"commitment\": \"12\"}
+
+{\"block\": 2, \"event\": \"delete\", \"index\": 0}
+{\"block\": 2, \"event\": \"register\", \"index\": 2, \"commitment\": \"13\"}
+{\"block\": 4, \"event\": \"register\", \"index\": 3, \"commitment\": \"14\"}
+";
+
+    let mut expected = IncrementalMerkleTree::empty(hasher.clone(), 3);
+    let mut block_roots = Vec::new();
+    for commitment in ["11", "12"].iter() {
+        expected
+            .update_next(Fr::from_str(commitment).unwrap())
+            .unwrap();
+    }
+    block_roots.push((1, expected.get_root()));
+    expected.delete(0).unwrap();
+    expected.update_next(Fr::from_str("13").unwrap()).unwrap();
+    block_roots.push((2, expected.get_root()));
+    expected.update_next(Fr::from_str("14").unwrap()).unwrap();
+    block_roots.push((4, expected.get_root()));
+
+    let mut tree = IncrementalMerkleTree::empty(hasher.clone(), 3);
+    let mut sync = TreeSync::new(SyncMode::Bootstrap);
+    let roots = sync
+        .sync(&mut tree, &mut JsonLinesSource::new(log.as_bytes()))
+        .unwrap();
+    assert_eq!(roots, vec![(4, expected.get_root())]);
+    assert_eq!(sync.mode(), SyncMode::Maintain);
+    assert_eq!(tree.current_index, 4);
+
+    let mut tree = IncrementalMerkleTree::empty(hasher.clone(), 3);
+    let mut sync = TreeSync::new(SyncMode::Maintain);
+    let roots = sync
+        .sync(&mut tree, &mut JsonLinesSource::new(log.as_bytes()))
+        .unwrap();
+    assert_eq!(roots, block_roots);
+    assert_eq!(sync.last_block(), Some(4));
+
+    // continues from the synced tree
+    let next = "{\"block\": 5, \"event\": \"register\", \"index\": 4, \"commitment\": \"15\"}";
+    let roots = sync
+        .sync(&mut tree, &mut JsonLinesSource::new(next.as_bytes()))
+        .unwrap();
+    expected.update_next(Fr::from_str("15").unwrap()).unwrap();
+    assert_eq!(roots, vec![(5, expected.get_root())]);
+
+    let invalid = [
+        "{\"block\": 6, \"event\": \"register\", \"index\": 7, \"commitment\": \"16\"}",
+        "{\"block\": 6, \"event\": \"delete\", \"index\": 5}",
+        "{\"block\": 3, \"event\": \"delete\", \"index\": 1}",
+    ];
+    for line in invalid.iter() {
+        match sync.sync(&mut tree, &mut JsonLinesSource::new(line.as_bytes())) {
+            Err(RLNError::InvalidEvent(_)) => (),
+            _ => panic!("expected invalid event"),
+        }
+    }
+    let malformed = "{\"block\": 6, \"event\": \"rename\", \"index\": 0}";
+    assert!(sync
+        .sync(&mut tree, &mut JsonLinesSource::new(malformed.as_bytes()))
+        .is_err());
+    assert_eq!(tree.get_root(), expected.get_root());
+
+    // rejected events do not advance the synced block
+    assert_eq!(sync.last_block(), Some(5));
+    let late = "{\"block\": 9, \"event\": \"delete\", \"index\": 9}";
+    assert!(sync
+        .sync(&mut tree, &mut JsonLinesSource::new(late.as_bytes()))
+        .is_err());
+    let retry = "{\"block\": 6, \"event\": \"register\", \"index\": 5, \"commitment\": \"16\"}";
+    let roots = sync
+        .sync(&mut tree, &mut JsonLinesSource::new(retry.as_bytes()))
+        .unwrap();
+    expected.update_next(Fr::from_str("16").unwrap()).unwrap();
+    assert_eq!(roots, vec![(6, expected.get_root())]);
+
+    // registrations before a rejected deletion are synced in bootstrap mode
+    let mut tree = IncrementalMerkleTree::empty(hasher.clone(), 3);
+    let mut sync = TreeSync::new(SyncMode::Bootstrap);
+    let rejected = "{\"block\": 1, \"event\": \"register\", \"index\": 0, \"commitment\": \"11\"}
+{\"block\": 2, \"event\": \"delete\", \"index\": 5}
+";
+    assert!(sync
+        .sync(&mut tree, &mut JsonLinesSource::new(rejected.as_bytes()))
+        .is_err());
+    assert_eq!(tree.current_index, 1);
+    assert_eq!(sync.last_block(), Some(1));
+}
@@ -4,9 +4,10 @@ use bellman::pairing::{CurveAffine, EncodedPoint, Engine};
 use byteorder::{LittleEndian, ReadBytesExt};
 use std::io::{self, Error, ErrorKind, Read, Write};
 
+use crate::error::{RLNError, Result};
 use crate::hash_to_field::hash_to_field;
 
-pub fn read_signal_hash<R: Read, E: Engine>(mut reader: R) -> io::Result<E::Fr> {
+pub fn read_signal_hash<R: Read, E: Engine>(mut reader: R) -> Result<E::Fr> {
     let n = reader.read_u64::<LittleEndian>()?;
     let mut buf: Vec<u8> = vec![0; n as usize];
     reader.read(&mut buf[..])?;
@@ -14,76 +15,157 @@ pub fn read_signal_hash<R: Read, E: Engine>(mut reader: R) -> io::Result<E::Fr>
     Ok(hash_to_field::<E>(&buf[..]))
 }
 
-pub fn read_fr<R: Read, E: Engine>(mut reader: R, n: usize) -> io::Result<Vec<E::Fr>> {
+// reads [ signal_len<8> | signal<var> ], the length is untrusted so the signal is read as far as it goes
+pub fn read_signal<R: Read>(mut reader: R) -> Result<Vec<u8>> {
+    let signal_len = reader.read_u64::<LittleEndian>()?;
+    let mut signal: Vec<u8> = Vec::new();
+    (&mut reader).take(signal_len).read_to_end(&mut signal)?;
+    if signal.len() as u64 != signal_len {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "truncated signal").into());
+    }
+    Ok(signal)
+}
+
+pub fn read_fr<R: Read, E: Engine>(mut reader: R, n: usize) -> Result<Vec<E::Fr>> {
     let mut out: Vec<E::Fr> = Vec::new();
     let mut buf = <E::Fr as PrimeField>::Repr::default();
     for _ in 0..n {
         buf.read_le(&mut reader)?;
-        let input =
-            E::Fr::from_repr(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let input = E::Fr::from_repr(buf).map_err(|_| RLNError::InvalidFieldElement)?;
         out.push(input);
     }
     Ok(out)
 }
 
-pub fn write_uncompressed_proof<W: Write, E: Engine>(
-    proof: Proof<E>,
-    mut writer: W,
-) -> io::Result<()> {
+pub fn fr_from_u64<E: Engine>(value: u64) -> E::Fr {
+    E::Fr::from_repr(<E::Fr as PrimeField>::Repr::from(value)).unwrap()
+}
+
+// encoding of a proof, the tag is written as the leading byte of a tagged proof
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProofFormat {
+    // [ a<64> | b<128> | c<64> ]
+    Uncompressed = 0,
+    // [ a<32> | b<64> | c<32> ]
+    Compressed = 1,
+}
+
+impl ProofFormat {
+    pub fn from_tag(tag: u8) -> Result<ProofFormat> {
+        match tag {
+            0 => Ok(ProofFormat::Uncompressed),
+            1 => Ok(ProofFormat::Compressed),
+            tag => Err(RLNError::InvalidProofFormat(tag)),
+        }
+    }
+
+    pub fn tag(self) -> u8 {
+        self as u8
+    }
+}
+
+pub fn write_uncompressed_proof<W: Write, E: Engine>(proof: Proof<E>, mut writer: W) -> Result<()> {
     writer.write_all(proof.a.into_uncompressed().as_ref())?;
     writer.write_all(proof.b.into_uncompressed().as_ref())?;
     writer.write_all(proof.c.into_uncompressed().as_ref())?;
     Ok(())
 }
 
-pub fn read_uncompressed_proof<R: Read, E: Engine>(mut reader: R) -> io::Result<Proof<E>> {
+pub fn read_uncompressed_proof<R: Read, E: Engine>(mut reader: R) -> Result<Proof<E>> {
     let mut g1_repr = <E::G1Affine as CurveAffine>::Uncompressed::empty();
     let mut g2_repr = <E::G2Affine as CurveAffine>::Uncompressed::empty();
 
     reader.read_exact(g1_repr.as_mut())?;
-    let a = g1_repr
-        .into_affine()
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
-        .and_then(|e| {
-            if e.is_zero() {
-                Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    "point at infinity",
-                ))
-            } else {
-                Ok(e)
-            }
-        })?;
-
+    let a = decode_point(&g1_repr)?;
     reader.read_exact(g2_repr.as_mut())?;
-    let b = g2_repr
-        .into_affine()
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
-        .and_then(|e| {
-            if e.is_zero() {
-                Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    "point at infinity",
-                ))
-            } else {
-                Ok(e)
-            }
-        })?;
+    let b = decode_point(&g2_repr)?;
+    reader.read_exact(g1_repr.as_mut())?;
+    let c = decode_point(&g1_repr)?;
+
+    Ok(Proof { a, b, c })
+}
+
+pub fn write_compressed_proof<W: Write, E: Engine>(proof: Proof<E>, mut writer: W) -> Result<()> {
+    writer.write_all(proof.a.into_compressed().as_ref())?;
+    writer.write_all(proof.b.into_compressed().as_ref())?;
+    writer.write_all(proof.c.into_compressed().as_ref())?;
+    Ok(())
+}
+
+pub fn read_compressed_proof<R: Read, E: Engine>(mut reader: R) -> Result<Proof<E>> {
+    let mut g1_repr = <E::G1Affine as CurveAffine>::Compressed::empty();
+    let mut g2_repr = <E::G2Affine as CurveAffine>::Compressed::empty();
 
     reader.read_exact(g1_repr.as_mut())?;
-    let c = g1_repr
-        .into_affine()
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
-        .and_then(|e| {
-            if e.is_zero() {
-                Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    "point at infinity",
-                ))
-            } else {
-                Ok(e)
-            }
-        })?;
+    let a = decode_point(&g1_repr)?;
+    reader.read_exact(g2_repr.as_mut())?;
+    let b = decode_point(&g2_repr)?;
+    reader.read_exact(g1_repr.as_mut())?;
+    let c = decode_point(&g1_repr)?;
 
     Ok(Proof { a, b, c })
 }
+
+/// writes [ format<1> | proof<var> ]
+pub fn write_tagged_proof<W: Write, E: Engine>(
+    proof: Proof<E>,
+    format: ProofFormat,
+    mut writer: W,
+) -> Result<()> {
+    writer.write_all(&[format.tag()])?;
+    match format {
+        ProofFormat::Uncompressed => write_uncompressed_proof(proof, writer),
+        ProofFormat::Compressed => write_compressed_proof(proof, writer),
+    }
+}
+
+/// reads [ format<1> | proof<var> ] in either format
+pub fn read_tagged_proof<R: Read, E: Engine>(mut reader: R) -> Result<Proof<E>> {
+    let mut tag = [0u8; 1];
+    reader.read_exact(&mut tag)?;
+    match ProofFormat::from_tag(tag[0])? {
+        ProofFormat::Uncompressed => read_uncompressed_proof(reader),
+        ProofFormat::Compressed => read_compressed_proof(reader),
+    }
+}
+
+// decodes a point checking it is on the curve and not the point at infinity
+fn decode_point<G: EncodedPoint>(repr: &G) -> Result<G::Affine> {
+    let point = repr
+        .into_affine()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    if point.is_zero() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "point at infinity").into());
+    }
+    Ok(point)
+}
+
+#[test]
+fn test_tagged_proof() {
+    use bellman::pairing::bn256::{Bn256, G1, G2};
+    use bellman::pairing::CurveProjective;
+    use rand::{thread_rng, Rand};
+
+    let mut rng = thread_rng();
+    let proof = Proof::<Bn256> {
+        a: G1::rand(&mut rng).into_affine(),
+        b: G2::rand(&mut rng).into_affine(),
+        c: G1::rand(&mut rng).into_affine(),
+    };
+
+    let mut compressed: Vec<u8> = Vec::new();
+    write_tagged_proof(proof.clone(), ProofFormat::Compressed, &mut compressed).unwrap();
+    assert_eq!(compressed.len(), 1 + 128);
+    let mut uncompressed: Vec<u8> = Vec::new();
+    write_tagged_proof(proof.clone(), ProofFormat::Uncompressed, &mut uncompressed).unwrap();
+    assert_eq!(uncompressed.len(), 1 + 256);
+
+    assert!(read_tagged_proof::<_, Bn256>(compressed.as_slice()).unwrap() == proof);
+    assert!(read_tagged_proof::<_, Bn256>(uncompressed.as_slice()).unwrap() == proof);
+
+    compressed[0] = 2;
+    match read_tagged_proof::<_, Bn256>(compressed.as_slice()) {
+        Err(RLNError::InvalidProofFormat(2)) => (),
+        _ => panic!("expected invalid proof format"),
+    }
+}
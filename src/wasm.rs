@@ -1,4 +1,5 @@
-use crate::public::RLN;
+use crate::error::RLNError;
+use crate::public::{ParametersCheck, ProofFormat, RLNVerifier, Semaphore, RLN};
 
 use std::io::{self, Error, ErrorKind, Read, Write};
 use wasm_bindgen::prelude::*;
@@ -17,6 +18,13 @@ pub fn set_panic_hook() {
     console_error_panic_hook::set_once();
 }
 
+// maps the error to a js Error with the numeric `code` property of the error
+fn js_error(e: RLNError) -> JsValue {
+    let error = js_sys::Error::new(&e.to_string());
+    let _ = js_sys::Reflect::set(&error, &JsValue::from("code"), &JsValue::from(e.code()));
+    error.into()
+}
+
 #[wasm_bindgen]
 pub struct RLNWasm {
     api: RLN<Bn256>,
@@ -25,11 +33,13 @@ pub struct RLNWasm {
 #[wasm_bindgen]
 impl RLNWasm {
     #[wasm_bindgen]
-    pub fn new(merkle_depth: usize) -> RLNWasm {
+    pub fn new(merkle_depth: usize) -> Result<RLNWasm, JsValue> {
         set_panic_hook();
-        RLNWasm {
-            api: RLN::<Bn256>::new(merkle_depth, None),
-        }
+        let api = match RLN::<Bn256>::new(merkle_depth, None) {
+            Ok(api) => api,
+            Err(e) => return Err(js_error(e)),
+        };
+        Ok(RLNWasm { api })
     }
 
     #[wasm_bindgen]
@@ -40,17 +50,220 @@ impl RLNWasm {
         set_panic_hook();
         let api = match RLN::new_with_raw_params(merkle_depth, raw_circuit_parameters, None) {
             Ok(api) => api,
-            Err(e) => return Err(e.to_string().into()),
+            Err(e) => return Err(js_error(e)),
+        };
+        Ok(RLNWasm { api })
+    }
+
+    /// creates an instance from parameters matching the pinned digest, points are not checked
+    /// * `share_degree` is the number of signals per epoch the parameters were generated for
+    #[wasm_bindgen]
+    pub fn new_with_pinned_params(
+        merkle_depth: usize,
+        share_degree: usize,
+        raw_circuit_parameters: &[u8],
+        digest: &[u8],
+    ) -> Result<RLNWasm, JsValue> {
+        set_panic_hook();
+        if digest.len() != 32 {
+            return Err(js_error(RLNError::InvalidParameters(
+                "pinned digest must be 32 bytes",
+            )));
+        }
+        let mut pinned = [0u8; 32];
+        pinned.copy_from_slice(digest);
+        let api = match RLN::new_with_checked_params(
+            merkle_depth,
+            share_degree,
+            raw_circuit_parameters,
+            None,
+            ParametersCheck::PinnedDigest(pinned),
+        ) {
+            Ok(api) => api,
+            Err(e) => return Err(js_error(e)),
         };
         Ok(RLNWasm { api })
     }
 
+    #[wasm_bindgen]
+    pub fn circuit_parameters_digest(&self) -> Result<Vec<u8>, JsValue> {
+        match self.api.circuit_parameters_digest() {
+            Ok(digest) => Ok(digest.to_vec()),
+            Err(e) => Err(js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn find_member_index(&self, id_commitment: &[u8]) -> Result<usize, JsValue> {
+        match self.api.find_member_index(id_commitment) {
+            Ok(index) => Ok(index),
+            Err(e) => Err(js_error(e)),
+        }
+    }
+
+    /// sets the number of most recent membership roots that proofs are accepted against
+    #[wasm_bindgen]
+    pub fn set_root_window(&mut self, size: usize) {
+        self.api.set_root_window(size)
+    }
+
+    #[wasm_bindgen]
+    pub fn generate_proof(&self, input: &[u8]) -> Result<Vec<u8>, JsValue> {
+        let mut proof: Vec<u8> = Vec::new();
+        let proof = match self.api.generate_proof(input, &mut proof) {
+            Ok(()) => proof,
+            Err(e) => return Err(js_error(e)),
+        };
+        Ok(proof)
+    }
+
+    #[wasm_bindgen]
+    pub fn verify(&self, uncompresed_proof: &[u8]) -> Result<bool, JsValue> {
+        let success = match self.api.verify(uncompresed_proof) {
+            Ok(success) => success,
+            Err(e) => return Err(js_error(e)),
+        };
+        Ok(success)
+    }
+
+    #[wasm_bindgen]
+    pub fn verify_tagged(&self, tagged_proof: &[u8]) -> Result<bool, JsValue> {
+        let success = match self.api.verify_tagged(tagged_proof) {
+            Ok(success) => success,
+            Err(e) => return Err(js_error(e)),
+        };
+        Ok(success)
+    }
+
+    /// * `format` is 0 for uncompressed and 1 for compressed proof
+    #[wasm_bindgen]
+    pub fn generate_tagged_proof(&self, input: &[u8], format: u8) -> Result<Vec<u8>, JsValue> {
+        let format = match ProofFormat::from_tag(format) {
+            Ok(format) => format,
+            Err(e) => return Err(js_error(e)),
+        };
+        let mut proof: Vec<u8> = Vec::new();
+        let proof = match self.api.generate_tagged_proof(input, format, &mut proof) {
+            Ok(()) => proof,
+            Err(e) => return Err(js_error(e)),
+        };
+        Ok(proof)
+    }
+
+    /// returns a byte per proof, 0 if verified and 1 otherwise as in the ffi bindings
+    #[wasm_bindgen]
+    pub fn verify_batch(&self, uncompresed_proofs: &[u8]) -> Result<Vec<u8>, JsValue> {
+        let results = match self.api.verify_batch(uncompresed_proofs) {
+            Ok(results) => results,
+            Err(e) => return Err(js_error(e)),
+        };
+        Ok(results
+            .into_iter()
+            .map(|verified| if verified { 0 } else { 1 })
+            .collect())
+    }
+
+    #[wasm_bindgen]
+    pub fn export_verifier_key(&self) -> Result<Vec<u8>, JsValue> {
+        let mut output: Vec<u8> = Vec::new();
+        match self.api.export_verifier_key(&mut output) {
+            Ok(_) => (),
+            Err(e) => return Err(js_error(e)),
+        };
+        Ok(output)
+    }
+
+    #[wasm_bindgen]
+    pub fn export_tree(&self) -> Result<Vec<u8>, JsValue> {
+        let mut output: Vec<u8> = Vec::new();
+        match self.api.export_tree(&mut output) {
+            Ok(_) => (),
+            Err(e) => return Err(js_error(e)),
+        };
+        Ok(output)
+    }
+
+    #[wasm_bindgen]
+    pub fn import_tree(&mut self, snapshot: &[u8]) -> Result<(), JsValue> {
+        match self.api.import_tree(snapshot) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn export_circuit_parameters(&self) -> Result<Vec<u8>, JsValue> {
+        let mut output: Vec<u8> = Vec::new();
+        match self.api.export_circuit_parameters(&mut output) {
+            Ok(_) => (),
+            Err(e) => return Err(js_error(e)),
+        };
+        Ok(output)
+    }
+}
+
+#[wasm_bindgen]
+pub struct SemaphoreWasm {
+    api: Semaphore<Bn256>,
+}
+
+#[wasm_bindgen]
+impl SemaphoreWasm {
+    #[wasm_bindgen]
+    pub fn new(merkle_depth: usize) -> Result<SemaphoreWasm, JsValue> {
+        set_panic_hook();
+        let api = match Semaphore::<Bn256>::new(merkle_depth, None) {
+            Ok(api) => api,
+            Err(e) => return Err(js_error(e)),
+        };
+        Ok(SemaphoreWasm { api })
+    }
+
+    #[wasm_bindgen]
+    pub fn new_with_raw_params(
+        merkle_depth: usize,
+        raw_circuit_parameters: &[u8],
+    ) -> Result<SemaphoreWasm, JsValue> {
+        set_panic_hook();
+        let api = match Semaphore::new_with_raw_params(merkle_depth, raw_circuit_parameters, None) {
+            Ok(api) => api,
+            Err(e) => return Err(js_error(e)),
+        };
+        Ok(SemaphoreWasm { api })
+    }
+
+    #[wasm_bindgen]
+    pub fn update_next_member(&mut self, id_commitment: &[u8]) -> Result<(), JsValue> {
+        match self.api.update_next_member(id_commitment) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(js_error(e)),
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn get_root(&self) -> Result<Vec<u8>, JsValue> {
+        let mut output: Vec<u8> = Vec::new();
+        match self.api.get_root(&mut output) {
+            Ok(_) => (),
+            Err(e) => return Err(js_error(e)),
+        };
+        Ok(output)
+    }
+
+    #[wasm_bindgen]
+    pub fn find_member_index(&self, id_commitment: &[u8]) -> Result<usize, JsValue> {
+        match self.api.find_member_index(id_commitment) {
+            Ok(index) => Ok(index),
+            Err(e) => Err(js_error(e)),
+        }
+    }
+
     #[wasm_bindgen]
     pub fn generate_proof(&self, input: &[u8]) -> Result<Vec<u8>, JsValue> {
         let mut proof: Vec<u8> = Vec::new();
         let proof = match self.api.generate_proof(input, &mut proof) {
             Ok(()) => proof,
-            Err(e) => return Err(e.to_string().into()),
+            Err(e) => return Err(js_error(e)),
         };
         Ok(proof)
     }
@@ -59,7 +272,7 @@ impl RLNWasm {
     pub fn verify(&self, uncompresed_proof: &[u8]) -> Result<bool, JsValue> {
         let success = match self.api.verify(uncompresed_proof) {
             Ok(success) => success,
-            Err(e) => return Err(e.to_string().into()),
+            Err(e) => return Err(js_error(e)),
         };
         Ok(success)
     }
@@ -69,7 +282,7 @@ impl RLNWasm {
         let mut output: Vec<u8> = Vec::new();
         match self.api.export_verifier_key(&mut output) {
             Ok(_) => (),
-            Err(e) => return Err(e.to_string().into()),
+            Err(e) => return Err(js_error(e)),
         };
         Ok(output)
     }
@@ -79,12 +292,61 @@ impl RLNWasm {
         let mut output: Vec<u8> = Vec::new();
         match self.api.export_circuit_parameters(&mut output) {
             Ok(_) => (),
-            Err(e) => return Err(e.to_string().into()),
+            Err(e) => return Err(js_error(e)),
         };
         Ok(output)
     }
 }
 
+#[wasm_bindgen]
+pub struct RLNVerifierWasm {
+    api: RLNVerifier<Bn256>,
+}
+
+#[wasm_bindgen]
+impl RLNVerifierWasm {
+    #[wasm_bindgen]
+    pub fn new_with_raw_verifier_key(raw_verifier_key: &[u8]) -> Result<RLNVerifierWasm, JsValue> {
+        set_panic_hook();
+        let api = match RLNVerifier::new_with_raw_verifier_key(raw_verifier_key) {
+            Ok(api) => api,
+            Err(e) => return Err(js_error(e)),
+        };
+        Ok(RLNVerifierWasm { api })
+    }
+
+    #[wasm_bindgen]
+    pub fn verify(&self, uncompresed_proof: &[u8]) -> Result<bool, JsValue> {
+        let success = match self.api.verify(uncompresed_proof) {
+            Ok(success) => success,
+            Err(e) => return Err(js_error(e)),
+        };
+        Ok(success)
+    }
+
+    #[wasm_bindgen]
+    pub fn verify_tagged(&self, tagged_proof: &[u8]) -> Result<bool, JsValue> {
+        let success = match self.api.verify_tagged(tagged_proof) {
+            Ok(success) => success,
+            Err(e) => return Err(js_error(e)),
+        };
+        Ok(success)
+    }
+
+    /// returns a byte per proof, 0 if verified and 1 otherwise as in the ffi bindings
+    #[wasm_bindgen]
+    pub fn verify_batch(&self, uncompresed_proofs: &[u8]) -> Result<Vec<u8>, JsValue> {
+        let results = match self.api.verify_batch(uncompresed_proofs) {
+            Ok(results) => results,
+            Err(e) => return Err(js_error(e)),
+        };
+        Ok(results
+            .into_iter()
+            .map(|verified| if verified { 0 } else { 1 })
+            .collect())
+    }
+}
+
 #[cfg(test)]
 mod test {
 
@@ -98,7 +360,9 @@ mod test {
     use bellman::groth16::{generate_random_parameters, Parameters, Proof};
     use bellman::pairing::bn256::{Bn256, Fr};
     use bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
+    use byteorder::{LittleEndian, WriteBytesExt};
     use rand::{Rand, SeedableRng, XorShiftRng};
+    use std::io::Write;
 
     #[wasm_bindgen_test]
     fn test_rln_wasm() {
@@ -106,7 +370,7 @@ mod test {
         let poseidon_params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
         let rln_test = bench::RLNTest::<Bn256>::new(merkle_depth, Some(poseidon_params));
 
-        let rln_wasm = super::RLNWasm::new(merkle_depth);
+        let rln_wasm = super::RLNWasm::new(merkle_depth).unwrap();
 
         let mut raw_inputs: Vec<u8> = Vec::new();
         let inputs = rln_test.valid_inputs();
@@ -121,4 +385,109 @@ mod test {
 
         assert_eq!(rln_wasm.verify(proof.as_slice()).unwrap(), true);
     }
+
+    #[wasm_bindgen_test]
+    fn test_verify_batch_wasm() {
+        let merkle_depth = 3usize;
+        let poseidon_params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+        let rln_test = bench::RLNTest::<Bn256>::new(merkle_depth, Some(poseidon_params));
+        let mut verifier_key: Vec<u8> = Vec::new();
+        rln_test.rln.export_verifier_key(&mut verifier_key).unwrap();
+        let verifier = super::RLNVerifierWasm::new_with_raw_verifier_key(&verifier_key).unwrap();
+
+        // the test member is inserted at index 0 with secret 1001
+        let signal = b"rln signal test xyz abc";
+        let mut input_data: Vec<u8> = Vec::new();
+        Fr::from_str("1001")
+            .unwrap()
+            .into_repr()
+            .write_le(&mut input_data)
+            .unwrap();
+        input_data.write_u64::<LittleEndian>(0).unwrap();
+        Fr::from_str("7")
+            .unwrap()
+            .into_repr()
+            .write_le(&mut input_data)
+            .unwrap();
+        input_data
+            .write_u64::<LittleEndian>(signal.len() as u64)
+            .unwrap();
+        input_data.write(&signal[..]).unwrap();
+        let mut proof_data: Vec<u8> = Vec::new();
+        rln_test
+            .rln
+            .generate_proof(input_data.as_slice(), &mut proof_data)
+            .unwrap();
+        proof_data
+            .write_u64::<LittleEndian>(signal.len() as u64)
+            .unwrap();
+        proof_data.write(&signal[..]).unwrap();
+
+        // valid proof and a proof with tampered share y, 0 is verified as in the ffi bindings
+        let mut tampered = proof_data.clone();
+        tampered[256 + 32 * 3] ^= 1;
+        let mut batch_data: Vec<u8> = Vec::new();
+        batch_data.write_u64::<LittleEndian>(2).unwrap();
+        batch_data.write(&proof_data[..]).unwrap();
+        batch_data.write(&tampered[..]).unwrap();
+        assert_eq!(verifier.verify_batch(&batch_data).unwrap(), vec![0u8, 1u8]);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_tagged_proof_wasm() {
+        let merkle_depth = 3usize;
+        let poseidon_params = PoseidonParams::<Bn256>::new(8, 55, 3, None, None, None);
+        let rln_test = bench::RLNTest::<Bn256>::new(merkle_depth, Some(poseidon_params));
+        let mut circuit_parameters: Vec<u8> = Vec::new();
+        rln_test
+            .rln
+            .export_circuit_parameters(&mut circuit_parameters)
+            .unwrap();
+        let mut snapshot: Vec<u8> = Vec::new();
+        rln_test.rln.export_tree(&mut snapshot).unwrap();
+        let mut rln_wasm =
+            super::RLNWasm::new_with_raw_params(merkle_depth, &circuit_parameters).unwrap();
+        rln_wasm.import_tree(&snapshot).unwrap();
+        let verifier = super::RLNVerifierWasm::new_with_raw_verifier_key(
+            &rln_wasm.export_verifier_key().unwrap(),
+        )
+        .unwrap();
+
+        // the test member is inserted at index 0 with secret 1001
+        let signal = b"rln signal test xyz abc";
+        let mut input_data: Vec<u8> = Vec::new();
+        Fr::from_str("1001")
+            .unwrap()
+            .into_repr()
+            .write_le(&mut input_data)
+            .unwrap();
+        input_data.write_u64::<LittleEndian>(0).unwrap();
+        Fr::from_str("7")
+            .unwrap()
+            .into_repr()
+            .write_le(&mut input_data)
+            .unwrap();
+        input_data
+            .write_u64::<LittleEndian>(signal.len() as u64)
+            .unwrap();
+        input_data.write(&signal[..]).unwrap();
+
+        for (tag, proof_len) in [(0u8, 256usize), (1u8, 128usize)].iter() {
+            let mut proof_data = rln_wasm.generate_tagged_proof(&input_data, *tag).unwrap();
+            assert_eq!(proof_data[0], *tag);
+            assert_eq!(proof_data.len(), 1 + proof_len + 32 * 5);
+            proof_data
+                .write_u64::<LittleEndian>(signal.len() as u64)
+                .unwrap();
+            proof_data.write(&signal[..]).unwrap();
+            assert!(rln_wasm.verify_tagged(&proof_data).unwrap());
+            assert!(verifier.verify_tagged(&proof_data).unwrap());
+
+            // tampered share y
+            proof_data[1 + proof_len + 32 * 3] ^= 1;
+            assert!(!rln_wasm.verify_tagged(&proof_data).unwrap());
+            assert!(!verifier.verify_tagged(&proof_data).unwrap());
+        }
+        assert!(rln_wasm.generate_tagged_proof(&input_data, 2).is_err());
+    }
 }